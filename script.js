@@ -23,6 +23,20 @@ async function run() {
 
     canvas = new Canvas();
     canvas.draw();
+    sync_panel();
+
+    // keep the panel in step with the canvas, whatever changed it
+    {
+        let control_panel = document.getElementById("control_panel");
+        control_panel.addEventListener("input", (e) => {
+            panel_input_target = e.target;
+        }, true);
+        control_panel.addEventListener("input", (e) => {
+            panel_input_target = null;
+        });
+
+        canvas.add_change_listener(on_canvas_change);
+    }
 
     // change number of points
     {
@@ -37,7 +51,7 @@ async function run() {
             if (val < 1) {
                 val = 1;
             }
-            canvas.set_points(val);
+            apply_input(points_input, () => canvas.set_points(val));
         });
 
         // by mouse wheel
@@ -46,15 +60,15 @@ async function run() {
             const step = parseFloat(points_input.step) || 1;
             const current = parseFloat(points_input.value) || 0;
             if (e.deltaY < 0) {
-                if (current + step >= 0) {
+                if (current + step >= 1) {
                     points_input.value = current + step;
                 }
             } else {
-                if (current - step >= 0) {
+                if (current - step >= 1) {
                     points_input.value = current - step;
                 }
             }
-            canvas.set_points(points_input.value);
+            apply_input(points_input, () => canvas.set_points(points_input.value));
         });
     }
 
@@ -71,7 +85,7 @@ async function run() {
             if (val < 1) {
                 val = 0;
             }
-            canvas.set_multiplier(val);
+            apply_input(mul_input, () => canvas.set_multiplier(val));
         });
 
         // by mouse wheel
@@ -86,32 +100,55 @@ async function run() {
                     mul_input.value = current - step;
                 }
             }
-            canvas.set_multiplier(mul_input.value);
+            apply_input(mul_input, () => canvas.set_multiplier(mul_input.value));
         });
     }
 
-    // zoom in and out by scrolling over the canvas
+    // reset view
     {
-        let webgl_canvas = document.getElementById("webgl_canvas");
-        webgl_canvas.addEventListener("wheel", function(e) {
-            e.preventDefault();
-            let step = canvas.get_r() / 5.0;
-            if (e.deltaY >= 0) {step *= -1;}
+        let reset_view_button = document.getElementById("reset_view_button");
+        reset_view_button.addEventListener("click", () => {
+            canvas.reset();
+        });
 
-            let normalized_x = (e.clientX - window.innerWidth/2) / (window.innerWidth / 2);
-            let normalized_y = (window.innerHeight/2 - e.clientY) / (window.innerHeight / 2);
+        // frame the circle, keeping the view rotation
+        let fit_view_button = document.getElementById("fit_view_button");
+        fit_view_button.addEventListener("click", () => {
+            canvas.fit_view(400);
+        });
+    }
 
-            canvas.add_to_r(step, normalized_x, normalized_y);
+    // compare k = 2..37 side by side, clicking a cell opens it
+    {
+        let gallery_button = document.getElementById("gallery_button");
+        gallery_button.addEventListener("click", () => {
+            if (canvas.is_gallery_shown()) {
+                canvas.close_gallery();
+            } else {
+                canvas.show_gallery(2, 37, 6);
+            }
+            update_gallery_button();
         });
+        document.getElementById("webgl_canvas").addEventListener("pointerup", update_gallery_button);
     }
-    
-    // reset view
+
+    // compare with the next multiplier: side by side, then as a difference, then off
     {
-        let reset_view_button = document.getElementById("reset_view_button");
-        reset_view_button.addEventListener("click", () => {
-            document.getElementById("rotation_slider").value = 0;
-            document.getElementById("rotation_label").textContent = 0;
-            canvas.reset();
+        let compare_button = document.getElementById("compare_button");
+        compare_button.addEventListener("click", () => {
+            const mode = canvas.get_comparison_mode();
+            // the multiplier is a u32, past the largest one comes 0
+            const next = (canvas.get_multiplier() + 1) % 2 ** 32;
+            apply_input(compare_button, () => {
+                if (mode === undefined) {
+                    canvas.show_comparison(canvas.get_points(), next, "split");
+                } else if (mode === "split") {
+                    canvas.show_comparison(canvas.get_points(), next, "difference");
+                } else {
+                    canvas.close_comparison();
+                }
+            });
+            update_compare_button();
         });
     }
     
@@ -119,7 +156,6 @@ async function run() {
     {
         let rotation_slider = document.getElementById("rotation_slider");
         rotation_slider.addEventListener("input", function() {
-            document.getElementById("rotation_label").textContent = this.value;
             canvas.set_rotation(this.value);
         });
     }
@@ -133,6 +169,14 @@ async function run() {
         });
     }
 
+    // show the overview inset while zoomed in
+    {
+        let minimap_cb = document.getElementById("minimap_cb");
+        minimap_cb.addEventListener("change", () => {
+            canvas.set_minimap(minimap_cb.checked);
+        });
+    }
+
     // change line width
     {
         // by mouse wheel
@@ -161,7 +205,7 @@ async function run() {
             } else {
                 val /= 10000.0;
             }
-            canvas.set_rect_width(val);
+            apply_input(line_width_input, () => canvas.set_rect_width(val));
         });
 
         // by input box
@@ -176,37 +220,22 @@ async function run() {
             } else {
                 val /= 10000.0;
             }
-            canvas.set_rect_width(val);
+            apply_input(line_width_input, () => canvas.set_rect_width(val));
         });
     }
 
     // change shader
     {
         // to lines
-        let line_width_input = document.getElementById("line_width");
         let lines_rb = document.getElementById("lines_rb");
         lines_rb.addEventListener("change", (e) => {
-            let val = !lines_rb.checked;
-            canvas.set_use_rects(val);
-
-            if (val) {
-                line_width_input.removeAttribute("disabled");
-            } else {
-                line_width_input.setAttribute("disabled", true);
-            }
+            canvas.set_use_rects(!lines_rb.checked);
         });
 
         // to rects
         let rects_rb = document.getElementById("rects_rb");
         rects_rb.addEventListener("change", (e) => {
-            let val = rects_rb.checked;
-            canvas.set_use_rects(val);
-
-            if (val) {
-                line_width_input.removeAttribute("disabled");
-            } else {
-                line_width_input.setAttribute("disabled", true);
-            }
+            canvas.set_use_rects(rects_rb.checked);
         });
     }
 
@@ -225,215 +254,237 @@ async function run() {
         });
     }
 
-    // adjust to new window size when resized
+    // presets
     {
-        window.addEventListener("resize", () => {
-            canvas.adjust_view();
-        });
-    }
-
-    // Movement by mouse drag
-    {
-        let dragging = false;
-        let last_x = 0.0;
-        let last_y = 0.0;
-
-        let webgl_canvas = document.getElementById("webgl_canvas");
+        let preset_select = document.getElementById("preset_select");
+        for (const preset of canvas.list_presets()) {
+            let option = document.createElement("option");
+            option.value = preset.id;
+            option.textContent = preset.name;
+            option.title = preset.description;
+            preset_select.appendChild(option);
+        }
 
-        // When mouse button is pressed, we start the drag state
-        webgl_canvas.addEventListener("mousedown", (e) => {
-            if (e.button !== 0) {
-                // Only accept left click
+        preset_select.addEventListener("change", () => {
+            if (preset_select.value === "") {
                 return;
             }
-            dragging = true;
-            last_x = e.clientX;
-            last_y = e.clientY;
-            webgl_canvas.style.cursor = "move";
+            canvas.apply_preset(preset_select.value, 800);
         });
 
-        // When mouse button is released, we end the drag state
-        webgl_canvas.addEventListener("mouseup", (e) => {
-            if (e.button !== 0) {
-                return;
+        // any manual change turns the selection back to "Custom"
+        let control_panel = document.getElementById("control_panel");
+        control_panel.addEventListener("input", (e) => {
+            if (e.target !== preset_select) {
+                preset_select.value = "";
             }
-            dragging = false;
-            webgl_canvas.style.cursor = "default";
         });
+    }
 
-        // When cursor leaves the screen, we should end the drag state to not cause confusion
-        webgl_canvas.addEventListener("mouseleave", (e) => {
-            if (e.button !== 0) {
-                return;
-            }
-            if (e.relatedTarget && e.relatedTarget.id === "control_panel") {
-                return;
-            }
-            if (e.relatedTarget && e.relatedTarget.id === "show_button") {
-                return;
-            }
-            dragging = false;
-            webgl_canvas.style.cursor = "default";
-        }); 
-
-        // If in drag mode, move the canvas
-        // If not, don't do anything
-        webgl_canvas.addEventListener("mousemove", (e) => {
-            if (!dragging) {
-                return;
+    // share the current view as a link
+    {
+        let copy_link_button = document.getElementById("copy_link_button");
+        copy_link_button.addEventListener("click", () => {
+            const hash = "#" + canvas.to_fragment();
+            history.replaceState(null, "", hash);
+            if (navigator.clipboard) {
+                navigator.clipboard.writeText(window.location.href);
             }
+        });
 
-            let w = window.innerWidth;
-            let h = window.innerHeight;
-
-            let dx = (e.clientX - last_x) * 2;
-            let dy = (last_y - e.clientY) * 2;
-            if (w >= h) {
-                dx /= h;
-                dy /= h;
-            } else {
-                dx /= w;
-                dy /= w;
+        // a link pasted into the address bar of an open tab
+        window.addEventListener("hashchange", () => {
+            try {
+                canvas.load_fragment(window.location.hash);
+            } catch (e) {
+                console.warn(e);
             }
-
-            last_x = e.clientX;
-            last_y = e.clientY;
-
-            canvas.move_shape(dx, dy);
         });
     }
 
-    let touch_zooming = false; // cancel touch moving if zooming
-
-    // Touchscreen move
+    // undo and redo, from the buttons or Ctrl+Z / Ctrl+Shift+Z / Ctrl+Y
     {
-        let webgl_canvas = document.getElementById("webgl_canvas");
-
-        let dragging = false;
-        let prev_x = 0.0;
-        let prev_y = 0.0;
+        document.getElementById("undo_button").addEventListener("click", () => {
+            canvas.undo();
+            update_undo_buttons();
+        });
+        document.getElementById("redo_button").addEventListener("click", () => {
+            canvas.redo();
+            update_undo_buttons();
+        });
 
-        // On first touch, begin dragging mode
-        webgl_canvas.addEventListener("touchstart", function(e) {
-            if (e.touches.length !== 1) {
+        window.addEventListener("keydown", (e) => {
+            if (!(e.ctrlKey || e.metaKey)) {
                 return;
             }
-
-            dragging = true;
-            prev_x = e.touches[0].clientX;
-            prev_y = e.touches[0].clientY;
+            const key = e.key.toLowerCase();
+            if (key === "z" && !e.shiftKey) {
+                canvas.undo();
+            } else if ((key === "z" && e.shiftKey) || key === "y") {
+                canvas.redo();
+            } else {
+                return;
+            }
+            e.preventDefault();
+            update_undo_buttons();
         });
 
-        // Exit dragging mode when touch event ends
-        webgl_canvas.addEventListener("touchend", function(e) {
-            dragging = false;
-        });
+        // keep the buttons enabled state current after any change
+        let control_panel = document.getElementById("control_panel");
+        control_panel.addEventListener("input", update_undo_buttons);
+        control_panel.addEventListener("click", update_undo_buttons);
+    }
 
-        webgl_canvas.addEventListener("touchcancel", function(e) {
-            dragging = false;
+    // Describe what is under the cursor, dragging and zooming are handled
+    // by the canvas itself
+    {
+        let webgl_canvas = document.getElementById("webgl_canvas");
+        webgl_canvas.addEventListener("pointermove", (e) => {
+            if (e.pointerType !== "mouse" || e.buttons !== 0) {
+                hide_pick_label();
+                return;
+            }
+            show_pick_label(e.clientX, e.clientY);
         });
+        webgl_canvas.addEventListener("pointerleave", hide_pick_label);
 
-        // Handle drag event if in dragging mode
-        webgl_canvas.addEventListener("touchmove", function(e) {
-            e.preventDefault();
-            if (!dragging) {
+        // double click a point or chord to frame its orbit, or the circle
+        // when there is nothing there
+        webgl_canvas.addEventListener("dblclick", (e) => {
+            if (canvas.is_over_minimap(e.clientX, e.clientY)) {
                 return;
             }
-            if (touch_zooming) {
+            let hit = canvas.pick(e.clientX, e.clientY);
+            if (hit === undefined) {
+                canvas.fit_view(400);
                 return;
             }
-
-            const cur_x = e.touches[0].clientX;
-            const cur_y = e.touches[0].clientY;
-
-            let w = window.innerWidth;
-            let h = window.innerHeight;
-
-            let dx = (cur_x - prev_x) * 2;
-            let dy = (prev_y - cur_y) * 2;
-            if (w >= h) {
-                dx /= h;
-                dy /= h;
-            } else {
-                dx /= w;
-                dy /= w;
-            }
-
-            prev_x = cur_x;
-            prev_y = cur_y;
-
-            canvas.move_shape(dx, dy);
+            canvas.fit_orbit(hit.source, 400);
+            hit.free();
         });
     }
 
-    // Touchscreen zoom
-    {
-        // helper function
-        function get_distance(touch0, touch1) {
-            const dx = touch0.clientX - touch1.clientX;
-            const dy = touch0.clientY - touch1.clientY;
-            return Math.sqrt(dx*dx + dy*dy);
-        }
+    console.log("Loading complete");
+}
 
-        let webgl_canvas = document.getElementById("webgl_canvas");
+function update_gallery_button() {
+    document.getElementById("gallery_button").textContent = canvas.is_gallery_shown() ? "Close Gallery" : "Gallery";
+}
 
-        let prev_dist = 0.0;
+function update_compare_button() {
+    const labels = {split: "Difference", difference: "Close Compare"};
+    document.getElementById("compare_button").textContent = labels[canvas.get_comparison_mode()] ?? "Compare";
+}
 
-        webgl_canvas.addEventListener("touchstart", function(e) {
-            if (e.touches.length === 2) {
-                prev_dist = get_distance(e.touches[0], e.touches[1]);
-            }
-        });
+function update_undo_buttons() {
+    document.getElementById("undo_button").disabled = !canvas.can_undo();
+    document.getElementById("redo_button").disabled = !canvas.can_redo();
+}
 
-        webgl_canvas.addEventListener("touchmove", function(e) {
-            if (e.touches.length !== 2) {
-                return;
-            }
+// Describe the point or chord under the cursor next to it, e.g.
+// "i = 137 → 137·72 mod 500 = 364"
+function show_pick_label(x, y) {
+    let label = document.getElementById("pick_label");
+    let hit = canvas.pick(x, y);
+    if (hit === undefined) {
+        label.style.display = "none";
+        return;
+    }
+    const points = canvas.get_points();
+    const mul = canvas.get_multiplier();
+    label.textContent = `i = ${hit.source} → ${hit.source}·${mul} mod ${points} = ${hit.destination}`;
+    label.style.left = (x + 14) + "px";
+    label.style.top = (y + 14) + "px";
+    label.style.display = "block";
+    hit.free();
+}
 
-            e.preventDefault();
-            touch_zooming = true;
-            const cur_r = canvas.get_r();
-            const step_coef = 0.005 * cur_r;
+function hide_pick_label() {
+    document.getElementById("pick_label").style.display = "none";
+}
+
+// Run a setter with a value from `input`, which is marked invalid with the
+// error's message while the canvas doesn't take the value, see limits.rs
+function apply_input(input, set) {
+    try {
+        set();
+        input.setCustomValidity("");
+    } catch (e) {
+        input.setCustomValidity(e.message);
+    }
+}
 
-            // Calculate difference in current and previous distance
-            const cur_dist = get_distance(e.touches[0], e.touches[1]);
-            const ddist_raw = cur_dist - prev_dist;
-            const ddist = ddist_raw * step_coef;
+// Panel element whose input event is being handled, it already shows the
+// new value and rewriting it would get in the way of typing
+let panel_input_target = null;
 
-            // Calculate center of pinch gesture
-            let center_x = (e.touches[0].clientX + e.touches[1].clientX) / 2;
-            let center_y = (e.touches[0].clientY + e.touches[1].clientY) / 2;
+function set_widget_value(id, value) {
+    let element = document.getElementById(id);
+    if (element !== panel_input_target) {
+        element.value = value;
+    }
+}
 
-            let w = window.innerWidth;
-            let h = window.innerHeight;
+function set_line_width_enabled(enabled) {
+    let line_width_input = document.getElementById("line_width");
+    if (enabled) {
+        line_width_input.removeAttribute("disabled");
+    } else {
+        line_width_input.setAttribute("disabled", true);
+    }
+}
 
-            center_x -= w/2;
-            center_y -= h/2;
-            center_y *= -1;
+// Called by the canvas for every field of the scene that changes
+function on_canvas_change(e) {
+    update_undo_buttons();
+
+    switch (e.field) {
+        case "points":
+            set_widget_value("points_input", e.value);
+            break;
+        case "multiplier":
+            set_widget_value("mul_input", e.value);
+            break;
+        case "rotation": {
+            const rotation = Math.round(e.value);
+            set_widget_value("rotation_slider", rotation);
+            document.getElementById("rotation_label").textContent = rotation;
+            break;
+        }
+        case "rect_width":
+            set_widget_value("line_width", Math.round(e.value * 10000.0));
+            break;
+        case "use_rects":
+            document.getElementById("rects_rb").checked = e.value;
+            document.getElementById("lines_rb").checked = !e.value;
+            set_line_width_enabled(e.value);
+            break;
+        case "enable_outline":
+            document.getElementById("draw_outline_cb").checked = e.value;
+            break;
+    }
+}
 
-            if (w >= h) {
-                center_x /= h;
-                center_y /= h;
-            } else {
-                center_x /= w;
-                center_y /= w;
-            }
+// Update the panel widgets to match the canvas state
+function sync_panel() {
+    document.getElementById("points_input").value = canvas.get_points();
+    document.getElementById("mul_input").value = canvas.get_multiplier();
 
-            center_x *= 2;
-            center_y *= 2;
+    const rotation = Math.round(canvas.get_rotation());
+    document.getElementById("rotation_slider").value = rotation;
+    document.getElementById("rotation_label").textContent = rotation;
 
-            canvas.add_to_r(ddist, center_x, center_y);
+    const use_rects = canvas.get_use_rects();
+    document.getElementById("rects_rb").checked = use_rects;
+    document.getElementById("lines_rb").checked = !use_rects;
 
-            prev_dist = cur_dist;
-        });
+    document.getElementById("line_width").value = Math.round(canvas.get_rect_width() * 10000.0);
+    set_line_width_enabled(use_rects);
 
-        webgl_canvas.addEventListener("touchend", function(e) {
-            prev_dist = 0.0;
-            touch_zooming = false;
-        });
-    }
+    document.getElementById("draw_outline_cb").checked = canvas.get_enable_outline();
+    document.getElementById("minimap_cb").checked = canvas.get_minimap();
 
-    console.log("Loading complete");
+    update_undo_buttons();
 }
 
 run();
\ No newline at end of file
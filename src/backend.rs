@@ -0,0 +1,177 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Which renderer a canvas gets. WebGL2 when the browser has it, and
+// otherwise WebGL1 with ANGLE_instanced_arrays, which every WebGL1 browser
+// still around offers, see webgl1_renderer.rs. Both draw the same.
+//
+// A canvas hands out one kind of context only, so the choice is made once,
+// on the first context asked for. Once a WebGL2 context is obtained, a
+// failure to set the renderer up on it is an error rather than a reason to
+// fall back. `Canvas.force_backend` makes the next canvases skip the
+// detection, to try the fallback in a browser that has WebGL2.
+//
+// `Canvas.get_capabilities` tells the page what was picked and why:
+//   backend            "webgl2" or "webgl1"
+//   version            the context's VERSION string
+//   shading_language   its SHADING_LANGUAGE_VERSION string
+//   fallback_reason    why WebGL2 isn't used, undefined when it is
+//   labels             whether `draw_text` draws anything
+
+use std::cell::Cell;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{WebGl2RenderingContext, WebGlRenderingContext, WebglLoseContext};
+
+use crate::canvas_state::CanvasState;
+use crate::error::ModmulError;
+use crate::renderer::Renderer;
+use crate::surface::Surface;
+use crate::webgl1_renderer::WebGl1Renderer;
+use crate::webgl_renderer::WebGlRenderer;
+
+thread_local! {
+    // Backend new canvases use without trying WebGL2 first, see `force_backend`
+    static FORCED: Cell<Option<Backend>> = const { Cell::new(None) };
+}
+
+// Struct declarations
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    WebGl2,
+    WebGl1
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Capabilities {
+    pub backend: Backend,
+    pub version: String,
+    pub shading_language: String,
+    pub fallback_reason: Option<String>, // none when WebGL2 is used
+    pub labels: bool
+}
+
+// Struct implementations
+
+impl Backend {
+    pub fn from_name(name: &str) -> Option<Backend> {
+        return match name {
+            "webgl2" => Some(Backend::WebGl2),
+            "webgl1" => Some(Backend::WebGl1),
+            _ => None
+        };
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Backend::WebGl2 => "webgl2",
+            Backend::WebGl1 => "webgl1"
+        };
+    }
+}
+
+impl Capabilities {
+    pub fn to_js(&self) -> JsValue {
+        let obj: js_sys::Object = js_sys::Object::new();
+        let _ = js_sys::Reflect::set(&obj, &JsValue::from_str("backend"), &JsValue::from_str(self.backend.name()));
+        let _ = js_sys::Reflect::set(&obj, &JsValue::from_str("version"), &JsValue::from_str(&self.version));
+        let _ = js_sys::Reflect::set(&obj, &JsValue::from_str("shading_language"), &JsValue::from_str(&self.shading_language));
+        if let Some(reason) = &self.fallback_reason {
+            let _ = js_sys::Reflect::set(&obj, &JsValue::from_str("fallback_reason"), &JsValue::from_str(reason));
+        }
+        let _ = js_sys::Reflect::set(&obj, &JsValue::from_str("labels"), &JsValue::from(self.labels));
+        return obj.into();
+    }
+}
+
+impl CanvasState {
+    pub fn get_capabilities(&self) -> JsValue {
+        return self.capabilities.to_js();
+    }
+}
+
+// Helper functions
+
+// Make canvases created from now on use `backend`, or detect it again with none
+pub fn force_backend(backend: Option<Backend>) {
+    FORCED.with(|forced: &Cell<Option<Backend>>| forced.set(backend));
+}
+
+// Renderer for a new canvas, and what it was built on
+pub fn create_renderer(surface: &Surface) -> Result<(Box<dyn Renderer>, Capabilities), ModmulError> {
+    let fallback_reason: String = match FORCED.with(Cell::get) {
+        Some(Backend::WebGl1) => String::from("WebGL1 was forced"),
+        Some(Backend::WebGl2) => return create_webgl2(surface.webgl2()?),
+        None => match surface.webgl2() {
+            Ok(gl) => return create_webgl2(gl),
+            Err(e) => e.to_string()
+        }
+    };
+    let gl: WebGlRenderingContext = surface.webgl1()?;
+    let version: String = parameter(gl.get_parameter(WebGlRenderingContext::VERSION));
+    let shading_language: String = parameter(gl.get_parameter(WebGlRenderingContext::SHADING_LANGUAGE_VERSION));
+    let renderer: WebGl1Renderer = WebGl1Renderer::new(gl)?;
+    let capabilities: Capabilities = Capabilities {
+        backend: Backend::WebGl1,
+        version,
+        shading_language,
+        fallback_reason: Some(fallback_reason),
+        labels: renderer.has_labels()
+    };
+    return Ok((Box::new(renderer), capabilities));
+}
+
+// Renderer on the restored context of a canvas that had one on `backend`
+pub fn recreate_renderer(surface: &Surface, backend: Backend) -> Result<Box<dyn Renderer>, ModmulError> {
+    return match backend {
+        Backend::WebGl2 => Ok(Box::new(WebGlRenderer::new(surface.webgl2()?)?)),
+        Backend::WebGl1 => Ok(Box::new(WebGl1Renderer::new(surface.webgl1()?)?))
+    };
+}
+
+// WEBGL_lose_context of the context `backend` runs on
+pub fn lose_context_extension(surface: &Surface, backend: Backend) -> Result<WebglLoseContext, ModmulError> {
+    let extension: Option<js_sys::Object> = match backend {
+        Backend::WebGl2 => surface.webgl2()?.get_extension("WEBGL_lose_context").ok().flatten(),
+        Backend::WebGl1 => surface.webgl1()?.get_extension("WEBGL_lose_context").ok().flatten()
+    };
+    return extension
+        .and_then(|extension: js_sys::Object| extension.dyn_into::<WebglLoseContext>().ok())
+        .ok_or(ModmulError::ExtensionUnsupported("WEBGL_lose_context"));
+}
+
+fn create_webgl2(gl: WebGl2RenderingContext) -> Result<(Box<dyn Renderer>, Capabilities), ModmulError> {
+    let version: String = parameter(gl.get_parameter(WebGl2RenderingContext::VERSION));
+    let shading_language: String = parameter(gl.get_parameter(WebGl2RenderingContext::SHADING_LANGUAGE_VERSION));
+
+    // Compile shaders and link programs
+    let renderer: WebGlRenderer = WebGlRenderer::new(gl)?;
+    let capabilities: Capabilities = Capabilities {
+        backend: Backend::WebGl2,
+        version,
+        shading_language,
+        fallback_reason: None,
+        labels: true
+    };
+    return Ok((Box::new(renderer), capabilities));
+}
+
+// A string parameter of the context, empty if it has none
+fn parameter(value: Result<JsValue, JsValue>) -> String {
+    return value.ok().and_then(|value: JsValue| value.as_string()).unwrap_or_default();
+}
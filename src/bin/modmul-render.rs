@@ -0,0 +1,297 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Headless renderer for batch generating figures as PNG, SVG or PDF files.
+// Build with `cargo build --release --features cli`.
+
+#![allow(unused_parens)]
+#![allow(clippy::needless_return)]
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use modmul_webgl::{Color, CpuRenderer, ModmulError, PdfRenderer, Renderer, Scene, SvgRenderer, limits, render};
+
+const USAGE: &str = "\
+Usage: modmul-render [OPTIONS]
+
+Options:
+  -n, --points <N>       number of points on the circle, 1 to 16777216 (default 500)
+  -k, --mul <K>          multiplier, or an inclusive range like 2..100 that
+                         writes one file per value (default 72)
+  -r, --rotation <DEG>   rotation in degrees (default 0)
+  -w, --width <W>        line width, same scale as the web panel (default 20)
+      --lines            draw 1px lines instead of rectangles
+      --fg <#RRGGBB>     foreground color (default #fafafa)
+      --bg <#RRGGBB>     background color (default #181818)
+      --outline          draw the circle outline
+      --label            write n and k in the top left corner
+  -s, --size <WxH>       output size in pixels (default 1000x1000)
+  -f, --format <FMT>     png, svg or pdf (default: from the output file name, else png)
+  -o, --output <PATH>    output file; for a range, {k} is replaced by the
+                         multiplier, otherwise _<k> is added before the extension
+  -h, --help             print this message";
+
+// Struct declarations
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Png,
+    Svg,
+    Pdf
+}
+
+struct Options {
+    points: u32,
+    mul_from: u32,
+    mul_to: u32,
+    rotation: f32,
+    width: f32,
+    lines: bool,
+    fg: Option<Color>,
+    bg: Option<Color>,
+    outline: bool,
+    label: bool,
+    size: (u32, u32),
+    format: Format,
+    output: Option<String>
+}
+
+// Struct implementations
+
+impl Format {
+    fn parse(s: &str) -> Option<Format> {
+        return match s.to_ascii_lowercase().as_str() {
+            "png" => Some(Format::Png),
+            "svg" => Some(Format::Svg),
+            "pdf" => Some(Format::Pdf),
+            _ => None
+        };
+    }
+
+    fn extension(&self) -> &'static str {
+        return match self {
+            Format::Png => "png",
+            Format::Svg => "svg",
+            Format::Pdf => "pdf"
+        };
+    }
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mut opts: Options = Options {
+            points: 500,
+            mul_from: 72,
+            mul_to: 72,
+            rotation: 0.0,
+            width: 20.0,
+            lines: false,
+            fg: None,
+            bg: None,
+            outline: false,
+            label: false,
+            size: (1000, 1000),
+            format: Format::Png,
+            output: None
+        };
+        let mut format: Option<Format> = None;
+
+        let mut i: usize = 0;
+        while (i < args.len()) {
+            let arg: &str = args[i].as_str();
+            // Flags that take a value accept both "--flag value" and "--flag=value"
+            let (name, inline): (&str, Option<&str>) = match arg.split_once('=') {
+                Some((n, v)) if n.starts_with("--") => (n, Some(v)),
+                _ => (arg, None)
+            };
+            let mut value = || -> Result<String, String> {
+                if let Some(v) = inline {
+                    return Ok(String::from(v));
+                }
+                i += 1;
+                return args.get(i).cloned().ok_or(format!("{} needs a value", name));
+            };
+
+            match name {
+                "-n" | "--points" => opts.points = check(limits::check_points(parse_num(&value()?, name)?))?,
+                "-k" | "--mul" => {
+                    let v: String = value()?;
+                    match v.split_once("..") {
+                        Some((from, to)) => {
+                            opts.mul_from = parse_num(from, name)?;
+                            opts.mul_to = parse_num(to.trim_start_matches('='), name)?;
+                            if (opts.mul_to < opts.mul_from) {
+                                return Err(format!("empty range {}", v));
+                            }
+                        }
+                        None => {
+                            opts.mul_from = parse_num(&v, name)?;
+                            opts.mul_to = opts.mul_from;
+                        }
+                    }
+                }
+                "-r" | "--rotation" => opts.rotation = check(limits::check_degrees("rotation", parse_num(&value()?, name)?))? as f32,
+                "-w" | "--width" => {
+                    opts.width = parse_num(&value()?, name)?;
+                    check(limits::check_line_width(opts.width / 10000.0))?;
+                }
+                "--lines" => opts.lines = true,
+                "--fg" => opts.fg = Some(parse_color(&value()?)?),
+                "--bg" => opts.bg = Some(parse_color(&value()?)?),
+                "--outline" => opts.outline = true,
+                "--label" => opts.label = true,
+                "-s" | "--size" => {
+                    let v: String = value()?;
+                    let (w, h): (&str, &str) = v.split_once(['x', 'X']).ok_or(format!("size must look like 1000x1000, got {}", v))?;
+                    opts.size = (parse_num(w, name)?, parse_num(h, name)?);
+                    if (opts.size.0 == 0 || opts.size.1 == 0) {
+                        return Err(String::from("size must not be zero"));
+                    }
+                }
+                "-f" | "--format" => {
+                    let v: String = value()?;
+                    format = Some(Format::parse(&v).ok_or(format!("unknown format {}", v))?);
+                }
+                "-o" | "--output" => opts.output = Some(value()?),
+                _ => return Err(format!("unknown option {}", arg))
+            }
+            i += 1;
+        }
+
+        // Fall back to the output extension, then to PNG
+        let from_ext: Option<Format> = opts.output.as_deref()
+            .and_then(|o| Path::new(o).extension())
+            .and_then(|e| Format::parse(&e.to_string_lossy()));
+        opts.format = format.or(from_ext).unwrap_or(Format::Png);
+
+        return Ok(opts);
+    }
+
+    // File name for multiplier `k`
+    fn output_path(&self, k: u32) -> String {
+        let is_range: bool = (self.mul_from != self.mul_to);
+        return match &self.output {
+            None => format!("modmul_{}_{}.{}", self.points, k, self.format.extension()),
+            Some(o) if !is_range => o.clone(),
+            Some(o) if o.contains("{k}") => o.replace("{k}", &k.to_string()),
+            Some(o) => {
+                let path: &Path = Path::new(o);
+                match (path.file_stem(), path.extension()) {
+                    (Some(stem), Some(ext)) => path.with_file_name(format!("{}_{}.{}", stem.to_string_lossy(), k, ext.to_string_lossy())).to_string_lossy().into_owned(),
+                    _ => format!("{}_{}", o, k)
+                }
+            }
+        };
+    }
+
+    fn scene(&self, k: u32) -> Scene {
+        let mut scene: Scene = Scene::new(self.size.0, self.size.1);
+        scene.set_points(self.points);
+        scene.set_multiplier(k);
+        scene.set_rotation(self.rotation);
+        scene.set_rect_width(self.width / 10000.0);
+        scene.set_use_rects(!self.lines);
+        scene.set_enable_outline(self.outline);
+        if let Some(fg) = self.fg {
+            scene.set_fg_color(fg);
+        }
+        if let Some(bg) = self.bg {
+            scene.set_bg_color(bg);
+        }
+        return scene;
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if (args.iter().any(|a| a == "-h" || a == "--help")) {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let opts: Options = match Options::parse(&args) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("modmul-render: {}\n\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for k in opts.mul_from..=opts.mul_to {
+        let path: String = opts.output_path(k);
+        let bytes: Vec<u8> = match render_file(&opts, k) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!("modmul-render: {}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        };
+        if let Err(e) = std::fs::write(&path, bytes) {
+            eprintln!("modmul-render: {}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+        println!("{}", path);
+    }
+
+    return ExitCode::SUCCESS;
+}
+
+// Helper functions
+
+fn render_file(opts: &Options, k: u32) -> Result<Vec<u8>, String> {
+    let scene: Scene = opts.scene(k);
+    let (w, h): (u32, u32) = opts.size;
+    return match opts.format {
+        Format::Png => {
+            let mut renderer: CpuRenderer = CpuRenderer::new(w, h);
+            draw(opts, &scene, k, &mut renderer);
+            renderer.encode_png()
+        }
+        Format::Svg => {
+            let mut renderer: SvgRenderer = SvgRenderer::new(w, h);
+            draw(opts, &scene, k, &mut renderer);
+            Ok(renderer.finish().into_bytes())
+        }
+        Format::Pdf => {
+            let mut renderer: PdfRenderer = PdfRenderer::new(w, h);
+            draw(opts, &scene, k, &mut renderer);
+            Ok(renderer.finish())
+        }
+    };
+}
+
+fn draw(opts: &Options, scene: &Scene, k: u32, renderer: &mut dyn Renderer) {
+    render(scene, renderer);
+    if (opts.label) {
+        let size: f32 = (opts.size.1 as f32 / 30.0).max(12.0);
+        let fg: Color = opts.fg.unwrap_or(Color::new(250, 250, 250));
+        renderer.draw_text(&format!("n = {}, k = {}", opts.points, k), size / 2.0, size / 2.0, size, &fg);
+    }
+}
+
+fn parse_num<T: std::str::FromStr>(s: &str, name: &str) -> Result<T, String> {
+    return s.trim().parse::<T>().map_err(|_| format!("invalid value {} for {}", s, name));
+}
+
+// Ranges of the library, see limits.rs
+fn check<T>(result: Result<T, ModmulError>) -> Result<T, String> {
+    return result.map_err(|e: ModmulError| e.to_string());
+}
+
+fn parse_color(s: &str) -> Result<Color, String> {
+    return Color::from_hex(s).ok_or(format!("colors must look like #rrggbb, got {}", s));
+}
@@ -0,0 +1,301 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// View onto the figure. The figure itself lives in world space, on the unit
+// circle around the origin, and never moves. Panning, zooming and rotating
+// only change the camera.
+//
+// Three coordinate spaces are involved:
+//   world   the unit circle around the origin, y up
+//   view    world after camera rotation and zoom, the shorter side of the
+//           surface spans -1.0..1.0 and y is up. Line widths are in view
+//           units so they don't change with the zoom.
+//   clip    view squeezed along the longer side so that -1.0..1.0 spans
+//           both sides, what WebGL expects
+//
+// The camera works in f64 so that it can zoom in far. At a zoom of 10^8 one
+// pixel covers about 10^-11 world units, well below what an f32 can resolve
+// around 1.0. View and pixel coordinates of what is on screen are small, so
+// those stay f32. See `DEEP_ZOOM` for how the renderers deal with this.
+
+use crate::scene::{DEFAULT_RADIUS, Point};
+
+// Above this zoom the f32 math in the shaders is no longer accurate to a
+// pixel. Renderers then take the visible geometry from `geometry`, computed
+// in f64 relative to the camera, instead of computing it on the GPU.
+//
+// GLSL ES makes no promise about how exact sin and cos are. Desktop GPUs are
+// good to about 4 ulp of an f32 around 1.0, 2^-22 world units, and we allow
+// four times that for mobile ones. The view matrix scales the error up with
+// the zoom, while a pixel is at least 2^-10 view units on surfaces up to 2048
+// pixels on their shorter side. Keeping points within half a pixel gives
+// 2^-11 / 2^-20, a zoom of 2^9.
+const SHADER_ERROR: f64 = 1.0 / (1 << 20) as f64; // world units
+const MIN_PIXEL: f64 = 1.0 / (1 << 10) as f64; // view units
+const PIXEL_TOLERANCE: f64 = 0.5; // pixels
+pub const DEEP_ZOOM: f64 = PIXEL_TOLERANCE * MIN_PIXEL / SHADER_ERROR;
+
+// Struct declarations
+
+// Point in world space
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorldPoint {
+    pub x: f64,
+    pub y: f64
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Camera {
+    pub(crate) center: WorldPoint, // world point shown in the middle of the surface
+    pub(crate) zoom: f64, // 1.0 shows the whole circle with a small margin
+    pub(crate) rotation: f64, // radians, counter-clockwise
+    pub(crate) dimensions: Point // surface size in pixels
+}
+
+// Struct implementations
+
+impl WorldPoint {
+    pub fn new(x: f64, y: f64) -> WorldPoint {
+        return WorldPoint {x, y};
+    }
+}
+
+impl Camera {
+    pub fn new(width: u32, height: u32) -> Camera {
+        return Camera {
+            center: WorldPoint::new(0.0, 0.0),
+            zoom: 1.0,
+            rotation: 0.0,
+            dimensions: Point::new(width as f32, height as f32)
+        };
+    }
+
+    pub fn center(&self) -> WorldPoint {
+        return self.center;
+    }
+
+    pub fn zoom(&self) -> f64 {
+        return self.zoom;
+    }
+
+    // Radians
+    pub fn rotation(&self) -> f64 {
+        return self.rotation;
+    }
+
+    pub fn dimensions(&self) -> Point {
+        return self.dimensions;
+    }
+
+    pub fn widescreen(&self) -> bool {
+        return self.dimensions.x >= self.dimensions.y;
+    }
+
+    // View units per world unit, which is also the radius of the circle in
+    // view units
+    pub fn scale(&self) -> f64 {
+        return self.zoom * DEFAULT_RADIUS as f64;
+    }
+
+    // Whether the view matrix is too coarse for the current zoom
+    pub fn is_deep(&self) -> bool {
+        return self.zoom > DEEP_ZOOM;
+    }
+
+    // Multipliers taking view to clip coordinates so that a circle stays
+    // round on a non-square surface. The longer side gets scaled down.
+    pub fn normalization(&self) -> (f32, f32) {
+        if (self.dimensions.x == 0.0 || self.dimensions.y == 0.0) {
+            return (1.0, 1.0);
+        }
+        if (self.widescreen()) {
+            return (self.dimensions.y / self.dimensions.x, 1.0);
+        } else {
+            return (1.0, self.dimensions.x / self.dimensions.y);
+        }
+    }
+
+    // Half the width and height of the surface in view units
+    pub fn view_extent(&self) -> (f32, f32) {
+        let (x_norm, y_norm): (f32, f32) = self.normalization();
+        return (1.0 / x_norm, 1.0 / y_norm);
+    }
+
+    pub fn set_dimensions(&mut self, width: u32, height: u32) {
+        self.dimensions = Point::new(width as f32, height as f32);
+    }
+
+    // Back to the whole circle, centered and upright
+    pub fn reset(&mut self) {
+        self.center = WorldPoint::new(0.0, 0.0);
+        self.zoom = 1.0;
+        self.rotation = 0.0;
+    }
+
+    // World to clip transform as a column-major 3x3 matrix, ready for
+    // `uniformMatrix3fv`. Only accurate while `is_deep` is false.
+    pub fn view_matrix(&self) -> [f32; 9] {
+        let (x_norm, y_norm): (f32, f32) = self.normalization();
+        let (x_norm, y_norm): (f64, f64) = (x_norm as f64, y_norm as f64);
+        let s: f64 = self.scale();
+        let (sin, cos): (f64, f64) = self.rotation.sin_cos();
+        let tx: f64 = -s * (cos * self.center.x - sin * self.center.y);
+        let ty: f64 = -s * (sin * self.center.x + cos * self.center.y);
+        let m: [f64; 9] = [
+            x_norm * s * cos, y_norm * s * sin, 0.0,
+            -x_norm * s * sin, y_norm * s * cos, 0.0,
+            x_norm * tx, y_norm * ty, 1.0
+        ];
+        return m.map(|v: f64| v as f32);
+    }
+
+    // View coordinates kept as f64, for callers doing more math with them
+    pub fn world_to_view_f64(&self, p: WorldPoint) -> (f64, f64) {
+        let s: f64 = self.scale();
+        let (sin, cos): (f64, f64) = self.rotation.sin_cos();
+        let dx: f64 = p.x - self.center.x;
+        let dy: f64 = p.y - self.center.y;
+        return (s * (cos * dx - sin * dy), s * (sin * dx + cos * dy));
+    }
+
+    pub fn world_to_view(&self, p: WorldPoint) -> Point {
+        let (x, y): (f64, f64) = self.world_to_view_f64(p);
+        return Point::new(x as f32, y as f32);
+    }
+
+    pub fn view_to_world(&self, v: Point) -> WorldPoint {
+        let s: f64 = self.scale();
+        let (sin, cos): (f64, f64) = self.rotation.sin_cos();
+        let (vx, vy): (f64, f64) = (v.x as f64, v.y as f64);
+        return WorldPoint::new(
+            self.center.x + (cos * vx + sin * vy) / s,
+            self.center.y + (-sin * vx + cos * vy) / s
+        );
+    }
+
+    // Pixels from the top left corner of the surface
+    pub fn view_to_pixels(&self, v: Point) -> Point {
+        let (x_norm, y_norm): (f32, f32) = self.normalization();
+        return Point::new(
+            (v.x * x_norm + 1.0) / 2.0 * self.dimensions.x,
+            (1.0 - v.y * y_norm) / 2.0 * self.dimensions.y
+        );
+    }
+
+    pub fn pixels_to_view(&self, px: Point) -> Point {
+        let (x_norm, y_norm): (f32, f32) = self.normalization();
+        return Point::new(
+            (px.x / self.dimensions.x * 2.0 - 1.0) / x_norm,
+            (1.0 - px.y / self.dimensions.y * 2.0) / y_norm
+        );
+    }
+
+    pub fn world_to_pixels(&self, p: WorldPoint) -> Point {
+        return self.view_to_pixels(self.world_to_view(p));
+    }
+
+    pub fn pixels_to_world(&self, px: Point) -> WorldPoint {
+        return self.view_to_world(self.pixels_to_view(px));
+    }
+
+    // Length in view units to pixels
+    pub fn length_to_pixels(&self, len: f32) -> f32 {
+        return len * self.dimensions.x.min(self.dimensions.y) / 2.0;
+    }
+
+    // Move the picture by `dx`, `dy` view units
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        let origin: WorldPoint = self.view_to_world(Point::new(0.0, 0.0));
+        let moved: WorldPoint = self.view_to_world(Point::new(dx, dy));
+        self.center.x -= moved.x - origin.x;
+        self.center.y -= moved.y - origin.y;
+    }
+
+    // Multiply the zoom by `factor`, keeping the world point under the view
+    // position `anchor` where it is. Factors that would leave the zoom
+    // non-finite or not above 0 are ignored.
+    pub fn zoom_at(&mut self, factor: f64, anchor: Point) {
+        let zoom: f64 = self.zoom * factor;
+        if (!zoom.is_finite() || zoom <= 0.0 || !anchor.x.is_finite() || !anchor.y.is_finite()) {
+            return;
+        }
+        let fixed: WorldPoint = self.view_to_world(anchor);
+        self.zoom = zoom;
+        self.keep_at(fixed, anchor);
+    }
+
+    // Center on `center` and zoom so that a box of `half` width and height
+    // in world units, upright on the surface, fits with `padding` view units
+    // to spare on every side. The rotation stays. A box with no size in
+    // either direction only moves the center.
+    pub fn frame(&mut self, center: WorldPoint, half: (f64, f64), padding: f32) {
+        self.center = center;
+        let (ex, ey): (f32, f32) = self.view_extent();
+        let padding: f64 = padding.clamp(0.0, 0.5) as f64;
+        let fit_x: f64 = if (half.0 > 0.0) { (ex as f64 - padding) / half.0 } else { f64::INFINITY };
+        let fit_y: f64 = if (half.1 > 0.0) { (ey as f64 - padding) / half.1 } else { f64::INFINITY };
+        let scale: f64 = fit_x.min(fit_y);
+        if (scale.is_finite()) {
+            self.zoom = scale / DEFAULT_RADIUS as f64;
+        }
+    }
+
+    // Smallest box around `points` that is upright on the surface, as its
+    // middle and half width and height in world units. None without points.
+    pub fn upright_bounds(&self, points: &[WorldPoint]) -> Option<(WorldPoint, (f64, f64))> {
+        if (points.is_empty()) {
+            return None;
+        }
+        let (sin, cos): (f64, f64) = self.rotation.sin_cos();
+        let (mut min, mut max): ((f64, f64), (f64, f64)) = ((f64::INFINITY, f64::INFINITY), (f64::NEG_INFINITY, f64::NEG_INFINITY));
+        for p in points {
+            // Turned the way the camera turns the world
+            let (x, y): (f64, f64) = (cos * p.x - sin * p.y, sin * p.x + cos * p.y);
+            min = (min.0.min(x), min.1.min(y));
+            max = (max.0.max(x), max.1.max(y));
+        }
+        let (mx, my): (f64, f64) = ((min.0 + max.0) / 2.0, (min.1 + max.1) / 2.0);
+        let center: WorldPoint = WorldPoint::new(cos * mx + sin * my, -sin * mx + cos * my);
+        return Some((center, ((max.0 - min.0) / 2.0, (max.1 - min.1) / 2.0)));
+    }
+
+    // Zoom so that the part of the surface between the view positions `a`
+    // and `b` fills it, as far as the aspect ratio allows
+    pub fn zoom_to_view_rect(&mut self, a: Point, b: Point) {
+        let center: WorldPoint = self.view_to_world(Point::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0));
+        let s: f64 = self.scale();
+        let half: (f64, f64) = ((b.x - a.x).abs() as f64 / 2.0 / s, (b.y - a.y).abs() as f64 / 2.0 / s);
+        self.frame(center, half, 0.0);
+    }
+
+    // Rotate the view by `delta` radians around the view position `anchor`
+    pub fn rotate_at(&mut self, delta: f64, anchor: Point) {
+        let fixed: WorldPoint = self.view_to_world(anchor);
+        self.rotation += delta;
+        self.keep_at(fixed, anchor);
+    }
+
+    ///////////////////////
+    // Private functions //
+    ///////////////////////
+
+    // Move the center so that world point `p` shows at view position `v`
+    fn keep_at(&mut self, p: WorldPoint, v: Point) {
+        let now: WorldPoint = self.view_to_world(v);
+        self.center.x += p.x - now.x;
+        self.center.y += p.y - now.y;
+    }
+}
@@ -0,0 +1,206 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Camera motion that continues after the input stops: a pan keeps gliding
+// after a drag is released, slowing down with friction, and wheel zoom
+// eases toward its target instead of jumping there. Each animation frame
+// asks for the next `CameraMove` with `step`, and once `is_active` is false
+// nothing more is needed, so no frames are drawn while idle.
+//
+// With reduced motion both are off: releases stop dead and zooms apply at
+// once.
+
+use std::collections::VecDeque;
+
+use crate::history::Change;
+use crate::input::CameraMove;
+use crate::scene::Point;
+
+pub const DEFAULT_FRICTION: f64 = 0.95;
+
+// Without any friction a glide would never end
+const MIN_FRICTION: f64 = 0.05;
+
+// Only the end of a drag counts towards the release speed, and a pointer
+// held still this long before release doesn't glide at all
+const VELOCITY_WINDOW_MS: f64 = 100.0;
+const STILL_MS: f64 = 50.0;
+
+// Glides stop below this speed, in view units per millisecond. About a
+// pixel per second on a typical surface.
+const MIN_SPEED: f64 = 0.000002;
+
+// Time constant of the zoom easing, the remaining zoom shrinks by a factor
+// of e every this many milliseconds
+const ZOOM_EASE_MS: f64 = 60.0;
+const ZOOM_DONE: f64 = 0.0005; // remaining log zoom considered done
+
+// Frames further apart than this are treated as this far apart, so a
+// glide doesn't jump after the tab was in the background
+const MAX_FRAME_MS: f64 = 100.0;
+
+// Struct declarations
+
+pub struct CameraController {
+    friction: f64, // fraction of the speed lost per second
+    samples: VecDeque<(f64, Point)>, // recent drag moves with their times
+    velocity: Point, // view units per millisecond
+    glide_change: Change, // history kind of the drag the glide continues
+    zoom_left: f64, // zoom factor still to apply
+    zoom_anchor: Point,
+    last_ms: f64 // time of the previous step
+}
+
+// Struct implementations
+
+impl CameraController {
+    pub fn new() -> CameraController {
+        return CameraController {
+            friction: DEFAULT_FRICTION,
+            samples: VecDeque::new(),
+            velocity: Point::new(0.0, 0.0),
+            glide_change: Change::Move,
+            zoom_left: 1.0,
+            zoom_anchor: Point::new(0.0, 0.0),
+            last_ms: 0.0
+        };
+    }
+
+    pub fn friction(&self) -> f64 {
+        return self.friction;
+    }
+
+    // Fraction of the glide speed lost per second, clamped to 0.05..=1.0.
+    // At 1.0 a pan stops dead on release.
+    pub fn set_friction(&mut self, friction: f64) {
+        if (friction.is_nan()) {
+            return;
+        }
+        self.friction = friction.clamp(MIN_FRICTION, 1.0);
+    }
+
+    pub fn is_active(&self) -> bool {
+        return self.is_gliding() || self.zoom_left != 1.0;
+    }
+
+    pub fn is_gliding(&self) -> bool {
+        return self.velocity != Point::new(0.0, 0.0);
+    }
+
+    // Drop any motion left
+    pub fn stop(&mut self) {
+        self.samples.clear();
+        self.velocity = Point::new(0.0, 0.0);
+        self.zoom_left = 1.0;
+    }
+
+    // A drag moved the camera at `now_ms`
+    pub fn track(&mut self, now_ms: f64, camera_move: &CameraMove) {
+        self.samples.push_back((now_ms, camera_move.pan));
+        self.glide_change = camera_move.change();
+        while let Some(&(t, _)) = self.samples.front()
+            && now_ms - t > VELOCITY_WINDOW_MS {
+            self.samples.pop_front();
+        }
+    }
+
+    // The drag ended at `now_ms`, start gliding at the speed it had
+    pub fn release(&mut self, now_ms: f64, reduced_motion: bool) {
+        let samples: Vec<(f64, Point)> = self.samples.drain(..).collect();
+        self.velocity = Point::new(0.0, 0.0);
+        let (Some(&(first_ms, _)), Some(&(last_ms, _))) = (samples.first(), samples.last()) else {
+            return;
+        };
+        if (reduced_motion || self.friction >= 1.0 || now_ms - last_ms > STILL_MS) {
+            return;
+        }
+
+        // The first sample's move happened since the event before it, about
+        // a frame earlier
+        let span: f64 = (now_ms - first_ms).max(16.0);
+        let (dx, dy): (f64, f64) = samples.iter().fold((0.0, 0.0), |(x, y), (_, pan)| (x + pan.x as f64, y + pan.y as f64));
+        if ((dx / span).hypot(dy / span) < MIN_SPEED) {
+            return;
+        }
+        self.velocity = Point::new((dx / span) as f32, (dy / span) as f32);
+        self.last_ms = now_ms;
+    }
+
+    // Zoom by `factor` around `anchor` over the next few frames. Returns the
+    // move to apply right away instead with reduced motion.
+    pub fn zoom_by(&mut self, now_ms: f64, factor: f64, anchor: Point, reduced_motion: bool) -> Option<CameraMove> {
+        if (reduced_motion) {
+            let mut out: CameraMove = CameraMove::new(anchor);
+            out.zoom = factor;
+            return Some(out);
+        }
+        if (!self.is_active()) {
+            self.last_ms = now_ms;
+        }
+        self.zoom_left *= factor;
+        self.zoom_anchor = anchor;
+        return None;
+    }
+
+    // History kind for the moves coming out of `step`
+    pub fn change(&self) -> Change {
+        return if (self.is_gliding()) { self.glide_change } else { Change::Zoom };
+    }
+
+    // Motion between the previous frame and `now_ms`
+    pub fn step(&mut self, now_ms: f64) -> Option<CameraMove> {
+        if (!self.is_active()) {
+            return None;
+        }
+        let dt: f64 = (now_ms - self.last_ms).clamp(0.0, MAX_FRAME_MS);
+        self.last_ms = now_ms;
+        let mut out: CameraMove = CameraMove::new(self.zoom_anchor);
+
+        if (self.is_gliding()) {
+            // Integral of the exponentially decaying speed over the frame
+            let rate: f64 = -(1.0 - self.friction).ln() / 1000.0;
+            let decay: f64 = (-rate * dt).exp();
+            let travel: f64 = if (rate > 0.0) { (1.0 - decay) / rate } else { dt };
+            out.pan = Point::new((self.velocity.x as f64 * travel) as f32, (self.velocity.y as f64 * travel) as f32);
+            self.velocity = Point::new((self.velocity.x as f64 * decay) as f32, (self.velocity.y as f64 * decay) as f32);
+            if ((self.velocity.x as f64).hypot(self.velocity.y as f64) < MIN_SPEED) {
+                self.velocity = Point::new(0.0, 0.0);
+            }
+        }
+
+        if (self.zoom_left != 1.0) {
+            let left: f64 = self.zoom_left.ln();
+            if (left.abs() < ZOOM_DONE) {
+                out.zoom = self.zoom_left;
+                self.zoom_left = 1.0;
+            } else {
+                out.zoom = (left * (1.0 - (-dt / ZOOM_EASE_MS).exp())).exp();
+                self.zoom_left /= out.zoom;
+            }
+        }
+
+        if (out.is_identity()) {
+            return None;
+        }
+        return Some(out);
+    }
+}
+
+impl Default for CameraController {
+    fn default() -> CameraController {
+        return CameraController::new();
+    }
+}
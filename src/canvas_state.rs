@@ -0,0 +1,690 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular 
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Everything behind a `Canvas`. It lives in an `Rc<RefCell<..>>` shared
+// between the JS facing `Canvas` and the event listeners attached to the
+// canvas element, so input can be handled without a round trip through JS.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use web_sys::{Window, MediaQueryList, WebglLoseContext};
+
+use crate::backend::{Capabilities, create_renderer};
+use crate::camera_controller::CameraController;
+use crate::error::ModmulError;
+use crate::events::{ChangeEvent, scene_changes};
+use crate::frame::FrameScheduler;
+use crate::compare::Comparison;
+use crate::gallery::Gallery;
+use crate::history::{Change, History, DEFAULT_HISTORY_LIMIT};
+use crate::input::{GestureMap, PointerTracker, RubberBand};
+use crate::keymap::{Keymap, claim_keys, release_keys};
+use crate::limits;
+use crate::minimap::Minimap;
+use crate::picking::{PickIndex, PickResult};
+use crate::presets::{Preset, PRESETS, find_preset};
+use crate::renderer::{Dirty, Renderer, render};
+use crate::scene::{Point, Scene};
+use crate::surface::{Layout, Surface};
+use crate::transition::Transition;
+
+// How close the cursor has to be to a point or chord for `pick`, in CSS pixels
+const DEFAULT_PICK_TOLERANCE: f32 = 6.0;
+
+// Struct declarations
+
+pub struct CanvasState {
+    pub(crate) id: u32, // unique among the canvases on the page
+    pub(crate) surface: Surface,
+    pub(crate) renderer: Box<dyn Renderer>,
+    pub(crate) capabilities: Capabilities, // what `renderer` runs on, see backend.rs
+    pub(crate) context_lost: bool, // nothing can be drawn until the context is restored
+    pub(crate) lose_context: Option<WebglLoseContext>, // extension that lost it, see `debug_lose_context`
+    pub(crate) scene: Scene,
+    transition: Option<Transition>,
+    history: History,
+    listeners: Vec<(u32, js_sys::Function)>,
+    next_listener_id: u32,
+    pick_index: Option<PickIndex>, // built on the first pick after the figure changes
+    pick_tolerance: f32, // CSS pixels
+    pub(crate) pointers: PointerTracker, // pointers down on the canvas
+    pub(crate) gestures: GestureMap, // what each kind of input does to the camera
+    pub(crate) rubber_band: Option<RubberBand>, // box zoom being dragged out
+    pub(crate) keymap: Keymap,
+    pub(crate) minimap: Minimap,
+    pub(crate) gallery: Option<Gallery>, // shown instead of the full view when set
+    pub(crate) comparison: Option<Comparison>, // second figure shown along with the scene
+    pub(crate) motion: CameraController, // glides and eased zooms still going on
+    pub(crate) frames: FrameScheduler, // animation frames for drawing, `motion` and `transition`
+    drawn: Option<Scene>, // scene as last drawn, none before the first frame
+    motion_query: Option<MediaQueryList>, // matches when the user prefers reduced motion
+    reduced_motion: Option<bool>, // set by the page, overrides `motion_query`
+    notified: Scene, // scene as last reported to the listeners
+    pending: Vec<ChangeEvent> // changes for the listeners, see `dispatch_changes`
+}
+
+// Struct implementations
+
+impl CanvasState {
+    // State drawing into `surface`, sized to its element as laid out
+    pub fn new(surface: Surface) -> Result<CanvasState, ModmulError> {
+        // WebGL2, or WebGL1 where there is none
+        let (renderer, capabilities): (Box<dyn Renderer>, Capabilities) = create_renderer(&surface)?;
+
+        // Workers have no media queries, the page passes the preference on
+        let motion_query: Option<MediaQueryList> = web_sys::window()
+            .and_then(|window: Window| window.match_media("(prefers-reduced-motion: reduce)").ok().flatten());
+
+        // Create scene, dimensions are set by adjusting to the element size below
+        let scene: Scene = Scene::new(0, 0);
+
+        let mut canvas_obj: CanvasState = CanvasState {
+            id: next_id(),
+            surface,
+            renderer,
+            capabilities,
+            context_lost: false,
+            lose_context: None,
+            notified: scene.clone(),
+            scene,
+            transition: None,
+            history: History::new(DEFAULT_HISTORY_LIMIT),
+            listeners: Vec::new(),
+            next_listener_id: 0,
+            pick_index: None,
+            pick_tolerance: DEFAULT_PICK_TOLERANCE,
+            pointers: PointerTracker::new(),
+            gestures: GestureMap::default(),
+            rubber_band: None,
+            keymap: Keymap::default(),
+            minimap: Minimap::new(),
+            gallery: None,
+            comparison: None,
+            motion: CameraController::new(),
+            frames: FrameScheduler::new(),
+            drawn: None,
+            motion_query,
+            reduced_motion: None,
+            pending: Vec::new()
+        };
+
+        // Adjust to element size
+        canvas_obj.resize_to_element();
+
+        // The first canvas on the page takes the keys
+        claim_keys(canvas_obj.id, false);
+
+        // Return self
+        return Ok(canvas_obj);
+    }
+
+    // Start from the view in the page's URL fragment if there is one
+    pub(crate) fn load_url_fragment(&mut self) {
+        let hash: String = web_sys::window().and_then(|window: Window| window.location().hash().ok()).unwrap_or_default();
+        if (hash.len() > 1) && let Err(e) = self.scene.load_fragment(&hash) {
+            web_sys::console::warn_1(&JsValue::from_str(&format!("Ignoring URL fragment: {}", e)));
+        }
+        self.notified = self.scene.clone();
+    }
+
+    // Public functions, each one backs the `Canvas` method of the same name
+
+    // Draw right away, changes are otherwise drawn on the next animation
+    // frame, once however many there were
+    pub fn draw(&mut self) {
+        // Drawn once the context is back, see context_loss.rs
+        if (self.context_lost) {
+            return;
+        }
+        let dirty: Dirty = match &self.drawn {
+            Some(drawn) => Dirty::between(drawn, &self.scene),
+            None => Dirty::all()
+        };
+        self.renderer.invalidate(dirty);
+        if let Some(gallery) = &self.gallery {
+            gallery.draw(&self.scene, self.renderer.as_mut());
+        } else {
+            match &mut self.comparison {
+                Some(comparison) => comparison.draw(&self.scene, self.renderer.as_mut()),
+                None => render(&self.scene, self.renderer.as_mut())
+            }
+            // Split panes have a camera of their own size
+            if (!self.is_split() && self.minimap.shows(&self.scene.camera)) {
+                self.minimap.draw(&self.scene, self.renderer.as_mut());
+            }
+        }
+        self.drawn = Some(self.scene.clone());
+    }
+
+    // Values from JS are checked against the ranges in limits.rs
+    pub fn set_points(&mut self, value: f64) -> Result<(), ModmulError> {
+        let value: u32 = limits::check_points(value)?;
+        self.mutate(Change::Points, |scene: &mut Scene| scene.set_points(value));
+        return Ok(());
+    }
+
+    pub fn set_multiplier(&mut self, value: f64) -> Result<(), ModmulError> {
+        let value: u32 = limits::check_multiplier(value)?;
+        self.mutate(Change::Multiplier, |scene: &mut Scene| scene.set_multiplier(value));
+        return Ok(());
+    }
+
+    pub fn set_rotation(&mut self, deg: f32) -> Result<(), ModmulError> {
+        limits::check_degrees("rotation", deg as f64)?;
+        self.mutate(Change::Rotation, |scene: &mut Scene| scene.set_rotation(deg));
+        return Ok(());
+    }
+
+    pub fn move_shape(&mut self, dx: f32, dy: f32) -> Result<(), ModmulError> {
+        limits::check_finite("dx", dx as f64)?;
+        limits::check_finite("dy", dy as f64)?;
+        self.mutate(Change::Move, |scene: &mut Scene| scene.move_shape(dx, dy));
+        return Ok(());
+    }
+
+    // Size the surface to the element again. Happens by itself when the
+    // element changes size, only needed where that can't be observed.
+    pub fn adjust_view(&mut self) -> Result<bool, ModmulError> {
+        self.resize_to_element();
+        return Ok(true);
+    }
+
+    pub fn reset(&mut self) {
+        self.stop_motion();
+        self.mutate(Change::Reset, |scene: &mut Scene| scene.reset_view());
+    }
+
+    // Zoom by `val` around the cursor location
+    // mx = mouse x, my = mouse y
+    pub fn add_to_r(&mut self, val: f32, mx: f32, my: f32) -> Result<(), ModmulError> {
+        let r: f64 = self.scene.camera.scale();
+        limits::check_zoom_factor((r + limits::check_finite("radius change", val as f64)?) / r)?;
+        limits::check_finite("mx", mx as f64)?;
+        limits::check_finite("my", my as f64)?;
+        self.mutate(Change::Zoom, |scene: &mut Scene| scene.add_to_r(val, mx, my));
+        return Ok(());
+    }
+
+    // Zoom by `factor` around the cursor location, in the same coordinates
+    // as `add_to_r`
+    pub fn zoom_at(&mut self, factor: f64, mx: f32, my: f32) -> Result<(), ModmulError> {
+        limits::check_zoom_factor(factor)?;
+        limits::check_finite("mx", mx as f64)?;
+        limits::check_finite("my", my as f64)?;
+        self.mutate(Change::Zoom, |scene: &mut Scene| scene.zoom_at(factor, mx, my));
+        return Ok(());
+    }
+
+    // 1 is the reset view
+    pub fn get_zoom(&self) -> f64 {
+        return self.scene.camera.zoom;
+    }
+
+    // Rotate the view, unlike `set_rotation` which rotates the figure.
+    // In degrees, around the middle of the surface.
+    pub fn set_view_rotation(&mut self, deg: f64) -> Result<(), ModmulError> {
+        limits::check_degrees("view rotation", deg)?;
+        self.mutate(Change::ViewRotation, |scene: &mut Scene| scene.set_view_rotation(deg));
+        return Ok(());
+    }
+
+    pub fn get_view_rotation(&self) -> f64 {
+        return self.scene.view_rotation_deg();
+    }
+
+    pub fn get_r(&self) -> f32 {
+        return self.scene.get_r();
+    }
+
+    pub fn get_points(&self) -> u32 {
+        return self.scene.shape().points();
+    }
+
+    pub fn get_multiplier(&self) -> u32 {
+        return self.scene.shape().multiplier();
+    }
+
+    // Degrees, like `set_rotation`
+    pub fn get_rotation(&self) -> f32 {
+        return self.scene.rotation_deg();
+    }
+
+    pub fn get_rect_width(&self) -> f32 {
+        return self.scene.shape().rect_width();
+    }
+
+    pub fn get_enable_outline(&self) -> bool {
+        return self.scene.enable_outline();
+    }
+
+    pub fn get_use_rects(&self) -> bool {
+        return self.scene.shape().use_rects();
+    }
+
+    // Save the whole scene as a versioned JSON document
+    pub fn to_json(&self) -> String {
+        return self.scene.to_json();
+    }
+
+    // Presets as an array of {id, name, description, points, multiplier}
+    pub fn list_presets(&self) -> js_sys::Array {
+        let list: js_sys::Array = js_sys::Array::new();
+        for preset in PRESETS {
+            let obj: js_sys::Object = js_sys::Object::new();
+            let _ = js_sys::Reflect::set(&obj, &JsValue::from_str("id"), &JsValue::from_str(preset.id));
+            let _ = js_sys::Reflect::set(&obj, &JsValue::from_str("name"), &JsValue::from_str(preset.name));
+            let _ = js_sys::Reflect::set(&obj, &JsValue::from_str("description"), &JsValue::from_str(preset.description));
+            let _ = js_sys::Reflect::set(&obj, &JsValue::from_str("points"), &JsValue::from(preset.points));
+            let _ = js_sys::Reflect::set(&obj, &JsValue::from_str("multiplier"), &JsValue::from(preset.multiplier));
+            list.push(&obj);
+        }
+        return list;
+    }
+
+    // Switch to a preset. With a positive duration the figure morphs into
+    // the preset over that many milliseconds, driven by `tick`, unless the
+    // user prefers reduced motion.
+    pub fn apply_preset(&mut self, id: &str, duration_ms: f64) -> Result<(), ModmulError> {
+        let preset: &Preset = find_preset(id).ok_or(ModmulError::unknown("preset", id))?;
+        limits::check_duration(duration_ms)?;
+        self.finish_transition();
+        self.stop_motion();
+
+        let mut target: Scene = self.scene.clone();
+        preset.apply(&mut target);
+        let duration_ms: f64 = if (self.reduced_motion()) { 0.0 } else { duration_ms };
+        self.transition_to(Change::Preset, target, duration_ms);
+        return Ok(());
+    }
+
+    // Advance a running transition to `now_ms`, a requestAnimationFrame
+    // timestamp. Returns true while there are more frames to come. Only
+    // needed to step a transition by hand, the canvas runs them itself.
+    pub fn tick(&mut self, now_ms: f64) -> bool {
+        let (frame, done): (Scene, bool) = match &mut self.transition {
+            Some(transition) => transition.sample(now_ms),
+            None => return false
+        };
+        self.scene = frame;
+        self.scene.set_dimensions(self.surface.width(), self.surface.height());
+        if (done) {
+            self.transition = None;
+        }
+
+        // Draw again
+        self.request_draw();
+        self.notify();
+
+        return !done;
+    }
+
+    pub fn is_animating(&self) -> bool {
+        return self.transition.is_some();
+    }
+
+    // Go back to the scene before the last change. Returns false if there
+    // was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        self.finish_transition();
+        return match self.history.undo(&self.scene) {
+            Some(scene) => {
+                self.restore(scene);
+                true
+            }
+            None => false
+        };
+    }
+
+    pub fn redo(&mut self) -> bool {
+        self.finish_transition();
+        return match self.history.redo(&self.scene) {
+            Some(scene) => {
+                self.restore(scene);
+                true
+            }
+            None => false
+        };
+    }
+
+    pub fn can_undo(&self) -> bool {
+        return self.history.can_undo();
+    }
+
+    pub fn can_redo(&self) -> bool {
+        return self.history.can_redo();
+    }
+
+    // Maximum number of undo steps kept
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history.set_limit(limit);
+    }
+
+    pub fn get_history_limit(&self) -> usize {
+        return self.history.limit();
+    }
+
+    // End the current group of merged changes, e.g. when a drag is released
+    pub fn checkpoint(&mut self) {
+        self.history.checkpoint();
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    // Point or chord under the client coordinates `x`, `y`, as in a mouse
+    // event. Points within the tolerance win over chords. Returns undefined
+    // when nothing is close enough.
+    pub fn pick(&mut self, x: f32, y: f32) -> Option<PickResult> {
+        if (self.gallery.is_some() || self.comparison.is_some()) {
+            return None;
+        }
+        let (px, pixel_ratio): (Point, f32) = self.client_to_pixels(x, y)?;
+        if (self.minimap.world_at(&self.scene.camera, px).is_some()) {
+            return None;
+        }
+        if (!self.pick_index.as_ref().is_some_and(|index: &PickIndex| index.matches(&self.scene.shape))) {
+            self.pick_index = Some(PickIndex::new(&self.scene.shape));
+        }
+        let index: &PickIndex = self.pick_index.as_ref()?;
+        return index.pick(&self.scene.shape, &self.scene.camera, px, self.pick_tolerance * pixel_ratio);
+    }
+
+    // How close, in CSS pixels, the cursor has to be for `pick`
+    pub fn set_pick_tolerance(&mut self, pixels: f32) {
+        self.pick_tolerance = pixels.max(0.0);
+    }
+
+    pub fn get_pick_tolerance(&self) -> f32 {
+        return self.pick_tolerance;
+    }
+
+    // Call `callback` with a ChangeEvent for every field of the scene that
+    // changes, whatever the cause. Returns an id for `remove_change_listener`.
+    // The callback runs once the change is done and may call into the canvas.
+    pub fn add_change_listener(&mut self, callback: js_sys::Function) -> u32 {
+        let id: u32 = self.next_listener_id;
+        self.next_listener_id += 1;
+        self.listeners.push((id, callback));
+        return id;
+    }
+
+    // Returns false if there was no listener with this id
+    pub fn remove_change_listener(&mut self, id: u32) -> bool {
+        let count: usize = self.listeners.len();
+        self.listeners.retain(|(listener_id, _)| *listener_id != id);
+        return self.listeners.len() != count;
+    }
+
+    // Encode the scene for the URL fragment, without the leading '#'
+    pub fn to_fragment(&self) -> String {
+        return self.scene.to_fragment();
+    }
+
+    // Apply a fragment written by `to_fragment`, a leading '#' is allowed
+    pub fn load_fragment(&mut self, fragment: &str) -> Result<(), ModmulError> {
+        self.stop_motion();
+        return self.mutate(Change::Load, |scene: &mut Scene| scene.load_fragment(fragment));
+    }
+
+    // Load a document written by `to_json`, including ones from older versions
+    pub fn load_json(&mut self, json: &str) -> Result<(), ModmulError> {
+        self.stop_motion();
+        return self.mutate(Change::Load, |scene: &mut Scene| scene.load_json(json))
+            .map_err(ModmulError::from);
+    }
+
+    pub fn set_enable_outline(&mut self, value: bool) {
+        self.mutate(Change::Outline, |scene: &mut Scene| scene.set_enable_outline(value));
+    }
+
+    pub fn set_use_rects(&mut self, value: bool) {
+        self.mutate(Change::RenderMode, |scene: &mut Scene| scene.set_use_rects(value));
+    }
+
+    pub fn set_rect_width(&mut self, value: f32) -> Result<(), ModmulError> {
+        let value: f32 = limits::check_line_width(value)?;
+        self.mutate(Change::RectWidth, |scene: &mut Scene| scene.set_rect_width(value));
+        return Ok(());
+    }
+
+    // Fraction of the glide speed lost per second after a drag, 0.05..=1.0
+    // where 1.0 stops dead on release
+    pub fn set_friction(&mut self, friction: f64) {
+        self.motion.set_friction(friction);
+    }
+
+    pub fn get_friction(&self) -> f64 {
+        return self.motion.friction();
+    }
+
+    // Animation frame requested through `frames`. Advances whatever is
+    // moving, then draws if anything changed since the last frame.
+    pub fn on_frame(&mut self, now_ms: f64) {
+        if (self.transition.is_some()) {
+            self.tick(now_ms);
+        }
+
+        if (self.motion.is_active()) {
+            let change: Change = self.motion.change();
+            if let Some(camera_move) = self.motion.step(now_ms) {
+                self.mutate(change, |scene: &mut Scene| camera_move.apply(&mut scene.camera));
+            }
+            if (!self.motion.is_active()) {
+                // A glide belongs to the drag before it, end the undo step here
+                self.checkpoint();
+            }
+        }
+
+        if (self.drawn.as_ref() != Some(&self.scene)) {
+            self.draw();
+        }
+
+        // Changes above asked for a frame too, only keep it if still moving
+        if (self.motion.is_active() || self.transition.is_some()) {
+            self.frames.request();
+        } else {
+            self.frames.cancel();
+        }
+    }
+
+    pub fn reduced_motion(&self) -> bool {
+        if let Some(value) = self.reduced_motion {
+            return value;
+        }
+        return self.motion_query.as_ref().is_some_and(|query: &MediaQueryList| query.matches());
+    }
+
+    // Override the user's reduced motion preference, undefined follows it
+    // again
+    pub fn set_reduced_motion(&mut self, value: Option<bool>) {
+        self.reduced_motion = value;
+    }
+
+    // The element of an offscreen surface is now `css_width` by
+    // `css_height` CSS pixels, with `pixel_ratio` device pixels to one
+    pub fn set_layout(&mut self, css_width: f64, css_height: f64, pixel_ratio: f64) {
+        self.surface.set_layout(Layout {
+            css_width,
+            css_height,
+            pixel_ratio
+        });
+        self.resize_to_element();
+    }
+
+    ///////////////////////
+    // Private functions //
+    ///////////////////////
+
+    // Apply `f` to the scene, record the change in the history and draw
+    pub(crate) fn mutate<R>(&mut self, change: Change, f: impl FnOnce(&mut Scene) -> R) -> R {
+        self.finish_transition();
+        let before: Scene = self.scene.clone();
+        let result: R = f(&mut self.scene);
+        if (self.scene != before) {
+            self.history.record(change, &before, js_sys::Date::now());
+        }
+
+        // Draw again
+        self.request_draw();
+        self.notify();
+
+        return result;
+    }
+
+    // Record `change` and go to `target`, morphing over `duration_ms` when
+    // positive. Running transitions and motion should be stopped first, and
+    // the duration checked, one that isn't finite changes at once.
+    pub(crate) fn transition_to(&mut self, change: Change, target: Scene, duration_ms: f64) {
+        if (target == self.scene) {
+            return;
+        }
+        self.history.record(change, &self.scene, js_sys::Date::now());
+        if (duration_ms > 0.0 && duration_ms.is_finite()) {
+            self.transition = Some(Transition::new(self.scene.clone(), target, duration_ms));
+            self.frames.request();
+        } else {
+            self.scene = target;
+
+            // Draw again
+            self.request_draw();
+            self.notify();
+        }
+    }
+
+    // Show a scene from the history, keeping the current surface size
+    fn restore(&mut self, scene: Scene) {
+        self.stop_motion();
+        self.scene = scene;
+        self.scene.set_dimensions(self.surface.width(), self.surface.height());
+
+        // Draw again
+        self.request_draw();
+        self.notify();
+    }
+
+    // Client coordinates to pixels of the drawing surface, along with the
+    // number of surface pixels per CSS pixel
+    pub(crate) fn client_to_pixels(&self, x: f32, y: f32) -> Option<(Point, f32)> {
+        let (left, top, width, height): (f64, f64, f64, f64) = self.surface.client_rect();
+        if (width <= 0.0 || height <= 0.0) {
+            return None;
+        }
+        let ratio: f32 = (self.surface.width() as f64 / width) as f32;
+        let px: Point = Point::new(
+            (x - left as f32) * ratio,
+            (y - top as f32) * (self.surface.height() as f64 / height) as f32
+        );
+        return Some((px, ratio));
+    }
+
+    // Queue everything that changed since the last call for the listeners,
+    // `dispatch_changes` calls them once the state is no longer borrowed
+    fn notify(&mut self) {
+        let events: Vec<ChangeEvent> = scene_changes(&self.notified, &self.scene);
+        if (events.is_empty()) {
+            return;
+        }
+        self.notified = self.scene.clone();
+        self.pending.extend(events);
+    }
+
+    fn request_draw(&mut self) {
+        self.frames.request();
+    }
+
+    // Draw on the next frame even if the scene is as last drawn, for
+    // changes to what is drawn around it
+    pub(crate) fn redraw(&mut self) {
+        self.drawn = None;
+        self.request_draw();
+    }
+
+    // Drop glides and eased zooms, before changes they would fight with
+    pub(crate) fn stop_motion(&mut self) {
+        self.motion.stop();
+    }
+
+    // Jump to the end of a running transition so a direct change starts
+    // from the state the user was heading to
+    pub(crate) fn finish_transition(&mut self) {
+        if let Some(transition) = self.transition.take() {
+            self.scene = transition.target().clone();
+            self.scene.set_dimensions(self.surface.width(), self.surface.height());
+        }
+    }
+
+    // Match the surface to the element's laid out size in device pixels
+    pub(crate) fn resize_to_element(&mut self) {
+        let dpr: f64 = self.surface.pixel_ratio();
+        let (_, _, width, height): (f64, f64, f64, f64) = self.surface.client_rect();
+        let w: u32 = (width * dpr).round() as u32;
+        let h: u32 = (height * dpr).round() as u32;
+        if (w == self.surface.width() && h == self.surface.height() && self.drawn.is_some()) {
+            return;
+        }
+        self.surface.set_size(w, h);
+
+        // Update renderer and scene according to new dimensions
+        self.renderer.resize(self.surface.width(), self.surface.height());
+        self.scene.set_dimensions(self.surface.width(), self.surface.height());
+
+        // Resizing clears the canvas
+        self.redraw();
+    }
+}
+
+impl Drop for CanvasState {
+    fn drop(&mut self) {
+        release_keys(self.id);
+    }
+}
+
+// Helper functions
+
+// Call the change listeners with the changes `notify` queued. Listeners may
+// call back into the canvas, so this has to run with `state` not borrowed.
+// If it still is, the changes wait for whoever borrowed it.
+pub(crate) fn dispatch_changes(state: &Rc<RefCell<CanvasState>>) {
+    let (events, callbacks): (Vec<ChangeEvent>, Vec<js_sys::Function>) = match state.try_borrow_mut() {
+        Ok(mut state) if (!state.pending.is_empty()) => (
+            std::mem::take(&mut state.pending),
+            state.listeners.iter().map(|(_, callback)| callback.clone()).collect()
+        ),
+        _ => return
+    };
+    for event in events {
+        for callback in &callbacks {
+            if let Err(e) = callback.call1(&JsValue::NULL, &JsValue::from(event.clone())) {
+                web_sys::console::warn_2(&JsValue::from_str("Change listener failed:"), &e);
+            }
+        }
+    }
+}
+
+// Identifies each canvas on the page, see `keymap::claim_keys`
+fn next_id() -> u32 {
+    thread_local! {
+        static NEXT_ID: Cell<u32> = const { Cell::new(1) };
+    }
+    return NEXT_ID.with(|next: &Cell<u32>| {
+        let id: u32 = next.get();
+        next.set(id + 1);
+        return id;
+    });
+}
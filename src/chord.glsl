@@ -0,0 +1,83 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular 
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Where chords end, shared by the line and rectangle shaders. The WEBGL1
+// switch works in floats instead, GLSL 100 ints can be as narrow as 17 bits.
+
+#ifdef WEBGL1
+
+// (a + b) mod n for whole numbers a, b < n, never going past n so every
+// value stays an exact float
+float add_mod(float a, float b, float n) {
+    if (a >= n - b) {
+        return a - (n - b);
+    }
+    return a + b;
+}
+
+// (a * b) mod n for whole numbers a, b < n <= 2^24, doubling and adding
+// one bit of b at a time
+float mul_mod(float a, float b, float n) {
+    float result = 0.0;
+    float rest = b;
+    float bit = 8388608.0; // 2^23
+    for (int i = 0; i < 24; i++) {
+        result = add_mod(result, result, n);
+        if (rest >= bit) {
+            rest -= bit;
+            result = add_mod(result, a, n);
+        }
+        bit *= 0.5;
+    }
+    return result;
+}
+
+// Point chord `i` ends at, between two points while a transition makes the
+// multiplier fractional
+float chord_end(float i, float multiplier, float multiplier_frac, float n) {
+    float end = mul_mod(i, multiplier, n);
+    if (multiplier_frac != 0.0) {
+        end = mod(end + i * multiplier_frac, n);
+    }
+    return end;
+}
+
+#else
+
+// (a * b) % n for a, b < n <= 2^24 without overflowing int. Small figures
+// take the product directly, larger ones double and add one bit of b at a
+// time so nothing ever exceeds 2n.
+int mul_mod(int a, int b, int n) {
+    if (n <= 46340) {
+        return (a * b) % n;
+    }
+    int result = 0;
+    for (int bit = 23; bit >= 0; bit--) {
+        result = (result * 2) % n;
+        if (((b >> bit) & 1) == 1) {
+            result = (result + a) % n;
+        }
+    }
+    return result;
+}
+
+// Point chord `i` ends at, between two points while a transition makes the
+// multiplier fractional
+float chord_end(int i, int multiplier, float multiplier_frac, int n) {
+    return mod(float(mul_mod(i, multiplier, n)) + float(i) * multiplier_frac, float(n));
+}
+
+#endif
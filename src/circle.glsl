@@ -0,0 +1,34 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular 
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Where the points of a figure sit, shared by every shader drawing them
+
+#define PI 3.1415926535897932384626
+
+// Angle of point `i` of `n`, point 0 at the top
+float point_angle(float i, float n, float rotation) {
+    return i * 2.0 * PI / n + rotation + (PI / 2.0);
+}
+
+// Point on the unit circle, mirrored so the points go around clockwise
+vec2 circle_point(float theta) {
+    return vec2(-1.0 * cos(theta), sin(theta));
+}
+
+// Clip to view coordinates, where both axes have the same scale
+vec2 clip_to_view(vec2 dimensions) {
+    return dimensions / min(dimensions.x, dimensions.y);
+}
@@ -1,7 +1,9 @@
 #version 300 es
 precision mediump float;
 uniform vec3 u_color;
+#ifndef WEBGL1
 out vec4 outColor;
+#endif
 
 /*
 This file is part of Modular Multiplication WebGL.
@@ -20,5 +22,9 @@ Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
 */
 
 void main() {
+#ifdef WEBGL1
+    gl_FragColor = vec4(u_color, 1.0);
+#else
     outColor = vec4(u_color, 1.0);
+#endif
 }
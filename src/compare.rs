@@ -0,0 +1,276 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Comparing the figure with a second one that has its own point count and
+// multiplier, through the same camera. Figure A is the scene, B only differs
+// in those two numbers.
+//
+// Modes, as seen from JS:
+//   "split"        A and B in two panes, side by side on a wide surface and
+//                  stacked on a tall one. Dragging in either moves both.
+//   "difference"   one view with the chords only A has in one color, the
+//                  ones only B has in another and the shared ones dimmed.
+//                  See `geometry::compare_chords`.
+
+use wasm_bindgen::prelude::*;
+
+use crate::camera::Camera;
+use crate::canvas_state::CanvasState;
+use crate::error::ModmulError;
+use crate::geometry::{self, ChordDiff};
+use crate::limits;
+use crate::renderer::{Dirty, Renderer, render};
+use crate::scene::{Color, Point, Scene};
+
+const DEFAULT_ONLY_A: Color = Color {r: 255, g: 140, b: 60};
+const DEFAULT_ONLY_B: Color = Color {r: 60, g: 190, b: 255};
+const SHARED_DIM: f32 = 0.3; // shared chords, this far from the background to the foreground
+
+const DIVIDER_PX: u32 = 2; // between the panes
+const LABEL_PX: f32 = 16.0;
+
+// Struct declarations
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompareMode {
+    Split,
+    Difference
+}
+
+pub struct Comparison {
+    mode: CompareMode,
+    points: u32, // of figure B
+    mul: u32,
+    only_a: Color,
+    only_b: Color,
+    diff: Option<((u32, u32, u32, u32), ChordDiff)> // last comparison and the figures it was for
+}
+
+// Struct implementations
+
+impl CompareMode {
+    pub fn from_name(name: &str) -> Option<CompareMode> {
+        return match name {
+            "split" => Some(CompareMode::Split),
+            "difference" => Some(CompareMode::Difference),
+            _ => None
+        };
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            CompareMode::Split => "split",
+            CompareMode::Difference => "difference"
+        };
+    }
+}
+
+impl Comparison {
+    pub fn new(mode: CompareMode, points: u32, mul: u32) -> Comparison {
+        return Comparison {
+            mode,
+            points,
+            mul,
+            only_a: DEFAULT_ONLY_A,
+            only_b: DEFAULT_ONLY_B,
+            diff: None
+        };
+    }
+
+    pub fn mode(&self) -> CompareMode {
+        return self.mode;
+    }
+
+    // Figure B for `scene`
+    pub fn other(&self, scene: &Scene) -> Scene {
+        let mut out: Scene = scene.clone();
+        out.set_points(self.points);
+        out.set_multiplier(self.mul);
+        return out;
+    }
+
+    // Left, top, width and height of the panes in pixels, A first
+    pub fn panes(&self, dimensions: Point) -> [(u32, u32, u32, u32); 2] {
+        let (w, h): (u32, u32) = (dimensions.x as u32, dimensions.y as u32);
+        if (w >= h) {
+            let pane: u32 = w.saturating_sub(DIVIDER_PX) / 2;
+            return [(0, 0, pane, h), (w - pane, 0, pane, h)];
+        }
+        let pane: u32 = h.saturating_sub(DIVIDER_PX) / 2;
+        return [(0, 0, w, pane), (0, h - pane, w, pane)];
+    }
+
+    // Size of each pane, none when not split
+    pub fn pane_size(&self, dimensions: Point) -> Option<(u32, u32)> {
+        if (self.mode != CompareMode::Split) {
+            return None;
+        }
+        let (_, _, w, h): (u32, u32, u32, u32) = self.panes(dimensions)[0];
+        return Some((w, h));
+    }
+
+    // `camera` sized to the pane at surface pixel `px`, and the top left
+    // corner of that pane. None outside the panes or when not split.
+    pub fn pane_at(&self, camera: &Camera, px: Point) -> Option<(Camera, Point)> {
+        if (self.mode != CompareMode::Split) {
+            return None;
+        }
+        for (x, y, w, h) in self.panes(camera.dimensions()) {
+            let (left, top): (f32, f32) = (x as f32, y as f32);
+            if (px.x >= left && px.y >= top && px.x < left + w as f32 && px.y < top + h as f32) {
+                let mut pane: Camera = camera.clone();
+                pane.set_dimensions(w, h);
+                return Some((pane, Point::new(left, top)));
+            }
+        }
+        return None;
+    }
+
+    // Chords of `scene` compared with figure B
+    pub fn diff(&mut self, scene: &Scene) -> &ChordDiff {
+        let key: (u32, u32, u32, u32) = (scene.shape.points, scene.shape.mul, self.points, self.mul);
+        if (self.diff.as_ref().is_some_and(|(k, _)| *k != key)) {
+            self.diff = None;
+        }
+        let (_, diff): &mut (_, ChordDiff) = self.diff.get_or_insert_with(|| (key, geometry::compare_chords(key.0, key.1, key.2, key.3)));
+        return diff;
+    }
+
+    pub fn draw(&mut self, scene: &Scene, renderer: &mut dyn Renderer) {
+        match self.mode {
+            CompareMode::Split => self.draw_split(scene, renderer),
+            CompareMode::Difference => self.draw_difference(scene, renderer)
+        }
+    }
+
+    ///////////////////////
+    // Private functions //
+    ///////////////////////
+
+    fn draw_split(&self, scene: &Scene, renderer: &mut dyn Renderer) {
+        // What shows between the panes is the divider
+        renderer.clear(&scene.bg.mix(&scene.shape.color, SHARED_DIM));
+
+        let other: Scene = self.other(scene);
+        for ((x, y, w, h), figure) in self.panes(scene.camera.dimensions()).into_iter().zip([scene, &other]) {
+            let mut pane: Scene = figure.clone();
+            pane.camera.set_dimensions(w, h);
+
+            // The panes differ in figure and surface, and at deep zoom in
+            // the vertices too
+            renderer.invalidate(Dirty {
+                colors: false,
+                ..Dirty::all()
+            });
+            renderer.set_viewport(x, y, w, h);
+            render(&pane, renderer);
+            renderer.reset_viewport();
+
+            let label: String = format!("n = {}, k = {}", pane.shape.points, pane.shape.mul);
+            renderer.draw_text(&label, x as f32 + LABEL_PX, y as f32 + LABEL_PX / 2.0, LABEL_PX, &scene.shape.color);
+        }
+
+        // Left as the last pane had them
+        renderer.invalidate(Dirty::all());
+    }
+
+    fn draw_difference(&mut self, scene: &Scene, renderer: &mut dyn Renderer) {
+        renderer.clear(&scene.bg);
+        if (scene.enable_outline) {
+            renderer.draw_outline(&scene.shape, &scene.camera, &scene.bg);
+        }
+
+        let other: Scene = self.other(scene);
+        let (only_a, only_b): (Color, Color) = (self.only_a, self.only_b);
+        let diff: &ChordDiff = self.diff(scene);
+        let camera: &Camera = &scene.camera;
+        let shared: Vec<(Point, Point)> = geometry::visible_chord_subset(&scene.shape, camera, 0.0, diff.shared.iter().copied());
+        let a: Vec<(Point, Point)> = geometry::visible_chord_subset(&scene.shape, camera, 0.0, diff.only_a.iter().copied());
+        let b: Vec<(Point, Point)> = geometry::visible_chord_subset(&other.shape, camera, 0.0, diff.only_b.iter().copied());
+        renderer.draw_segments(&shared, camera, &scene.bg.mix(&scene.shape.color, SHARED_DIM));
+        renderer.draw_segments(&a, camera, &only_a);
+        renderer.draw_segments(&b, camera, &only_b);
+    }
+}
+
+impl CanvasState {
+    // Compare the figure with one of `points` points and multiplier
+    // `multiplier`, modes are listed at the top of this file
+    pub fn show_comparison(&mut self, points: u32, multiplier: u32, mode: &str) -> Result<(), ModmulError> {
+        let mode: CompareMode = CompareMode::from_name(mode).ok_or(ModmulError::unknown("comparison mode", mode))?;
+        let points: u32 = limits::check_points(points as f64)?;
+        self.comparison = Some(Comparison::new(mode, points, multiplier));
+        self.redraw();
+        return Ok(());
+    }
+
+    pub fn close_comparison(&mut self) {
+        if (self.comparison.take().is_some()) {
+            self.redraw();
+        }
+    }
+
+    // Mode name, undefined when not comparing
+    pub fn get_comparison_mode(&self) -> Option<String> {
+        return self.comparison.as_ref().map(|comparison: &Comparison| String::from(comparison.mode().name()));
+    }
+
+    // Colors of the chords only A and only B have, as "#rrggbb"
+    pub fn set_comparison_colors(&mut self, only_a: &str, only_b: &str) -> Result<(), ModmulError> {
+        let a: Color = Color::from_hex(only_a).ok_or(ModmulError::InvalidColor(String::from(only_a)))?;
+        let b: Color = Color::from_hex(only_b).ok_or(ModmulError::InvalidColor(String::from(only_b)))?;
+        let comparison: &mut Comparison = self.comparison.as_mut().ok_or(ModmulError::Invalid(String::from("Not comparing")))?;
+        comparison.only_a = a;
+        comparison.only_b = b;
+        self.redraw();
+        return Ok(());
+    }
+
+    pub(crate) fn is_split(&self) -> bool {
+        return self.comparison.as_ref().is_some_and(|comparison: &Comparison| comparison.mode() == CompareMode::Split);
+    }
+
+    // Camera the surface pixel `px` is seen through, and `px` relative to
+    // what that camera covers. Each pane has its own while split, moving
+    // either moves the scene's.
+    pub(crate) fn camera_at(&self, px: Point) -> (Camera, Point) {
+        let pane: Option<(Camera, Point)> = self.comparison.as_ref()
+            .and_then(|comparison: &Comparison| comparison.pane_at(&self.scene.camera, px));
+        return match pane {
+            Some((camera, origin)) => (camera, Point::new(px.x - origin.x, px.y - origin.y)),
+            None => (self.scene.camera.clone(), px)
+        };
+    }
+
+    // Size one view of the figure is drawn at, a pane's while split
+    pub(crate) fn view_size(&self) -> (u32, u32) {
+        let dimensions: Point = self.scene.camera.dimensions();
+        return self.comparison.as_ref()
+            .and_then(|comparison: &Comparison| comparison.pane_size(dimensions))
+            .unwrap_or((dimensions.x as u32, dimensions.y as u32));
+    }
+
+    // Chord counts as {only_a, only_b, shared}, undefined when not comparing
+    pub fn get_comparison_counts(&mut self) -> Option<js_sys::Object> {
+        let comparison: &mut Comparison = self.comparison.as_mut()?;
+        let diff: &ChordDiff = comparison.diff(&self.scene);
+        let obj: js_sys::Object = js_sys::Object::new();
+        let _ = js_sys::Reflect::set(&obj, &JsValue::from_str("only_a"), &JsValue::from(diff.only_a.len() as u32));
+        let _ = js_sys::Reflect::set(&obj, &JsValue::from_str("only_b"), &JsValue::from(diff.only_b.len() as u32));
+        let _ = js_sys::Reflect::set(&obj, &JsValue::from_str("shared"), &JsValue::from(diff.shared.len() as u32));
+        return Some(obj);
+    }
+}
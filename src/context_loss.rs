@@ -0,0 +1,147 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Surviving the loss of the WebGL context, e.g. when the GPU process resets
+// or the browser reclaims contexts of background tabs. Everything on the GPU
+// is gone then: shaders, programs, buffers and the label texture. Nothing is
+// drawn while the context is lost, the scene keeps changing as usual, and
+// once the browser restores the context a new renderer is built and the
+// scene as it is by then gets drawn.
+//
+// `debug_lose_context` and `debug_restore_context` go through the
+// WEBGL_lose_context extension to try this without a GPU reset.
+
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Event, EventTarget, WebglLoseContext};
+
+use crate::backend::{lose_context_extension, recreate_renderer};
+use crate::canvas_state::{CanvasState, dispatch_changes};
+use crate::error::ModmulError;
+use crate::renderer::Renderer;
+
+type Listener = Closure<dyn FnMut(Event)>;
+
+// Struct declarations
+
+// Context loss listeners on the surface, removed again on drop
+pub struct ContextWatcher {
+    target: EventTarget,
+    lost: Listener,
+    restored: Listener
+}
+
+// Struct implementations
+
+impl ContextWatcher {
+    pub fn attach(state: &Rc<RefCell<CanvasState>>) -> Result<ContextWatcher, JsValue> {
+        let target: EventTarget = state.borrow().surface.event_target();
+
+        // Unless the default is prevented, the context is never restored
+        let weak: Weak<RefCell<CanvasState>> = Rc::downgrade(state);
+        let lost: Listener = Closure::new(move |e: Event| {
+            e.prevent_default();
+            if let Some(state) = weak.upgrade() {
+                if let Ok(mut state) = state.try_borrow_mut() {
+                    state.on_context_lost();
+                }
+                dispatch_changes(&state);
+            }
+        });
+        let weak: Weak<RefCell<CanvasState>> = Rc::downgrade(state);
+        let restored: Listener = Closure::new(move |_: Event| {
+            if let Some(state) = weak.upgrade() {
+                if let Ok(mut state) = state.try_borrow_mut() {
+                    state.on_context_restored();
+                }
+                dispatch_changes(&state);
+            }
+        });
+        target.add_event_listener_with_callback("webglcontextlost", lost.as_ref().unchecked_ref())?;
+        target.add_event_listener_with_callback("webglcontextrestored", restored.as_ref().unchecked_ref())?;
+        return Ok(ContextWatcher {
+            target,
+            lost,
+            restored
+        });
+    }
+}
+
+impl Drop for ContextWatcher {
+    fn drop(&mut self) {
+        let _ = self.target.remove_event_listener_with_callback("webglcontextlost", self.lost.as_ref().unchecked_ref());
+        let _ = self.target.remove_event_listener_with_callback("webglcontextrestored", self.restored.as_ref().unchecked_ref());
+    }
+}
+
+impl CanvasState {
+    pub fn is_context_lost(&self) -> bool {
+        return self.context_lost;
+    }
+
+    // Lose the WebGL context as if the GPU had reset, for testing. The
+    // browser doesn't restore it by itself, see `debug_restore_context`.
+    pub fn debug_lose_context(&mut self) -> Result<(), ModmulError> {
+        let extension: WebglLoseContext = self.lose_context_extension()?;
+        extension.lose_context();
+        self.lose_context = Some(extension);
+        return Ok(());
+    }
+
+    // Give back a context lost through `debug_lose_context`
+    pub fn debug_restore_context(&mut self) -> Result<(), ModmulError> {
+        let extension: &WebglLoseContext = self.lose_context.as_ref()
+            .ok_or(ModmulError::Invalid(String::from("The context was not lost through debug_lose_context")))?;
+        extension.restore_context();
+        return Ok(());
+    }
+
+    pub(crate) fn on_context_lost(&mut self) {
+        self.context_lost = true;
+        web_sys::console::warn_1(&JsValue::from_str("WebGL context lost, waiting for it to be restored"));
+    }
+
+    // Everything the old renderer had on the GPU is gone, so build it anew,
+    // on the same kind of context
+    pub(crate) fn on_context_restored(&mut self) {
+        let renderer: Result<Box<dyn Renderer>, ModmulError> = recreate_renderer(&self.surface, self.capabilities.backend);
+        match renderer {
+            Ok(renderer) => {
+                self.renderer = renderer;
+                self.renderer.resize(self.surface.width(), self.surface.height());
+                self.context_lost = false;
+                self.redraw();
+            }
+            Err(e) => {
+                web_sys::console::error_1(&JsValue::from_str(&format!("Unable to restore the WebGL context: {}", e)));
+            }
+        }
+    }
+
+    ///////////////////////
+    // Private functions //
+    ///////////////////////
+
+    fn lose_context_extension(&self) -> Result<WebglLoseContext, ModmulError> {
+        if let Some(extension) = &self.lose_context {
+            return Ok(extension.clone());
+        }
+        return lose_context_extension(&self.surface, self.capabilities.backend);
+    }
+}
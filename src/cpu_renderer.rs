@@ -0,0 +1,253 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Software rasterizer producing an RGB image, used for headless PNG output
+
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont, point};
+
+use crate::camera::{Camera, WorldPoint};
+use crate::geometry;
+use crate::renderer::Renderer;
+use crate::scene::{Color, Point, Shape};
+
+// Struct declarations
+
+pub struct CpuRenderer {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>, // RGB, row major from the top left corner
+    font: FontRef<'static>
+}
+
+// Struct implementations
+
+impl CpuRenderer {
+    pub fn new(width: u32, height: u32) -> CpuRenderer {
+        let font: FontRef<'static> = FontRef::try_from_slice(include_bytes!("../NotoSans-Regular-subset.ttf"))
+            .expect("bundled font is valid");
+        return CpuRenderer {
+            width,
+            height,
+            pixels: vec![0; (width as usize) * (height as usize) * 3],
+            font
+        };
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        return &self.pixels;
+    }
+
+    pub fn encode_png(&self) -> Result<Vec<u8>, String> {
+        let mut out: Vec<u8> = Vec::new();
+        {
+            let mut encoder: png::Encoder<&mut Vec<u8>> = png::Encoder::new(&mut out, self.width, self.height);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+            writer.write_image_data(&self.pixels).map_err(|e| e.to_string())?;
+        }
+        return Ok(out);
+    }
+
+    ///////////////////////
+    // Private functions //
+    ///////////////////////
+
+    // Mix `color` into the pixel at (x, y) by `coverage` (0.0 to 1.0)
+    fn blend(&mut self, x: i64, y: i64, color: (f32, f32, f32), coverage: f32) {
+        if (x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 || coverage <= 0.0) {
+            return;
+        }
+        let a: f32 = coverage.min(1.0);
+        let i: usize = ((y as usize) * (self.width as usize) + (x as usize)) * 3;
+        let src: [f32; 3] = [color.0 * 255.0, color.1 * 255.0, color.2 * 255.0];
+        for (c, value) in src.iter().enumerate() {
+            let dst: f32 = self.pixels[i + c] as f32;
+            self.pixels[i + c] = (dst + (value - dst) * a).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+
+    // Rectangle of `width` pixels with `a` and `b` at the middle of its short sides.
+    // Coverage is the exact overlap of each pixel with the rectangle along
+    // both of its axes, which gives cheap but decent antialiasing.
+    fn fill_segment(&mut self, a: Point, b: Point, width: f32, color: (f32, f32, f32)) {
+        let dx: f32 = b.x - a.x;
+        let dy: f32 = b.y - a.y;
+        let len: f32 = (dx * dx + dy * dy).sqrt();
+        if (len < 1e-6 || !len.is_finite()) {
+            return;
+        }
+        let ux: f32 = dx / len;
+        let uy: f32 = dy / len;
+        let half_w: f32 = width / 2.0;
+
+        let visit = |s: &mut CpuRenderer, px: i64, py: i64| {
+            let cx: f32 = px as f32 + 0.5 - a.x;
+            let cy: f32 = py as f32 + 0.5 - a.y;
+            let t: f32 = cx * ux + cy * uy; // along the segment
+            let n: f32 = -cx * uy + cy * ux; // across the segment
+            let cov_t: f32 = overlap(t - 0.5, t + 0.5, 0.0, len);
+            let cov_n: f32 = overlap(n - 0.5, n + 0.5, -half_w, half_w);
+            s.blend(px, py, color, cov_t * cov_n);
+        };
+
+        // Walk along the major axis and only visit the band of pixels around the segment
+        if (dx.abs() >= dy.abs()) {
+            let ext: f32 = half_w / ux.abs() + 1.0;
+            let (x0, x1): (f32, f32) = (a.x.min(b.x) - ext, a.x.max(b.x) + ext);
+            let x_start: i64 = (x0.floor() as i64).max(0);
+            let x_end: i64 = (x1.ceil() as i64).min(self.width as i64 - 1);
+            for px in x_start..=x_end {
+                let t: f32 = ((px as f32 + 0.5 - a.x) / dx).clamp(0.0, 1.0);
+                let yc: f32 = a.y + t * dy;
+                let y_start: i64 = ((yc - ext).floor() as i64).max(0);
+                let y_end: i64 = ((yc + ext).ceil() as i64).min(self.height as i64 - 1);
+                for py in y_start..=y_end {
+                    visit(self, px, py);
+                }
+            }
+        } else {
+            let ext: f32 = half_w / uy.abs() + 1.0;
+            let (y0, y1): (f32, f32) = (a.y.min(b.y) - ext, a.y.max(b.y) + ext);
+            let y_start: i64 = (y0.floor() as i64).max(0);
+            let y_end: i64 = (y1.ceil() as i64).min(self.height as i64 - 1);
+            for py in y_start..=y_end {
+                let t: f32 = ((py as f32 + 0.5 - a.y) / dy).clamp(0.0, 1.0);
+                let xc: f32 = a.x + t * dx;
+                let x_start: i64 = ((xc - ext).floor() as i64).max(0);
+                let x_end: i64 = ((xc + ext).ceil() as i64).min(self.width as i64 - 1);
+                for px in x_start..=x_end {
+                    visit(self, px, py);
+                }
+            }
+        }
+    }
+
+    fn fill_square(&mut self, center: Point, size: f32, color: (f32, f32, f32)) {
+        let half: f32 = size / 2.0;
+        let x_start: i64 = ((center.x - half).floor() as i64).max(0);
+        let x_end: i64 = ((center.x + half).ceil() as i64).min(self.width as i64 - 1);
+        let y_start: i64 = ((center.y - half).floor() as i64).max(0);
+        let y_end: i64 = ((center.y + half).ceil() as i64).min(self.height as i64 - 1);
+        for py in y_start..=y_end {
+            for px in x_start..=x_end {
+                let cov_x: f32 = overlap(px as f32, px as f32 + 1.0, center.x - half, center.x + half);
+                let cov_y: f32 = overlap(py as f32, py as f32 + 1.0, center.y - half, center.y + half);
+                self.blend(px, py, color, cov_x * cov_y);
+            }
+        }
+    }
+
+    fn fill_disc(&mut self, center: Point, radius: f32, color: (f32, f32, f32)) {
+        if (radius <= 0.0) {
+            return;
+        }
+        let x_start: i64 = ((center.x - radius - 1.0).floor() as i64).max(0);
+        let x_end: i64 = ((center.x + radius + 1.0).ceil() as i64).min(self.width as i64 - 1);
+        let y_start: i64 = ((center.y - radius - 1.0).floor() as i64).max(0);
+        let y_end: i64 = ((center.y + radius + 1.0).ceil() as i64).min(self.height as i64 - 1);
+        for py in y_start..=y_end {
+            for px in x_start..=x_end {
+                let ddx: f32 = px as f32 + 0.5 - center.x;
+                let ddy: f32 = py as f32 + 0.5 - center.y;
+                let d: f32 = (ddx * ddx + ddy * ddy).sqrt();
+                self.blend(px, py, color, (radius + 0.5 - d).clamp(0.0, 1.0));
+            }
+        }
+    }
+}
+
+impl Renderer for CpuRenderer {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.pixels = vec![0; (width as usize) * (height as usize) * 3];
+    }
+
+    fn clear(&mut self, color: &Color) {
+        for px in self.pixels.chunks_exact_mut(3) {
+            px[0] = color.r;
+            px[1] = color.g;
+            px[2] = color.b;
+        }
+    }
+
+    fn draw_outline(&mut self, shape: &Shape, camera: &Camera, bg: &Color) {
+        let center: Point = geometry::to_pixels(camera, WorldPoint::new(0.0, 0.0));
+        let r: f32 = geometry::length_to_pixels(camera, camera.scale() as f32);
+        let half_w: f32 = geometry::length_to_pixels(camera, shape.outline_width / 2.0);
+        self.fill_disc(center, r + half_w, shape.color.to_f32());
+        self.fill_disc(center, r - half_w, bg.to_f32());
+    }
+
+    fn draw_chords(&mut self, shape: &Shape, camera: &Camera) {
+        // "Lines" are the 1px wide lines WebGL draws
+        let width: f32 = if (shape.use_rects_instead_of_lines) {
+            geometry::length_to_pixels(camera, shape.rect_width)
+        } else {
+            1.0
+        };
+        let color: (f32, f32, f32) = shape.color.to_f32();
+        for i in 0..shape.points {
+            let (src, dst): (WorldPoint, WorldPoint) = geometry::chord(shape, i);
+            self.fill_segment(geometry::to_pixels(camera, src), geometry::to_pixels(camera, dst), width, color);
+        }
+    }
+
+    fn draw_points(&mut self, shape: &Shape, camera: &Camera) {
+        let color: (f32, f32, f32) = shape.color.to_f32();
+        for i in 0..shape.points {
+            let p: Point = geometry::to_pixels(camera, geometry::point_position(shape, i));
+            self.fill_square(p, shape.point_size, color);
+        }
+    }
+
+    fn draw_text(&mut self, text: &str, x: f32, y: f32, size: f32, color: &Color) {
+        let scaled = self.font.as_scaled(PxScale::from(size));
+        let baseline: f32 = y + scaled.ascent();
+        let rgb: (f32, f32, f32) = color.to_f32();
+
+        let mut caret: f32 = x;
+        let mut prev = None;
+        let mut coverage: Vec<(i64, i64, f32)> = Vec::new();
+        for c in text.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(prev_id) = prev {
+                caret += scaled.kern(prev_id, id);
+            }
+            let glyph = id.with_scale_and_position(PxScale::from(size), point(caret, baseline));
+            caret += scaled.h_advance(id);
+            prev = Some(id);
+
+            if let Some(outlined) = self.font.outline_glyph(glyph) {
+                let bounds = outlined.px_bounds();
+                outlined.draw(|gx: u32, gy: u32, c: f32| {
+                    coverage.push((bounds.min.x as i64 + gx as i64, bounds.min.y as i64 + gy as i64, c));
+                });
+            }
+        }
+        for (px, py, c) in coverage {
+            self.blend(px, py, rgb, c);
+        }
+    }
+}
+
+// Helper functions
+
+// Length of the overlap between the ranges [a0, a1] and [b0, b1]
+fn overlap(a0: f32, a1: f32, b0: f32, b1: f32) -> f32 {
+    return (a1.min(b1) - a0.max(b0)).max(0.0);
+}
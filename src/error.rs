@@ -0,0 +1,160 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Everything that can go wrong in a call from JS. Nothing in the crate
+// panics on bad input or an unusual browser, it returns one of these.
+//
+// JS gets an `Error` named "ModmulError" with the message below, a `code`
+// to tell the cases apart and the details of the case as properties:
+//   "no_window"              not running on a page
+//   "no_document"
+//   "element_not_found"      id
+//   "not_a_canvas"           id
+//   "not_in_document"        the canvas element isn't attached to a page
+//   "webgl2_unsupported"     asked for WebGL2 only, see backend.rs
+//   "webgl_unsupported"      neither WebGL2 nor WebGL1 is available
+//   "shader_compile"         shader (file name), log with locations in the original files, see glsl.rs
+//   "link"                   program, log
+//   "missing_uniform"        program, uniform
+//   "missing_attribute"      program, attribute
+//   "create_failed"          what, e.g. "buffer", usually a lost context
+//   "extension_unsupported"  extension
+//   "unknown_name"           kind, name, e.g. a preset or gesture that doesn't exist
+//   "invalid_color"          value
+//   "out_of_range"           name, value, min, max, see limits.rs
+//   "invalid"                anything else the call can't use, the message says why
+
+use std::fmt;
+
+use wasm_bindgen::prelude::*;
+
+// Struct declarations
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ModmulError {
+    NoWindow,
+    NoDocument,
+    ElementNotFound(String),
+    NotACanvas(String),
+    NotInDocument,
+    WebGl2Unsupported,
+    WebGlUnsupported,
+    ShaderCompile {shader: &'static str, log: String},
+    Link {program: &'static str, log: String},
+    MissingUniform {program: &'static str, uniform: &'static str},
+    MissingAttribute {program: &'static str, attribute: &'static str},
+    CreateFailed(&'static str),
+    ExtensionUnsupported(&'static str),
+    UnknownName {kind: &'static str, name: String},
+    InvalidColor(String),
+    OutOfRange {name: &'static str, value: f64, min: f64, max: f64},
+    Invalid(String)
+}
+
+// Struct implementations
+
+impl ModmulError {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ModmulError::NoWindow => "no_window",
+            ModmulError::NoDocument => "no_document",
+            ModmulError::ElementNotFound(_) => "element_not_found",
+            ModmulError::NotACanvas(_) => "not_a_canvas",
+            ModmulError::NotInDocument => "not_in_document",
+            ModmulError::WebGl2Unsupported => "webgl2_unsupported",
+            ModmulError::WebGlUnsupported => "webgl_unsupported",
+            ModmulError::ShaderCompile {..} => "shader_compile",
+            ModmulError::Link {..} => "link",
+            ModmulError::MissingUniform {..} => "missing_uniform",
+            ModmulError::MissingAttribute {..} => "missing_attribute",
+            ModmulError::CreateFailed(_) => "create_failed",
+            ModmulError::ExtensionUnsupported(_) => "extension_unsupported",
+            ModmulError::UnknownName {..} => "unknown_name",
+            ModmulError::InvalidColor(_) => "invalid_color",
+            ModmulError::OutOfRange {..} => "out_of_range",
+            ModmulError::Invalid(_) => "invalid"
+        };
+    }
+
+    // Name of the thing a name wasn't found for, and the name
+    pub fn unknown(kind: &'static str, name: &str) -> ModmulError {
+        return ModmulError::UnknownName {
+            kind,
+            name: String::from(name)
+        };
+    }
+
+    // Details as (property, value) pairs, see the list at the top of this file
+    fn details(&self) -> Vec<(&'static str, JsValue)> {
+        return match self {
+            ModmulError::ElementNotFound(id) | ModmulError::NotACanvas(id) => vec![("id", JsValue::from_str(id))],
+            ModmulError::ShaderCompile {shader, log} => vec![("shader", JsValue::from_str(shader)), ("log", JsValue::from_str(log))],
+            ModmulError::Link {program, log} => vec![("program", JsValue::from_str(program)), ("log", JsValue::from_str(log))],
+            ModmulError::MissingUniform {program, uniform} => vec![("program", JsValue::from_str(program)), ("uniform", JsValue::from_str(uniform))],
+            ModmulError::MissingAttribute {program, attribute} => vec![("program", JsValue::from_str(program)), ("attribute", JsValue::from_str(attribute))],
+            ModmulError::CreateFailed(what) => vec![("what", JsValue::from_str(what))],
+            ModmulError::ExtensionUnsupported(extension) => vec![("extension", JsValue::from_str(extension))],
+            ModmulError::UnknownName {kind, name} => vec![("kind", JsValue::from_str(kind)), ("name", JsValue::from_str(name))],
+            ModmulError::InvalidColor(value) => vec![("value", JsValue::from_str(value))],
+            ModmulError::OutOfRange {name, value, min, max} => vec![("name", JsValue::from_str(name)), ("value", JsValue::from(*value)), ("min", JsValue::from(*min)), ("max", JsValue::from(*max))],
+            _ => Vec::new()
+        };
+    }
+}
+
+impl fmt::Display for ModmulError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            ModmulError::NoWindow => write!(f, "No window"),
+            ModmulError::NoDocument => write!(f, "No document"),
+            ModmulError::ElementNotFound(id) => write!(f, "No element with id {}", id),
+            ModmulError::NotACanvas(id) => write!(f, "Element {} is not a canvas", id),
+            ModmulError::NotInDocument => write!(f, "Canvas is not in a document"),
+            ModmulError::WebGl2Unsupported => write!(f, "WebGL2 is not available"),
+            ModmulError::WebGlUnsupported => write!(f, "WebGL is not available"),
+            ModmulError::ShaderCompile {shader, log} => write!(f, "Unable to compile {}: {}", shader, log),
+            ModmulError::Link {program, log} => write!(f, "Unable to link the {} program: {}", program, log),
+            ModmulError::MissingUniform {program, uniform} => write!(f, "The {} program has no uniform {}", program, uniform),
+            ModmulError::MissingAttribute {program, attribute} => write!(f, "The {} program has no attribute {}", program, attribute),
+            ModmulError::CreateFailed(what) => write!(f, "Unable to create {}", what),
+            ModmulError::ExtensionUnsupported(extension) => write!(f, "{} is not supported", extension),
+            ModmulError::UnknownName {kind, name} => write!(f, "Unknown {} {}", kind, name),
+            ModmulError::InvalidColor(value) => write!(f, "Invalid color {}", value),
+            ModmulError::OutOfRange {name, value, min, max} => write!(f, "{} must be from {} to {}, got {}", name, min, max, value),
+            ModmulError::Invalid(message) => write!(f, "{}", message)
+        };
+    }
+}
+
+impl std::error::Error for ModmulError {}
+
+impl From<String> for ModmulError {
+    fn from(message: String) -> ModmulError {
+        return ModmulError::Invalid(message);
+    }
+}
+
+impl From<ModmulError> for JsValue {
+    fn from(error: ModmulError) -> JsValue {
+        let out: js_sys::Error = js_sys::Error::new(&error.to_string());
+        out.set_name("ModmulError");
+        let _ = js_sys::Reflect::set(&out, &JsValue::from_str("code"), &JsValue::from_str(error.code()));
+        for (name, value) in error.details() {
+            let _ = js_sys::Reflect::set(&out, &JsValue::from_str(name), &value);
+        }
+        return out.into();
+    }
+}
@@ -0,0 +1,178 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Change events sent to JS listeners. Rather than every code path reporting
+// what it touched, the canvas compares the scene with the one listeners last
+// saw and sends one event per field that differs. So setters, undo, presets
+// and URL loads are all covered the same way.
+//
+// Field names and values, as seen from JS:
+//   "points"          number         "multiplier"     number
+//   "rotation"        number, deg    "rect_width"     number
+//   "enable_outline"  boolean        "use_rects"      boolean
+//   "zoom"            number, 1 is the reset view
+//   "view_rotation"   number, deg
+//   "position"        {x, y}, world point in the middle of the surface
+//   "foreground"      "#rrggbb"      "background"     "#rrggbb"
+
+use wasm_bindgen::prelude::*;
+
+use crate::camera::WorldPoint;
+use crate::scene::Scene;
+
+// Struct declarations
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SceneField {
+    Points,
+    Multiplier,
+    Rotation,
+    RectWidth,
+    EnableOutline,
+    UseRects,
+    Zoom,
+    ViewRotation,
+    Position,
+    Foreground,
+    Background
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldValue {
+    Int(u32),
+    Float(f64),
+    Bool(bool),
+    Point(WorldPoint),
+    Color(String)
+}
+
+// Event handed to the JS callbacks
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct ChangeEvent {
+    field: SceneField,
+    value: FieldValue
+}
+
+// Struct implementations
+
+impl SceneField {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            SceneField::Points => "points",
+            SceneField::Multiplier => "multiplier",
+            SceneField::Rotation => "rotation",
+            SceneField::RectWidth => "rect_width",
+            SceneField::EnableOutline => "enable_outline",
+            SceneField::UseRects => "use_rects",
+            SceneField::Zoom => "zoom",
+            SceneField::ViewRotation => "view_rotation",
+            SceneField::Position => "position",
+            SceneField::Foreground => "foreground",
+            SceneField::Background => "background"
+        };
+    }
+}
+
+impl FieldValue {
+    pub fn to_js(&self) -> JsValue {
+        return match self {
+            FieldValue::Int(v) => JsValue::from(*v),
+            FieldValue::Float(v) => JsValue::from(*v),
+            FieldValue::Bool(v) => JsValue::from(*v),
+            FieldValue::Point(p) => {
+                let obj: js_sys::Object = js_sys::Object::new();
+                let _ = js_sys::Reflect::set(&obj, &JsValue::from_str("x"), &JsValue::from(p.x));
+                let _ = js_sys::Reflect::set(&obj, &JsValue::from_str("y"), &JsValue::from(p.y));
+                obj.into()
+            }
+            FieldValue::Color(hex) => JsValue::from_str(hex)
+        };
+    }
+}
+
+impl ChangeEvent {
+    pub fn new(field: SceneField, value: FieldValue) -> ChangeEvent {
+        return ChangeEvent {field, value};
+    }
+
+    pub fn scene_field(&self) -> SceneField {
+        return self.field;
+    }
+
+    pub fn field_value(&self) -> &FieldValue {
+        return &self.value;
+    }
+}
+
+#[wasm_bindgen]
+impl ChangeEvent {
+    // Name of the field, see the list at the top of this file
+    #[wasm_bindgen(getter)]
+    pub fn field(&self) -> String {
+        return String::from(self.field.name());
+    }
+
+    // New value of the field
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> JsValue {
+        return self.value.to_js();
+    }
+}
+
+// Helper functions
+
+// Fields that differ between `before` and `after`, with their new values.
+// The surface size is not part of the state and never reported.
+pub fn scene_changes(before: &Scene, after: &Scene) -> Vec<ChangeEvent> {
+    let mut out: Vec<ChangeEvent> = Vec::new();
+    let (b, a) = (&before.shape, &after.shape);
+
+    if (b.points != a.points) {
+        out.push(ChangeEvent::new(SceneField::Points, FieldValue::Int(a.points)));
+    }
+    if (b.mul != a.mul) {
+        out.push(ChangeEvent::new(SceneField::Multiplier, FieldValue::Int(a.mul)));
+    }
+    if (b.rotation != a.rotation) {
+        out.push(ChangeEvent::new(SceneField::Rotation, FieldValue::Float(after.rotation_deg() as f64)));
+    }
+    if (b.rect_width != a.rect_width) {
+        out.push(ChangeEvent::new(SceneField::RectWidth, FieldValue::Float(a.rect_width as f64)));
+    }
+    if (before.enable_outline != after.enable_outline) {
+        out.push(ChangeEvent::new(SceneField::EnableOutline, FieldValue::Bool(after.enable_outline)));
+    }
+    if (b.use_rects_instead_of_lines != a.use_rects_instead_of_lines) {
+        out.push(ChangeEvent::new(SceneField::UseRects, FieldValue::Bool(a.use_rects_instead_of_lines)));
+    }
+    if (before.camera.zoom != after.camera.zoom) {
+        out.push(ChangeEvent::new(SceneField::Zoom, FieldValue::Float(after.camera.zoom)));
+    }
+    if (before.camera.rotation != after.camera.rotation) {
+        out.push(ChangeEvent::new(SceneField::ViewRotation, FieldValue::Float(after.view_rotation_deg())));
+    }
+    if (before.camera.center != after.camera.center) {
+        out.push(ChangeEvent::new(SceneField::Position, FieldValue::Point(after.camera.center)));
+    }
+    if (b.color != a.color) {
+        out.push(ChangeEvent::new(SceneField::Foreground, FieldValue::Color(a.color.to_hex())));
+    }
+    if (before.bg != after.bg) {
+        out.push(ChangeEvent::new(SceneField::Background, FieldValue::Color(after.bg.to_hex())));
+    }
+    return out;
+}
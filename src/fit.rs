@@ -0,0 +1,106 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// View commands that frame something: the whole circle, the points of an
+// orbit, or a box dragged on the surface. All of them keep the view
+// rotation, fit along whichever side of the surface is the tighter one, and
+// animate over a duration unless the user prefers reduced motion.
+
+use crate::camera::{Camera, WorldPoint};
+use crate::canvas_state::CanvasState;
+use crate::error::ModmulError;
+use crate::geometry::{orbit, point_position};
+use crate::history::Change;
+use crate::limits;
+use crate::scene::{DEFAULT_RADIUS, Point, Scene};
+
+// Space left around what is framed, in view units. The same margin as the
+// reset view, so fitting the circle at zoom 1 changes nothing.
+pub const FIT_PADDING: f32 = 1.0 - DEFAULT_RADIUS;
+
+pub const FIT_DURATION_MS: f64 = 400.0;
+
+impl CanvasState {
+    // Frame the whole circle, keeping the view rotation
+    pub fn fit_view(&mut self, duration_ms: f64) -> Result<(), ModmulError> {
+        limits::check_duration(duration_ms)?;
+        self.animate_camera(duration_ms, |scene: &mut Scene| {
+            scene.camera.frame(WorldPoint::new(0.0, 0.0), (1.0, 1.0), FIT_PADDING);
+        });
+        return Ok(());
+    }
+
+    // Frame the points reached from point `start` by following chords
+    pub fn fit_orbit(&mut self, start: u32, duration_ms: f64) -> Result<(), ModmulError> {
+        let points: u32 = self.scene.shape.points;
+        if (start >= points) {
+            return Err(ModmulError::Invalid(format!("Point {} out of range 0..{}", start, points)));
+        }
+        limits::check_duration(duration_ms)?;
+        self.animate_camera(duration_ms, |scene: &mut Scene| {
+            let positions: Vec<WorldPoint> = orbit(points, scene.shape.mul, start).into_iter()
+                .map(|i: u32| point_position(&scene.shape, i))
+                .collect();
+            if let Some((center, half)) = scene.camera.upright_bounds(&positions) {
+                scene.camera.frame(center, half, FIT_PADDING);
+            }
+        });
+        return Ok(());
+    }
+
+    // Zoom into the box between the client coordinates `x0`, `y0` and `x1`,
+    // `y1`, as in mouse events
+    pub fn zoom_to_box(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, duration_ms: f64) -> Result<(), ModmulError> {
+        for (name, value) in [("x0", x0), ("y0", y0), ("x1", x1), ("y1", y1)] {
+            limits::check_finite(name, value as f64)?;
+        }
+        limits::check_duration(duration_ms)?;
+        let (Some((a, _)), Some((b, _))) = (self.client_to_pixels(x0, y0), self.client_to_pixels(x1, y1)) else {
+            return Ok(());
+        };
+        if (a.x == b.x && a.y == b.y) {
+            return Ok(());
+        }
+        // While split, the box is in the pane its first corner is in
+        let (_, local): (Camera, Point) = self.camera_at(a);
+        let (a, b): (Point, Point) = (local, Point::new(b.x - (a.x - local.x), b.y - (a.y - local.y)));
+        self.animate_camera(duration_ms, |scene: &mut Scene| {
+            let a: Point = scene.camera.pixels_to_view(a);
+            let b: Point = scene.camera.pixels_to_view(b);
+            scene.camera.zoom_to_view_rect(a, b);
+        });
+        return Ok(());
+    }
+
+    ///////////////////////
+    // Private functions //
+    ///////////////////////
+
+    // Move the camera with `f`, animated from where it is now
+    fn animate_camera(&mut self, duration_ms: f64, f: impl FnOnce(&mut Scene)) {
+        self.finish_transition();
+        self.stop_motion();
+        // Framed for one pane while split
+        let dimensions: Point = self.scene.camera.dimensions();
+        let (width, height): (u32, u32) = self.view_size();
+        let mut target: Scene = self.scene.clone();
+        target.camera.set_dimensions(width, height);
+        f(&mut target);
+        target.camera.set_dimensions(dimensions.x as u32, dimensions.y as u32);
+        let duration_ms: f64 = if (self.reduced_motion()) { 0.0 } else { duration_ms };
+        self.transition_to(Change::Fit, target, duration_ms);
+    }
+}
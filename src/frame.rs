@@ -0,0 +1,114 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// requestAnimationFrame from Rust, on the page or in a worker. `request`
+// asks for one call of the callback on the next frame, however often it is
+// called before that frame. The callback has to ask again if it wants
+// another frame, so nothing runs while there is nothing to do.
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{DedicatedWorkerGlobalScope, Performance, Window, WorkerGlobalScope};
+
+type FrameCallback = Closure<dyn FnMut(f64)>;
+
+// Struct declarations
+
+pub struct FrameScheduler {
+    callback: Option<FrameCallback>,
+    pending: Rc<Cell<Option<i32>>> // id of the requested frame
+}
+
+// Struct implementations
+
+impl FrameScheduler {
+    pub fn new() -> FrameScheduler {
+        return FrameScheduler {
+            callback: None,
+            pending: Rc::new(Cell::new(None))
+        };
+    }
+
+    // Called with the requestAnimationFrame timestamp
+    pub fn set_callback(&mut self, mut f: impl FnMut(f64) + 'static) {
+        let pending: Rc<Cell<Option<i32>>> = Rc::clone(&self.pending);
+        let closure: FrameCallback = Closure::new(move |now_ms: f64| {
+            pending.set(None);
+            f(now_ms);
+        });
+        self.callback = Some(closure);
+    }
+
+    pub fn request(&self) {
+        if (self.pending.get().is_some()) {
+            return;
+        }
+        let Some(closure) = self.callback.as_ref() else {
+            return;
+        };
+        let global: js_sys::Object = js_sys::global();
+        let id: Option<i32> = if let Some(window) = global.dyn_ref::<Window>() {
+            window.request_animation_frame(closure.as_ref().unchecked_ref()).ok()
+        } else if let Some(worker) = global.dyn_ref::<DedicatedWorkerGlobalScope>() {
+            worker.request_animation_frame(closure.as_ref().unchecked_ref()).ok()
+        } else {
+            None
+        };
+        self.pending.set(id);
+    }
+
+    pub fn cancel(&self) {
+        let Some(id) = self.pending.take() else {
+            return;
+        };
+        let global: js_sys::Object = js_sys::global();
+        if let Some(window) = global.dyn_ref::<Window>() {
+            let _ = window.cancel_animation_frame(id);
+        } else if let Some(worker) = global.dyn_ref::<DedicatedWorkerGlobalScope>() {
+            let _ = worker.cancel_animation_frame(id);
+        }
+    }
+}
+
+impl Default for FrameScheduler {
+    fn default() -> FrameScheduler {
+        return FrameScheduler::new();
+    }
+}
+
+impl Drop for FrameScheduler {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+// Helper functions
+
+// Milliseconds on the clock frame timestamps are on
+pub fn now_ms() -> f64 {
+    let global: js_sys::Object = js_sys::global();
+    let performance: Option<Performance> = if let Some(window) = global.dyn_ref::<Window>() {
+        window.performance()
+    } else if let Some(worker) = global.dyn_ref::<WorkerGlobalScope>() {
+        worker.performance()
+    } else {
+        None
+    };
+    return performance.map_or(0.0, |performance: Performance| performance.now());
+}
@@ -0,0 +1,250 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Small multiples: a grid of the current figure with a range of
+// multipliers, one per cell, filled row by row. Every cell is drawn by the
+// same programs as the full view, into its own viewport, and only the
+// multiplier changes between cells, so even large grids are a few uniform
+// uploads and draw calls each. Clicking a cell opens that multiplier in the
+// full view.
+//
+// While the gallery is shown the camera doesn't move and keys are ignored.
+
+use crate::camera::Camera;
+use crate::canvas_state::CanvasState;
+use crate::error::ModmulError;
+use crate::history::Change;
+use crate::input::InputEvent;
+use crate::renderer::{Dirty, Renderer};
+use crate::scene::{Point, Scene, Shape};
+
+// More cells than this would leave each a few pixels
+const MAX_CELLS: u32 = 1024;
+
+// Height of the label under each cell, as a fraction of the cell height
+const LABEL_SIZE: f32 = 0.12;
+const MAX_LABEL_PX: f32 = 24.0;
+const MIN_LABEL_PX: f32 = 7.0; // smaller labels are left out
+
+// A press that moves further than this, in CSS pixels, is not a click
+const CLICK_SLOP: f32 = 6.0;
+
+// Struct declarations
+
+pub struct Gallery {
+    first: u32, // multiplier in the top left cell
+    count: u32,
+    columns: u32,
+    press: Option<(i32, f32, f32)> // pointer id and client coordinates of a press on a cell
+}
+
+// Where a cell goes on the surface, in pixels from the top left corner
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    pub multiplier: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32, // figure only, the label goes below
+    pub label: f32 // label text size, 0 when there is no room
+}
+
+// Struct implementations
+
+impl Gallery {
+    // Multipliers `first..=last` in rows of `columns`
+    pub fn new(first: u32, last: u32, columns: u32) -> Result<Gallery, String> {
+        if (first > last) {
+            return Err(format!("Empty multiplier range {}..{}", first, last));
+        }
+        // Compared before adding 1, 0..=u32::MAX has more cells than a u32 counts
+        if (last - first >= MAX_CELLS) {
+            return Err(format!("{} cells, at most {} fit", (last - first) as u64 + 1, MAX_CELLS));
+        }
+        let count: u32 = last - first + 1;
+        if (columns == 0) {
+            return Err(String::from("A gallery needs at least one column"));
+        }
+        return Ok(Gallery {
+            first,
+            count,
+            columns: columns.min(count),
+            press: None
+        });
+    }
+
+    pub fn rows(&self) -> u32 {
+        return self.count.div_ceil(self.columns);
+    }
+
+    // Layout of every cell on a surface of `dimensions` pixels
+    pub fn cells(&self, dimensions: Point) -> Vec<Cell> {
+        let cell_w: u32 = dimensions.x as u32 / self.columns;
+        let cell_h: u32 = dimensions.y as u32 / self.rows();
+        let label_px: f32 = (cell_h as f32 * LABEL_SIZE).min(MAX_LABEL_PX);
+        let label: f32 = if (label_px >= MIN_LABEL_PX) { label_px } else { 0.0 };
+        let figure_h: u32 = cell_h.saturating_sub((label * 1.4).ceil() as u32);
+        return (0..self.count).map(|i: u32| Cell {
+            multiplier: self.first + i,
+            x: (i % self.columns) * cell_w,
+            y: (i / self.columns) * cell_h,
+            width: cell_w,
+            height: figure_h,
+            label
+        }).collect();
+    }
+
+    // Cell under the surface pixel `px`, label included
+    pub fn cell_at(&self, dimensions: Point, px: Point) -> Option<Cell> {
+        if (px.x < 0.0 || px.y < 0.0) {
+            return None;
+        }
+        let cell_w: f32 = (dimensions.x as u32 / self.columns) as f32;
+        let cell_h: f32 = (dimensions.y as u32 / self.rows()) as f32;
+        if (cell_w == 0.0 || cell_h == 0.0) {
+            return None;
+        }
+        let (column, row): (u32, u32) = ((px.x / cell_w) as u32, (px.y / cell_h) as u32);
+        if (column >= self.columns) {
+            return None;
+        }
+        return self.cells(dimensions).get((row * self.columns + column) as usize).copied();
+    }
+
+    // Draw every cell of `scene`'s figure
+    pub fn draw(&self, scene: &Scene, renderer: &mut dyn Renderer) {
+        renderer.clear(&scene.bg);
+        let cells: Vec<Cell> = self.cells(scene.camera.dimensions());
+        let Some(first) = cells.first() else {
+            return;
+        };
+        if (first.width == 0 || first.height == 0) {
+            return;
+        }
+
+        // Every cell has the same size, so the same camera
+        let camera: Camera = Camera::new(first.width, first.height);
+        let mut shape: Shape = scene.shape.clone();
+        shape.mul_frac = 0.0;
+        shape.point_size = shape.point_size.min(1.0);
+        for cell in &cells {
+            shape.mul = cell.multiplier;
+            renderer.invalidate(Dirty {
+                figure: true,
+                ..Dirty::default()
+            });
+            renderer.set_viewport(cell.x, cell.y, cell.width, cell.height);
+            if (scene.enable_outline) {
+                renderer.draw_outline(&shape, &camera, &scene.bg);
+            }
+            renderer.draw_chords(&shape, &camera);
+            renderer.draw_points(&shape, &camera);
+        }
+        renderer.reset_viewport();
+
+        // Labels are placed in pixels of the whole surface
+        for cell in cells.iter().filter(|cell: &&Cell| cell.label > 0.0) {
+            let text: String = format!("k = {}", cell.multiplier);
+            renderer.draw_text(&text, cell.x as f32 + cell.label * 0.5, (cell.y + cell.height) as f32, cell.label, &scene.shape.color);
+        }
+
+        // The programs were left with the last cell's figure
+        renderer.invalidate(Dirty {
+            figure: true,
+            ..Dirty::default()
+        });
+    }
+}
+
+impl CanvasState {
+    // Show multipliers `first..=last` side by side in rows of `columns`,
+    // instead of the full view
+    pub fn show_gallery(&mut self, first: u32, last: u32, columns: u32) -> Result<(), ModmulError> {
+        self.gallery = Some(Gallery::new(first, last, columns)?);
+        self.stop_motion();
+        self.redraw();
+        return Ok(());
+    }
+
+    // Back to the full view
+    pub fn close_gallery(&mut self) {
+        if (self.gallery.take().is_some()) {
+            self.redraw();
+        }
+    }
+
+    pub fn is_gallery_shown(&self) -> bool {
+        return self.gallery.is_some();
+    }
+
+    // Multiplier of the cell under the client coordinates `x`, `y`, as in
+    // mouse events
+    pub fn gallery_cell_at(&self, x: f32, y: f32) -> Option<u32> {
+        let gallery: &Gallery = self.gallery.as_ref()?;
+        let (px, _): (Point, f32) = self.client_to_pixels(x, y)?;
+        return gallery.cell_at(self.scene.camera.dimensions(), px).map(|cell: Cell| cell.multiplier);
+    }
+
+    // Show the cell with multiplier `multiplier` in the full view
+    pub fn open_gallery_cell(&mut self, multiplier: u32) {
+        self.gallery = None;
+        self.redraw();
+        self.mutate(Change::Multiplier, |scene: &mut Scene| scene.set_multiplier(multiplier));
+        self.checkpoint();
+    }
+
+    // A pointer went down, returns true if the gallery took it
+    pub(crate) fn gallery_pointer_down(&mut self, e: &InputEvent) -> bool {
+        let Some(gallery) = &mut self.gallery else {
+            return false;
+        };
+        if (e.button == 0) {
+            gallery.press = Some((e.pointer_id, e.x, e.y));
+        }
+        return true;
+    }
+
+    // Returns true if the gallery took the pointer, opening the cell it
+    // clicked on
+    pub(crate) fn gallery_pointer_up(&mut self, e: &InputEvent) -> bool {
+        let Some(gallery) = &mut self.gallery else {
+            return false;
+        };
+        let Some((_, x, y)) = gallery.press.take_if(|press: &mut (i32, f32, f32)| press.0 == e.pointer_id) else {
+            return true;
+        };
+        let (dx, dy): (f32, f32) = (e.x - x, e.y - y);
+        if (e.kind == "pointerup" && dx.hypot(dy) <= CLICK_SLOP)
+            && let Some(multiplier) = self.gallery_cell_at(x, y) {
+            self.open_gallery_cell(multiplier);
+        }
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_past_the_cell_limit_are_rejected_without_overflowing() {
+        assert!(Gallery::new(0, u32::MAX, 4).is_err());
+        assert!(Gallery::new(1, u32::MAX, 4).is_err());
+        assert!(Gallery::new(0, MAX_CELLS, 4).is_err());
+        assert_eq!(Gallery::new(0, MAX_CELLS - 1, 4).map(|gallery: Gallery| gallery.count), Ok(MAX_CELLS));
+        assert_eq!(Gallery::new(u32::MAX, u32::MAX, 4).map(|gallery: Gallery| gallery.rows()), Ok(1));
+    }
+}
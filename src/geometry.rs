@@ -0,0 +1,401 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Host-side version of the figure geometry the vertex shaders compute on
+// the GPU. Backends that cannot run the shaders draw from these instead,
+// and so does the WebGL backend past `DEEP_ZOOM`, where only f64 is precise
+// enough. Everything is computed in f64 and only turned into f32 once it is
+// relative to the camera, where the numbers are small.
+
+use std::collections::HashSet;
+
+use crate::camera::{Camera, WorldPoint};
+use crate::scene::{Point, Shape};
+
+// Index of the point that point `i` connects to
+pub fn chord_destination(points: u32, mul: u32, i: u32) -> u32 {
+    if (points == 0) {
+        return 0;
+    }
+    // u64 so that large multipliers don't overflow like the shaders' int math
+    return ((i as u64 * mul as u64) % points as u64) as u32;
+}
+
+// Where chord `i` ends, as a possibly fractional point index. Only differs
+// from `chord_destination` while a transition animates the multiplier.
+pub fn chord_destination_index(shape: &Shape, i: u32) -> f64 {
+    let dst: u32 = chord_destination(shape.points, shape.mul, i);
+    if (shape.mul_frac == 0.0) {
+        return dst as f64;
+    }
+    return (dst as f64 + (i as f64) * (shape.mul_frac as f64)).rem_euclid(shape.points as f64);
+}
+
+// Angle of point `i` in radians, point 0 sits at the top of the circle
+pub fn point_angle(points: u32, i: f64, rotation: f32) -> f64 {
+    let pi: f64 = std::f64::consts::PI;
+    return i * 2.0 * pi / (points as f64) + (rotation as f64) + (pi / 2.0);
+}
+
+// Position of point `i` in world coordinates
+pub fn point_position(shape: &Shape, i: u32) -> WorldPoint {
+    return position_at(shape, i as f64);
+}
+
+// Position on the circle at a possibly fractional point index
+pub fn position_at(shape: &Shape, index: f64) -> WorldPoint {
+    let theta: f64 = point_angle(shape.points, index, shape.rotation);
+    return WorldPoint::new(-theta.cos(), theta.sin());
+}
+
+// Source and destination of chord `i` in world coordinates
+pub fn chord(shape: &Shape, i: u32) -> (WorldPoint, WorldPoint) {
+    return (point_position(shape, i), position_at(shape, chord_destination_index(shape, i)));
+}
+
+// Points reached from `start` by following chords, `start` first, until one
+// comes up again. The chord from i goes to i * mul, so these are the powers
+// of mul times start.
+pub fn orbit(points: u32, mul: u32, start: u32) -> Vec<u32> {
+    if (start >= points) {
+        return Vec::new();
+    }
+    let mut seen: Vec<bool> = vec![false; points as usize];
+    let mut out: Vec<u32> = Vec::new();
+    let mut i: u32 = start;
+    while (!seen[i as usize]) {
+        seen[i as usize] = true;
+        out.push(i);
+        i = chord_destination(points, mul, i);
+    }
+    return out;
+}
+
+// Chords of figure A sorted by whether figure B has a chord between the same
+// two spots on the circle, and B's chords that A lacks. The figures may have
+// different point counts, spots are compared exactly as fractions of a turn.
+// Both figures are taken to have the same rotation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ChordDiff {
+    pub only_a: Vec<u32>, // chord numbers in A
+    pub only_b: Vec<u32>, // chord numbers in B
+    pub shared: Vec<u32> // chord numbers in A
+}
+
+pub fn compare_chords(a_points: u32, a_mul: u32, b_points: u32, b_mul: u32) -> ChordDiff {
+    let in_b: HashSet<ChordKey> = (0..b_points).map(|i: u32| chord_key(b_points, b_mul, i)).collect();
+    let in_a: HashSet<ChordKey> = (0..a_points).map(|i: u32| chord_key(a_points, a_mul, i)).collect();
+    let mut out: ChordDiff = ChordDiff::default();
+    for i in 0..a_points {
+        if (in_b.contains(&chord_key(a_points, a_mul, i))) {
+            out.shared.push(i);
+        } else {
+            out.only_a.push(i);
+        }
+    }
+    out.only_b = (0..b_points).filter(|&i: &u32| !in_a.contains(&chord_key(b_points, b_mul, i))).collect();
+    return out;
+}
+
+// Convert world coordinates to pixels from the top left corner of the surface
+pub fn to_pixels(camera: &Camera, p: WorldPoint) -> Point {
+    return camera.world_to_pixels(p);
+}
+
+// Convert a length in view units to pixels
+pub fn length_to_pixels(camera: &Camera, len: f32) -> f32 {
+    return camera.length_to_pixels(len);
+}
+
+// Chords that cross the surface, as view coordinates clipped to the surface
+// grown by `margin` view units on every side
+pub fn visible_chords(shape: &Shape, camera: &Camera, margin: f32) -> Vec<(Point, Point)> {
+    return visible_chord_subset(shape, camera, margin, 0..shape.points);
+}
+
+// Same as `visible_chords` for only the chords numbered in `indices`
+pub fn visible_chord_subset(shape: &Shape, camera: &Camera, margin: f32, indices: impl IntoIterator<Item = u32>) -> Vec<(Point, Point)> {
+    let bounds: (f64, f64) = view_bounds(camera, margin);
+    let mut out: Vec<(Point, Point)> = Vec::new();
+    for i in indices {
+        let (src, dst): (WorldPoint, WorldPoint) = chord(shape, i);
+        let a: (f64, f64) = camera.world_to_view_f64(src);
+        let b: (f64, f64) = camera.world_to_view_f64(dst);
+        if let Some((a, b)) = clip_segment(a, b, bounds) {
+            out.push((Point::new(a.0 as f32, a.1 as f32), Point::new(b.0 as f32, b.1 as f32)));
+        }
+    }
+    return out;
+}
+
+// Points on the surface grown by `margin`, in view coordinates
+pub fn visible_points(shape: &Shape, camera: &Camera, margin: f32) -> Vec<Point> {
+    let (ex, ey): (f64, f64) = view_bounds(camera, margin);
+    let mut out: Vec<Point> = Vec::new();
+    for i in 0..shape.points {
+        let (x, y): (f64, f64) = camera.world_to_view_f64(point_position(shape, i));
+        if (x.abs() <= ex && y.abs() <= ey) {
+            out.push(Point::new(x as f32, y as f32));
+        }
+    }
+    return out;
+}
+
+// The part of the circle near the surface as a polyline of `segments`
+// pieces in view coordinates. Empty when the circle is out of sight.
+pub fn visible_arc(camera: &Camera, margin: f32, segments: u32) -> Vec<Point> {
+    let (ex, ey): (f64, f64) = view_bounds(camera, margin);
+    let reach: f64 = ex.hypot(ey); // the surface fits in a disc this big
+    let (ox, oy): (f64, f64) = camera.world_to_view_f64(WorldPoint::new(0.0, 0.0));
+    let r: f64 = camera.scale();
+    let d: f64 = ox.hypot(oy);
+    if ((d - r).abs() > reach) {
+        return Vec::new();
+    }
+
+    // Angles, seen from the center of the circle, whose points are within
+    // `reach` of the middle of the surface
+    let facing: f64 = (-oy).atan2(-ox);
+    let half: f64 = if (d == 0.0) {
+        std::f64::consts::PI
+    } else {
+        ((d * d + r * r - reach * reach) / (2.0 * d * r)).clamp(-1.0, 1.0).acos()
+    };
+
+    let segments: u32 = segments.max(1);
+    let mut out: Vec<Point> = Vec::new();
+    for j in 0..=segments {
+        let angle: f64 = facing - half + 2.0 * half * (j as f64) / (segments as f64);
+        out.push(Point::new((ox + r * angle.cos()) as f32, (oy + r * angle.sin()) as f32));
+    }
+    return out;
+}
+
+// Ends of a chord as reduced fractions of a turn, lower one first
+type ChordKey = ((u32, u32), (u32, u32));
+
+fn chord_key(points: u32, mul: u32, i: u32) -> ChordKey {
+    let a: (u32, u32) = turn_fraction(i, points);
+    let b: (u32, u32) = turn_fraction(chord_destination(points, mul, i), points);
+    return if (a <= b) { (a, b) } else { (b, a) };
+}
+
+fn turn_fraction(i: u32, points: u32) -> (u32, u32) {
+    let d: u32 = gcd(i, points).max(1);
+    return (i / d, points / d);
+}
+
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while (b != 0) {
+        (a, b) = (b, a % b);
+    }
+    return a;
+}
+
+// Half the width and height of the surface grown by `margin`, in view units
+fn view_bounds(camera: &Camera, margin: f32) -> (f64, f64) {
+    let (ex, ey): (f32, f32) = camera.view_extent();
+    return ((ex + margin) as f64, (ey + margin) as f64);
+}
+
+// Liang-Barsky clipping of the segment a-b to |x| <= ex, |y| <= ey
+fn clip_segment(a: (f64, f64), b: (f64, f64), (ex, ey): (f64, f64)) -> Option<((f64, f64), (f64, f64))> {
+    let dx: f64 = b.0 - a.0;
+    let dy: f64 = b.1 - a.1;
+    let mut t0: f64 = 0.0;
+    let mut t1: f64 = 1.0;
+    let edges: [(f64, f64); 4] = [(-dx, a.0 + ex), (dx, ex - a.0), (-dy, a.1 + ey), (dy, ey - a.1)];
+    for (p, q) in edges {
+        if (p == 0.0) {
+            if (q < 0.0) {
+                return None;
+            }
+        } else {
+            let t: f64 = q / p;
+            if (p < 0.0) {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+    }
+    if (t0 > t1) {
+        return None;
+    }
+    return Some(((a.0 + t0 * dx, a.1 + t0 * dy), (a.0 + t1 * dx, a.1 + t1 * dy)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Scene;
+
+    // Zoom in by 10^8 on the envelope of the default figure, keeping `anchor`
+    // (view coordinates) fixed, the way the mouse wheel does it
+    fn deep_scene(anchor: Point) -> Scene {
+        let mut scene: Scene = Scene::new(1920, 1080);
+        while (scene.camera.zoom < 1e8) {
+            scene.camera.zoom_at(1.2, anchor);
+        }
+        return scene;
+    }
+
+    // Distance from p to the segment a-b
+    fn distance_to_segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+        let (dx, dy): (f64, f64) = (b.0 - a.0, b.1 - a.1);
+        let t: f64 = (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / (dx * dx + dy * dy)).clamp(0.0, 1.0);
+        return (p.0 - a.0 - t * dx).hypot(p.1 - a.1 - t * dy);
+    }
+
+    #[test]
+    fn zoom_keeps_the_anchor_fixed_at_1e8() {
+        let anchor: Point = Point::new(0.31, -0.47);
+        let before: WorldPoint = Scene::new(1920, 1080).camera.view_to_world(anchor);
+        let scene: Scene = deep_scene(anchor);
+
+        // One pixel is 1 / 540 view units here
+        let (x, y): (f64, f64) = scene.camera.world_to_view_f64(before);
+        assert!((x - anchor.x as f64).abs() < 1e-4, "x drifted to {}", x);
+        assert!((y - anchor.y as f64).abs() < 1e-4, "y drifted to {}", y);
+    }
+
+    #[test]
+    fn pan_moves_by_exactly_the_requested_amount_at_1e8() {
+        let mut scene: Scene = deep_scene(Point::new(0.2, 0.6));
+        let p: WorldPoint = scene.camera.view_to_world(Point::new(0.1, 0.1));
+        let (x0, y0): (f64, f64) = scene.camera.world_to_view_f64(p);
+        scene.camera.pan(0.003, -0.002);
+        let (x1, y1): (f64, f64) = scene.camera.world_to_view_f64(p);
+        assert!((x1 - x0 - 0.003).abs() < 1e-6);
+        assert!((y1 - y0 + 0.002).abs() < 1e-6);
+    }
+
+    #[test]
+    fn visible_chords_match_the_exact_geometry_at_1e8() {
+        // Center the view on a point of chord 137, 1/3 of the way along
+        let mut scene: Scene = Scene::new(1920, 1080);
+        let (src, dst): (WorldPoint, WorldPoint) = chord(&scene.shape, 137);
+        let target: WorldPoint = WorldPoint::new(src.x + (dst.x - src.x) / 3.0, src.y + (dst.y - src.y) / 3.0);
+        scene.camera.center = target;
+        scene.camera.zoom = 1e8;
+
+        let chords: Vec<(Point, Point)> = visible_chords(&scene.shape, &scene.camera, 0.0);
+        assert!(!chords.is_empty());
+
+        // Chord 137 must pass through the middle of the surface to well
+        // within a pixel, and so must the clipped segment uploaded for it
+        let nearest: f64 = chords.iter()
+            .map(|(a, b)| distance_to_segment((0.0, 0.0), (a.x as f64, a.y as f64), (b.x as f64, b.y as f64)))
+            .fold(f64::INFINITY, f64::min);
+        assert!(nearest < 1e-4, "nearest chord is {} view units away", nearest);
+
+        // Every segment stays on the surface, so nothing large reaches f32
+        let (ex, ey): (f32, f32) = scene.camera.view_extent();
+        for (a, b) in &chords {
+            for p in [a, b] {
+                assert!(p.x.abs() <= ex * 1.0001 && p.y.abs() <= ey * 1.0001);
+            }
+        }
+    }
+
+    #[test]
+    fn every_visible_chord_stays_within_a_pixel_at_1e8() {
+        // At 10^8 only a few chords cross the surface, most where they meet
+        // at a point: point 1000 has its own chord, and chords 500 and 10500
+        // end there
+        let mut scene: Scene = Scene::new(1920, 1080);
+        scene.set_points(20000);
+        scene.set_multiplier(2);
+        let (src, dst): (WorldPoint, WorldPoint) = chord(&scene.shape, 4321);
+        let centers: [WorldPoint; 2] = [
+            point_position(&scene.shape, 1000),
+            WorldPoint::new(src.x + (dst.x - src.x) * 0.6, src.y + (dst.y - src.y) * 0.6)
+        ];
+        let pixel: f64 = 2.0 / 1080.0; // view units
+
+        let mut seen: usize = 0;
+        for center in centers {
+            scene.camera.center = center;
+            scene.camera.zoom = 1e8;
+            for i in 0..scene.shape.points {
+                let Some((a, b)) = visible_chord_subset(&scene.shape, &scene.camera, 0.0, [i]).pop() else {
+                    continue;
+                };
+                seen += 1;
+
+                // Distance of both drawn ends from the exact line of the chord
+                let (src, dst): (WorldPoint, WorldPoint) = chord(&scene.shape, i);
+                let (p, q): ((f64, f64), (f64, f64)) = (scene.camera.world_to_view_f64(src), scene.camera.world_to_view_f64(dst));
+                let (dx, dy): (f64, f64) = (q.0 - p.0, q.1 - p.1);
+                for end in [a, b] {
+                    let off: f64 = ((end.x as f64 - p.0) * dy - (end.y as f64 - p.1) * dx).abs() / dx.hypot(dy);
+                    assert!(off < pixel, "chord {} is drawn {} pixels off", i, off / pixel);
+                }
+            }
+        }
+        assert!(seen >= 4, "only {} chords crossed the surface", seen);
+    }
+
+    #[test]
+    fn chords_off_the_surface_are_dropped() {
+        let mut scene: Scene = Scene::new(800, 600);
+        scene.set_points(4);
+        scene.set_multiplier(1); // four points connected to themselves
+        scene.camera.zoom = 1e6;
+        assert!(visible_chords(&scene.shape, &scene.camera, 0.0).is_empty());
+        assert!(visible_points(&scene.shape, &scene.camera, 0.0).is_empty());
+        assert!(visible_arc(&scene.camera, 0.0, 16).is_empty());
+    }
+
+    #[test]
+    fn visible_arc_follows_the_circle_at_1e8() {
+        let mut scene: Scene = Scene::new(1000, 1000);
+        scene.camera.center = WorldPoint::new(0.6, 0.8); // on the circle
+        scene.camera.zoom = 1e8;
+
+        let arc: Vec<Point> = visible_arc(&scene.camera, 0.0, 64);
+        assert_eq!(arc.len(), 65);
+        let r: f64 = scene.camera.scale();
+        let (ox, oy): (f64, f64) = scene.camera.world_to_view_f64(WorldPoint::new(0.0, 0.0));
+        for p in &arc {
+            let d: f64 = (p.x as f64 - ox).hypot(p.y as f64 - oy);
+            assert!((d - r).abs() < 1e-3, "arc point is {} view units off the circle", d - r);
+        }
+    }
+
+    #[test]
+    fn orbit_follows_chords_until_it_repeats() {
+        // Doubling 3 mod 50 comes back to 6 after 28
+        let out: Vec<u32> = orbit(50, 2, 3);
+        assert_eq!(out[..5], [3, 6, 12, 24, 48]);
+        assert_eq!(*out.last().unwrap(), 28);
+        assert_eq!(out.len(), 21);
+        assert_eq!(orbit(50, 2, 0), [0]);
+        assert!(orbit(50, 2, 50).is_empty());
+    }
+
+    #[test]
+    fn doubling_the_points_keeps_every_chord() {
+        // Chord i of n points is chord 2i of 2n points
+        let diff: ChordDiff = compare_chords(10, 3, 20, 3);
+        assert_eq!(diff.shared.len(), 10);
+        assert!(diff.only_a.is_empty());
+        assert_eq!(diff.only_b, (0..20).filter(|i: &u32| i % 2 == 1).collect::<Vec<u32>>());
+
+        let same: ChordDiff = compare_chords(50, 7, 50, 7);
+        assert_eq!((same.shared.len(), same.only_a.len(), same.only_b.len()), (50, 0, 0));
+    }
+}
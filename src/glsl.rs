@@ -0,0 +1,212 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Shader sources as the renderers hand them to WebGL. Code more than one
+// shader needs lives in chunks, the *.glsl files, that the shaders pull in
+// with a line of its own:
+//   #include "circle.glsl"
+// Each chunk is pasted in once per shader, however often it is included.
+// Includes are expanded whatever #ifdef they sit in, the conditionals are
+// left to the GLSL preprocessor.
+//
+// Switches are names `shader_source` defines right after the #version line,
+// for the shader and its chunks to #ifdef on. Shaders are written against
+// "#version 300 es", and the WEBGL1 switch also replaces that line by
+// "#version 100", so webgl1_renderer.rs compiles the same files with their
+// WEBGL1 branches for what GLSL 100 lacks: attributes instead of
+// gl_VertexID, attribute and varying instead of in and out, texture2D and
+// gl_FragColor.
+//
+// Expanded sources are kept for the page's lifetime, one per file and set of
+// switches, so a second canvas or a restored context doesn't expand them
+// again. Compile logs refer to lines of the expanded source, `map_log` turns
+// them back into the file and line they came from.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::error::ModmulError;
+
+// Every file `shader_source` can expand, shaders and chunks alike
+const FILES: &[(&str, &str)] = &[
+    ("circle.glsl", include_str!("circle.glsl")),
+    ("chord.glsl", include_str!("chord.glsl")),
+    ("rect.glsl", include_str!("rect.glsl")),
+    ("point_shader.vert", include_str!("point_shader.vert")),
+    ("line_shader.vert", include_str!("line_shader.vert")),
+    ("outline_shader.vert", include_str!("outline_shader.vert")),
+    ("rect_shader.vert", include_str!("rect_shader.vert")),
+    ("text_shader.vert", include_str!("text_shader.vert")),
+    ("text_shader.frag", include_str!("text_shader.frag")),
+    ("view_shader.vert", include_str!("view_shader.vert")),
+    ("color_shader.frag", include_str!("color_shader.frag"))
+];
+
+// Switch that turns a shader into GLSL 100, and the #version line it gets
+const WEBGL1: &str = "WEBGL1";
+const VERSION_100: &str = "#version 100";
+
+type CacheKey = (&'static str, Vec<&'static str>);
+
+thread_local! {
+    static CACHE: RefCell<HashMap<CacheKey, Rc<ShaderSource>>> = RefCell::new(HashMap::new());
+}
+
+// Struct declarations
+
+#[derive(Debug)]
+pub struct ShaderSource {
+    pub name: &'static str, // file the shader was expanded from
+    pub text: String,
+    origins: Vec<(&'static str, usize)> // file and line each line of `text` came from
+}
+
+// Struct implementations
+
+impl ShaderSource {
+    // `log` with every "0:12:" location, the source string and line in the
+    // format WebGL compilers report them in, replaced by the file and line
+    pub fn map_log(&self, log: &str) -> String {
+        return log.lines().map(|line: &str| self.map_line(line)).collect::<Vec<String>>().join("\n");
+    }
+
+    // File and line line `line` of `text` came from, counting from 1
+    pub fn origin(&self, line: usize) -> Option<(&'static str, usize)> {
+        return line.checked_sub(1).and_then(|i: usize| self.origins.get(i)).copied();
+    }
+
+    ///////////////////////
+    // Private functions //
+    ///////////////////////
+
+    fn map_line(&self, line: &str) -> String {
+        for (at, _) in line.match_indices("0:") {
+            if (line[..at].ends_with(|c: char| c.is_ascii_digit())) {
+                continue;
+            }
+            let rest: &str = &line[at + 2..];
+            let digits: usize = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            if (digits == 0 || !rest[digits..].starts_with(':')) {
+                continue;
+            }
+            let Some((file, number)) = rest[..digits].parse::<usize>().ok().and_then(|n: usize| self.origin(n)) else {
+                continue;
+            };
+            return format!("{}{}:{}{}", &line[..at], file, number, &rest[digits..]);
+        }
+        return String::from(line);
+    }
+
+    fn push(&mut self, line: &str, file: &'static str, number: usize) {
+        self.text.push_str(line);
+        self.text.push('\n');
+        self.origins.push((file, number));
+    }
+}
+
+// Helper functions
+
+// `name` with its chunks included and `switches` defined
+pub fn shader_source(name: &'static str, switches: &[&'static str]) -> Result<Rc<ShaderSource>, ModmulError> {
+    let key: CacheKey = (name, switches.to_vec());
+    if let Some(source) = CACHE.with(|cache: &RefCell<HashMap<CacheKey, Rc<ShaderSource>>>| cache.borrow().get(&key).cloned()) {
+        return Ok(source);
+    }
+
+    let (name, text): (&'static str, &'static str) = find_file(name).ok_or(ModmulError::unknown("shader", name))?;
+    let mut source: ShaderSource = ShaderSource {
+        name,
+        text: String::new(),
+        origins: Vec::new()
+    };
+    let mut lines: std::iter::Enumerate<std::str::Lines> = text.lines().enumerate();
+
+    // #version has to come first, the switches go right after it
+    if let Some((_, first)) = lines.next() {
+        if (!first.trim_start().starts_with("#version")) {
+            return Err(ModmulError::ShaderCompile {shader: name, log: format!("{}:1: #version has to be the first line", name)});
+        }
+        source.push(if (switches.contains(&WEBGL1)) { VERSION_100 } else { first }, name, 1);
+    }
+    for switch in switches {
+        source.push(&format!("#define {}", switch), name, 1);
+    }
+    let mut included: Vec<&'static str> = Vec::new();
+    expand(name, lines, &mut included, &mut source)?;
+
+    let source: Rc<ShaderSource> = Rc::new(source);
+    CACHE.with(|cache: &RefCell<HashMap<CacheKey, Rc<ShaderSource>>>| cache.borrow_mut().insert(key, source.clone()));
+    return Ok(source);
+}
+
+// Append `lines` of `file` to `source`, chunks in place of their #include
+fn expand(file: &'static str, lines: std::iter::Enumerate<std::str::Lines<'static>>, included: &mut Vec<&'static str>, source: &mut ShaderSource) -> Result<(), ModmulError> {
+    for (i, line) in lines {
+        let Some(target) = include_target(line) else {
+            source.push(line, file, i + 1);
+            continue;
+        };
+        let (chunk, text): (&'static str, &'static str) = find_file(target).ok_or_else(|| ModmulError::ShaderCompile {
+            shader: source.name,
+            log: format!("{}:{}: no chunk named {}", file, i + 1, target)
+        })?;
+        if (!included.contains(&chunk)) {
+            included.push(chunk);
+            expand(chunk, text.lines().enumerate(), included, source)?;
+        }
+    }
+    return Ok(());
+}
+
+// The file named in `line` if it is an #include
+fn include_target(line: &str) -> Option<&str> {
+    let rest: &str = line.trim().strip_prefix("#include")?;
+    return rest.trim().strip_prefix('"')?.strip_suffix('"');
+}
+
+fn find_file(name: &str) -> Option<(&'static str, &'static str)> {
+    return FILES.iter().find(|(n, _)| *n == name).copied();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_are_included_once_after_the_switches() {
+        let source: Rc<ShaderSource> = shader_source("rect_shader.vert", &["WEBGL1"]).unwrap();
+        let lines: Vec<&str> = source.text.lines().collect();
+        assert_eq!(lines[0], "#version 100");
+        assert_eq!(lines[1], "#define WEBGL1");
+        assert!(!source.text.contains("#include"));
+        assert_eq!(source.text.matches("#define PI ").count(), 1);
+        assert!(Rc::ptr_eq(&source, &shader_source("rect_shader.vert", &["WEBGL1"]).unwrap()));
+        assert_eq!(shader_source("rect_shader.vert", &[]).unwrap().text.lines().next(), Some("#version 300 es"));
+    }
+
+    #[test]
+    fn compile_logs_point_at_the_original_file_and_line() {
+        let source: Rc<ShaderSource> = shader_source("point_shader.vert", &[]).unwrap();
+        let line: usize = source.text.lines().position(|l: &str| l.starts_with("vec2 circle_point(")).unwrap() + 1;
+        let chunk_line: usize = include_str!("circle.glsl").lines().position(|l: &str| l.starts_with("vec2 circle_point(")).unwrap() + 1;
+        assert_eq!(source.origin(line), Some(("circle.glsl", chunk_line)));
+        assert_eq!(
+            source.map_log(&format!("ERROR: 0:{}: 'x' : undeclared identifier\nERROR: 1 compilation errors.", line)),
+            format!("ERROR: circle.glsl:{}: 'x' : undeclared identifier\nERROR: 1 compilation errors.", chunk_line)
+        );
+    }
+}
@@ -0,0 +1,233 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Undo/redo history. Every entry keeps the scene as it was before a change,
+// which is small enough that snapshots are simpler than inverse commands.
+//
+// Coalescing: a change of the same kind as the previous one, arriving within
+// COALESCE_MS of it, extends that entry instead of adding a new one. So a
+// drag, a wheel flick or a slider sweep becomes a single undo step.
+// `checkpoint` ends the current group early, e.g. when a drag ends.
+
+use std::collections::VecDeque;
+
+use crate::scene::Scene;
+
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+const COALESCE_MS: f64 = 750.0;
+
+// Struct declarations
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Points,
+    Multiplier,
+    Rotation,
+    ViewRotation,
+    Move,
+    Zoom,
+    View, // pan, zoom and rotation at once, from a two finger gesture
+    RectWidth,
+    Outline,
+    RenderMode,
+    Reset,
+    Preset,
+    Fit, // fit_view, fit_orbit and box zoom
+    Load
+}
+
+struct Entry {
+    change: Change,
+    before: Scene,
+    last_ms: f64 // time of the latest change merged into this entry
+}
+
+pub struct History {
+    undo: VecDeque<Entry>,
+    redo: Vec<Scene>,
+    limit: usize,
+    sealed: bool // true when the next change must start a new entry
+}
+
+// Struct implementations
+
+impl Change {
+    // Continuous adjustments merge, one-off actions never do
+    fn coalesces(&self) -> bool {
+        return matches!(self, Change::Points | Change::Multiplier | Change::Rotation | Change::ViewRotation | Change::Move | Change::Zoom | Change::View | Change::RectWidth);
+    }
+}
+
+impl History {
+    pub fn new(limit: usize) -> History {
+        return History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            limit: limit.max(1),
+            sealed: false
+        };
+    }
+
+    // Record that `before` was the scene just before a `change` at `now_ms`
+    pub fn record(&mut self, change: Change, before: &Scene, now_ms: f64) {
+        self.redo.clear();
+
+        if (!self.sealed && change.coalesces())
+            && let Some(last) = self.undo.back_mut()
+            && last.change == change
+            && now_ms - last.last_ms <= COALESCE_MS {
+            last.last_ms = now_ms;
+            return;
+        }
+
+        self.undo.push_back(Entry {
+            change,
+            before: before.clone(),
+            last_ms: now_ms
+        });
+        while (self.undo.len() > self.limit) {
+            self.undo.pop_front();
+        }
+        self.sealed = false;
+    }
+
+    // Make the next change start a new entry
+    pub fn checkpoint(&mut self) {
+        self.sealed = true;
+    }
+
+    // Scene to go back to, given the one currently shown
+    pub fn undo(&mut self, current: &Scene) -> Option<Scene> {
+        let entry: Entry = self.undo.pop_back()?;
+        self.redo.push(current.clone());
+        self.sealed = true;
+        return Some(entry.before);
+    }
+
+    pub fn redo(&mut self, current: &Scene) -> Option<Scene> {
+        let next: Scene = self.redo.pop()?;
+        self.undo.push_back(Entry {
+            change: Change::Load,
+            before: current.clone(),
+            last_ms: f64::NEG_INFINITY
+        });
+        self.sealed = true;
+        return Some(next);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        return !self.undo.is_empty();
+    }
+
+    pub fn can_redo(&self) -> bool {
+        return !self.redo.is_empty();
+    }
+
+    pub fn limit(&self) -> usize {
+        return self.limit;
+    }
+
+    // Drops the oldest entries if the history is already longer
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit.max(1);
+        while (self.undo.len() > self.limit) {
+            self.undo.pop_front();
+        }
+        while (self.redo.len() > self.limit) {
+            self.redo.remove(0);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene(points: u32) -> Scene {
+        let mut scene: Scene = Scene::new(800, 600);
+        scene.set_points(points);
+        return scene;
+    }
+
+    #[test]
+    fn changes_within_the_window_merge() {
+        let mut history: History = History::new(DEFAULT_HISTORY_LIMIT);
+        history.record(Change::Points, &scene(1), 0.0);
+        history.record(Change::Points, &scene(2), 500.0);
+        // Measured from the latest merged change, not the first
+        history.record(Change::Points, &scene(3), 500.0 + COALESCE_MS);
+        assert_eq!(history.undo(&scene(4)), Some(scene(1)));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn changes_past_the_window_or_of_another_kind_or_after_a_checkpoint_dont() {
+        let mut history: History = History::new(DEFAULT_HISTORY_LIMIT);
+        history.record(Change::Points, &scene(1), 0.0);
+        history.record(Change::Points, &scene(2), COALESCE_MS + 1.0);
+        history.record(Change::Multiplier, &scene(3), COALESCE_MS + 2.0);
+        history.checkpoint();
+        history.record(Change::Multiplier, &scene(4), COALESCE_MS + 3.0);
+        history.record(Change::Reset, &scene(5), COALESCE_MS + 4.0);
+        history.record(Change::Reset, &scene(6), COALESCE_MS + 5.0);
+        for expected in (1..=6).rev() {
+            assert_eq!(history.undo(&scene(7)), Some(scene(expected)));
+        }
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn a_new_change_clears_redo() {
+        let mut history: History = History::new(DEFAULT_HISTORY_LIMIT);
+        history.record(Change::Reset, &scene(1), 0.0);
+        assert_eq!(history.undo(&scene(2)), Some(scene(1)));
+        assert!(history.can_redo());
+        assert_eq!(history.redo(&scene(1)), Some(scene(2)));
+        assert_eq!(history.undo(&scene(2)), Some(scene(1)));
+
+        history.record(Change::Reset, &scene(1), 10.0);
+        assert!(!history.can_redo());
+        assert_eq!(history.redo(&scene(3)), None);
+    }
+
+    #[test]
+    fn the_oldest_entries_go_past_the_limit() {
+        let mut history: History = History::new(3);
+        for i in 1..=5 {
+            history.record(Change::Reset, &scene(i), i as f64);
+        }
+        for expected in [5, 4, 3] {
+            assert_eq!(history.undo(&scene(6)), Some(scene(expected)));
+        }
+        assert!(!history.can_undo());
+
+        // Lowering the limit drops from the old end too
+        let mut history: History = History::new(DEFAULT_HISTORY_LIMIT);
+        for i in 1..=5 {
+            history.record(Change::Reset, &scene(i), i as f64);
+        }
+        history.set_limit(2);
+        assert_eq!(history.limit(), 2);
+        assert_eq!(history.undo(&scene(6)), Some(scene(5)));
+        assert_eq!(history.undo(&scene(5)), Some(scene(4)));
+        assert!(!history.can_undo());
+    }
+}
@@ -0,0 +1,921 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Pointer and wheel input on the canvas element. The listeners live in Rust
+// so that client coordinates are turned into view units by the camera, in
+// one place, instead of by copies of the same math in JS.
+//
+// Every pointer that goes down on the canvas is tracked. With one pointer
+// down, moving it drags, and what a drag does depends on the button that
+// started it. With two, their midpoint pans, their distance zooms and the
+// angle between them rotates, all around the midpoint. Further pointers are
+// ignored until one of the first two lifts.
+//
+// Gesture names and actions, as seen from JS:
+//   "primary_drag"     default "pan"     left button, finger or pen
+//   "secondary_drag"   default "rotate"  right button
+//   "middle_drag"      default "zoom"    middle button
+//   "wheel"            default "zoom"
+//   "ctrl_wheel"       default "zoom"    also what touchpad pinches send
+//   "shift_wheel"      default "rotate"
+//   "two_finger_drag"  "pan" or "none"
+//   "pinch"            "zoom" or "none"
+//   "twist"            "rotate" or "none"
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{AddEventListenerOptions, Document, Event, EventTarget, HtmlCanvasElement, HtmlElement, KeyboardEvent, MouseEvent, PointerEvent, WheelEvent};
+
+use crate::camera::Camera;
+use crate::canvas_state::{CanvasState, dispatch_changes};
+use crate::error::ModmulError;
+use crate::fit::FIT_DURATION_MS;
+use crate::frame::now_ms;
+use crate::history::Change;
+use crate::keymap::claim_keys;
+use crate::scene::{Point, Scene};
+
+// Zoom factor per view unit dragged, as a power of e. Dragging up across
+// half the shorter side of the surface zooms in about 7x.
+const DRAG_ZOOM_RATE: f64 = 2.0;
+
+// Wheel deltas are scaled to a notch of a typical mouse wheel, 100 pixels
+const WHEEL_NOTCH: f64 = 100.0;
+const WHEEL_ZOOM_STEP: f64 = 1.2; // zoom factor per notch
+const WHEEL_ROTATE_STEP: f64 = 15.0; // degrees per notch
+const LINE_HEIGHT: f64 = 16.0; // pixels per line, for wheels reporting lines
+
+// Boxes smaller than this many CSS pixels either way count as a click
+const MIN_BOX: f32 = 4.0;
+
+type Listener = Closure<dyn FnMut(Event)>;
+
+// Struct declarations
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gesture {
+    PrimaryDrag,
+    SecondaryDrag,
+    MiddleDrag,
+    Wheel,
+    CtrlWheel,
+    ShiftWheel,
+    TwoFingerDrag,
+    Pinch,
+    Twist
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GestureAction {
+    None,
+    Pan,
+    Zoom,
+    Rotate
+}
+
+// What each gesture does
+#[derive(Clone, Debug, PartialEq)]
+pub struct GestureMap {
+    primary_drag: GestureAction,
+    secondary_drag: GestureAction,
+    middle_drag: GestureAction,
+    wheel: GestureAction,
+    ctrl_wheel: GestureAction,
+    shift_wheel: GestureAction,
+    two_finger_drag: GestureAction,
+    pinch: GestureAction,
+    twist: GestureAction
+}
+
+// Camera change produced by one input event. Zoom and rotation keep the
+// world point under `anchor` in place, and are applied after the pan.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraMove {
+    pub pan: Point, // view units
+    pub zoom: f64, // factor
+    pub rotate: f64, // radians, counter-clockwise
+    pub anchor: Point // view coordinates
+}
+
+struct Pointer {
+    id: i32,
+    action: GestureAction, // what dragging this pointer alone does
+    press: Point, // view position where it went down
+    position: Point // latest view position
+}
+
+// Pointers currently down, in the order they went down
+pub struct PointerTracker {
+    pointers: Vec<Pointer>
+}
+
+// Box being dragged out for a box zoom, drawn as an element over the page
+pub struct RubberBand {
+    id: i32, // pointer drawing it
+    press: (f32, f32), // client coordinates where it started
+    element: Option<HtmlElement>
+}
+
+// The parts of a pointer, wheel or key event the handlers read. Built from
+// the DOM event on the page, or from the copy of one the page posted to a
+// worker, whose client coordinates are relative to the canvas element.
+pub struct InputEvent {
+    pub kind: String, // event type, e.g. "pointerdown"
+    pub pointer_id: i32,
+    pub button: i16,
+    pub pointer_type: String,
+    pub x: f32, // client coordinates
+    pub y: f32,
+    pub delta: (f64, f64), // wheel, in `delta_mode` units
+    pub delta_mode: u32,
+    pub key: String,
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub meta: bool,
+    pub time_ms: f64,
+    event: Option<Event> // none for copies, whose default the page handles
+}
+
+// Listeners attached to the canvas element and the document, removed
+// again on drop
+pub struct InputListeners {
+    listeners: Vec<(EventTarget, &'static str, Listener)>
+}
+
+// Struct implementations
+
+impl Gesture {
+    pub fn from_name(name: &str) -> Option<Gesture> {
+        return match name {
+            "primary_drag" => Some(Gesture::PrimaryDrag),
+            "secondary_drag" => Some(Gesture::SecondaryDrag),
+            "middle_drag" => Some(Gesture::MiddleDrag),
+            "wheel" => Some(Gesture::Wheel),
+            "ctrl_wheel" => Some(Gesture::CtrlWheel),
+            "shift_wheel" => Some(Gesture::ShiftWheel),
+            "two_finger_drag" => Some(Gesture::TwoFingerDrag),
+            "pinch" => Some(Gesture::Pinch),
+            "twist" => Some(Gesture::Twist),
+            _ => None
+        };
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Gesture::PrimaryDrag => "primary_drag",
+            Gesture::SecondaryDrag => "secondary_drag",
+            Gesture::MiddleDrag => "middle_drag",
+            Gesture::Wheel => "wheel",
+            Gesture::CtrlWheel => "ctrl_wheel",
+            Gesture::ShiftWheel => "shift_wheel",
+            Gesture::TwoFingerDrag => "two_finger_drag",
+            Gesture::Pinch => "pinch",
+            Gesture::Twist => "twist"
+        };
+    }
+
+    // Two finger gestures only make sense with their own action
+    pub fn allows(&self, action: GestureAction) -> bool {
+        return match self {
+            Gesture::TwoFingerDrag => matches!(action, GestureAction::Pan | GestureAction::None),
+            Gesture::Pinch => matches!(action, GestureAction::Zoom | GestureAction::None),
+            Gesture::Twist => matches!(action, GestureAction::Rotate | GestureAction::None),
+            _ => true
+        };
+    }
+}
+
+impl GestureAction {
+    pub fn from_name(name: &str) -> Option<GestureAction> {
+        return match name {
+            "none" => Some(GestureAction::None),
+            "pan" => Some(GestureAction::Pan),
+            "zoom" => Some(GestureAction::Zoom),
+            "rotate" => Some(GestureAction::Rotate),
+            _ => None
+        };
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            GestureAction::None => "none",
+            GestureAction::Pan => "pan",
+            GestureAction::Zoom => "zoom",
+            GestureAction::Rotate => "rotate"
+        };
+    }
+}
+
+impl Default for GestureMap {
+    fn default() -> GestureMap {
+        return GestureMap {
+            primary_drag: GestureAction::Pan,
+            secondary_drag: GestureAction::Rotate,
+            middle_drag: GestureAction::Zoom,
+            wheel: GestureAction::Zoom,
+            ctrl_wheel: GestureAction::Zoom,
+            shift_wheel: GestureAction::Rotate,
+            two_finger_drag: GestureAction::Pan,
+            pinch: GestureAction::Zoom,
+            twist: GestureAction::Rotate
+        };
+    }
+}
+
+impl GestureMap {
+    pub fn get(&self, gesture: Gesture) -> GestureAction {
+        return *self.slot(gesture);
+    }
+
+    pub fn set(&mut self, gesture: Gesture, action: GestureAction) -> Result<(), String> {
+        if (!gesture.allows(action)) {
+            return Err(format!("Gesture {} can't {}", gesture.name(), action.name()));
+        }
+        *self.slot_mut(gesture) = action;
+        return Ok(());
+    }
+
+    // Action for a mouse button as in `MouseEvent.button`, touches and pen
+    // contacts report 0
+    pub fn for_button(&self, button: i16) -> GestureAction {
+        return match button {
+            0 => self.primary_drag,
+            1 => self.middle_drag,
+            2 => self.secondary_drag,
+            _ => GestureAction::None
+        };
+    }
+
+    ///////////////////////
+    // Private functions //
+    ///////////////////////
+
+    fn slot(&self, gesture: Gesture) -> &GestureAction {
+        return match gesture {
+            Gesture::PrimaryDrag => &self.primary_drag,
+            Gesture::SecondaryDrag => &self.secondary_drag,
+            Gesture::MiddleDrag => &self.middle_drag,
+            Gesture::Wheel => &self.wheel,
+            Gesture::CtrlWheel => &self.ctrl_wheel,
+            Gesture::ShiftWheel => &self.shift_wheel,
+            Gesture::TwoFingerDrag => &self.two_finger_drag,
+            Gesture::Pinch => &self.pinch,
+            Gesture::Twist => &self.twist
+        };
+    }
+
+    fn slot_mut(&mut self, gesture: Gesture) -> &mut GestureAction {
+        return match gesture {
+            Gesture::PrimaryDrag => &mut self.primary_drag,
+            Gesture::SecondaryDrag => &mut self.secondary_drag,
+            Gesture::MiddleDrag => &mut self.middle_drag,
+            Gesture::Wheel => &mut self.wheel,
+            Gesture::CtrlWheel => &mut self.ctrl_wheel,
+            Gesture::ShiftWheel => &mut self.shift_wheel,
+            Gesture::TwoFingerDrag => &mut self.two_finger_drag,
+            Gesture::Pinch => &mut self.pinch,
+            Gesture::Twist => &mut self.twist
+        };
+    }
+}
+
+impl CameraMove {
+    pub fn new(anchor: Point) -> CameraMove {
+        return CameraMove {
+            pan: Point::new(0.0, 0.0),
+            zoom: 1.0,
+            rotate: 0.0,
+            anchor
+        };
+    }
+
+    pub fn is_identity(&self) -> bool {
+        return self.pan == Point::new(0.0, 0.0) && self.zoom == 1.0 && self.rotate == 0.0;
+    }
+
+    // History entry kind, a move doing several things at once is its own
+    pub fn change(&self) -> Change {
+        let panned: bool = self.pan != Point::new(0.0, 0.0);
+        let zoomed: bool = self.zoom != 1.0;
+        let rotated: bool = self.rotate != 0.0;
+        return match (panned, zoomed, rotated) {
+            (_, false, false) => Change::Move,
+            (false, true, false) => Change::Zoom,
+            (false, false, true) => Change::ViewRotation,
+            _ => Change::View
+        };
+    }
+
+    pub fn apply(&self, camera: &mut Camera) {
+        if (self.pan != Point::new(0.0, 0.0)) {
+            camera.pan(self.pan.x, self.pan.y);
+        }
+        if (self.zoom != 1.0) {
+            camera.zoom_at(self.zoom, self.anchor);
+        }
+        if (self.rotate != 0.0) {
+            camera.rotate_at(self.rotate, self.anchor);
+        }
+    }
+
+    // One pointer moved from `from` to `to`, having gone down at `press`
+    pub fn drag(action: GestureAction, press: Point, from: Point, to: Point) -> Option<CameraMove> {
+        let mut out: CameraMove;
+        match action {
+            GestureAction::None => return None,
+            GestureAction::Pan => {
+                out = CameraMove::new(to);
+                out.pan = Point::new(to.x - from.x, to.y - from.y);
+            }
+            GestureAction::Zoom => {
+                out = CameraMove::new(press);
+                out.zoom = ((to.y - from.y) as f64 * DRAG_ZOOM_RATE).exp();
+            }
+            GestureAction::Rotate => {
+                // Around the middle of the surface, following the pointer
+                out = CameraMove::new(Point::new(0.0, 0.0));
+                if (from == out.anchor || to == out.anchor) {
+                    return None;
+                }
+                out.rotate = angle_between(from, to);
+            }
+        }
+        return Some(out);
+    }
+
+    // Two pointers moved from `from` to `to`
+    pub fn two_finger(map: &GestureMap, from: (Point, Point), to: (Point, Point)) -> CameraMove {
+        let mid_from: Point = midpoint(from.0, from.1);
+        let mid_to: Point = midpoint(to.0, to.1);
+        let mut out: CameraMove = CameraMove::new(mid_to);
+
+        if (map.two_finger_drag == GestureAction::Pan) {
+            out.pan = Point::new(mid_to.x - mid_from.x, mid_to.y - mid_from.y);
+        }
+        let span_from: Point = Point::new(from.1.x - from.0.x, from.1.y - from.0.y);
+        let span_to: Point = Point::new(to.1.x - to.0.x, to.1.y - to.0.y);
+        let len_from: f64 = (span_from.x as f64).hypot(span_from.y as f64);
+        let len_to: f64 = (span_to.x as f64).hypot(span_to.y as f64);
+        if (len_from > 0.0 && len_to > 0.0) {
+            if (map.pinch == GestureAction::Zoom) {
+                out.zoom = len_to / len_from;
+            }
+            if (map.twist == GestureAction::Rotate) {
+                out.rotate = angle_between(span_from, span_to);
+            }
+        }
+        return out;
+    }
+
+    // A wheel turned by `delta` pixels, `delta_view` being the same in view
+    // units, with the cursor at `anchor`
+    pub fn wheel(action: GestureAction, delta: (f64, f64), delta_view: Point, anchor: Point) -> Option<CameraMove> {
+        // Some systems turn shift + wheel into a horizontal scroll
+        let amount: f64 = if (delta.1.abs() >= delta.0.abs()) { delta.1 } else { delta.0 };
+        let mut out: CameraMove = CameraMove::new(anchor);
+        match action {
+            GestureAction::None => return None,
+            GestureAction::Pan => out.pan = Point::new(-delta_view.x, -delta_view.y),
+            GestureAction::Zoom => out.zoom = WHEEL_ZOOM_STEP.powf(-amount / WHEEL_NOTCH),
+            GestureAction::Rotate => out.rotate = (-amount / WHEEL_NOTCH * WHEEL_ROTATE_STEP).to_radians()
+        }
+        return Some(out);
+    }
+}
+
+impl PointerTracker {
+    pub fn new() -> PointerTracker {
+        return PointerTracker {
+            pointers: Vec::new()
+        };
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.pointers.is_empty();
+    }
+
+    pub fn contains(&self, id: i32) -> bool {
+        return self.pointers.iter().any(|p: &Pointer| p.id == id);
+    }
+
+    pub fn down(&mut self, id: i32, action: GestureAction, position: Point) {
+        self.pointers.retain(|p: &Pointer| p.id != id);
+        self.pointers.push(Pointer {
+            id,
+            action,
+            press: position,
+            position
+        });
+    }
+
+    // Pointer `id` is now at `position`, returns what that does to the camera
+    pub fn moved(&mut self, id: i32, position: Point, map: &GestureMap) -> Option<CameraMove> {
+        let index: usize = self.pointers.iter().position(|p: &Pointer| p.id == id)?;
+        let from: Point = self.pointers[index].position;
+        self.pointers[index].position = position;
+        if (from == position || index >= 2) {
+            return None;
+        }
+
+        if (self.pointers.len() == 1) {
+            let pointer: &Pointer = &self.pointers[0];
+            return CameraMove::drag(pointer.action, pointer.press, from, position);
+        }
+        let (a, b): (Point, Point) = (self.pointers[0].position, self.pointers[1].position);
+        let before: (Point, Point) = if (index == 0) { (from, b) } else { (a, from) };
+        let out: CameraMove = CameraMove::two_finger(map, before, (a, b));
+        if (out.is_identity()) {
+            return None;
+        }
+        return Some(out);
+    }
+
+    // Returns true when this was the last pointer down
+    pub fn up(&mut self, id: i32) -> bool {
+        let count: usize = self.pointers.len();
+        self.pointers.retain(|p: &Pointer| p.id != id);
+        return count > 0 && self.pointers.is_empty();
+    }
+}
+
+impl Default for PointerTracker {
+    fn default() -> PointerTracker {
+        return PointerTracker::new();
+    }
+}
+
+impl InputEvent {
+    pub fn from_event(e: &Event) -> InputEvent {
+        let mut out: InputEvent = InputEvent::empty(e.type_(), e.time_stamp());
+        if let Some(e) = e.dyn_ref::<MouseEvent>() {
+            out.button = e.button();
+            out.x = e.client_x() as f32;
+            out.y = e.client_y() as f32;
+            out.shift = e.shift_key();
+            out.ctrl = e.ctrl_key();
+            out.alt = e.alt_key();
+            out.meta = e.meta_key();
+        }
+        if let Some(e) = e.dyn_ref::<PointerEvent>() {
+            out.pointer_id = e.pointer_id();
+            out.pointer_type = e.pointer_type();
+        }
+        if let Some(e) = e.dyn_ref::<WheelEvent>() {
+            out.delta = (e.delta_x(), e.delta_y());
+            out.delta_mode = e.delta_mode();
+        }
+        if let Some(e) = e.dyn_ref::<KeyboardEvent>() {
+            out.key = e.key();
+            out.shift = e.shift_key();
+            out.ctrl = e.ctrl_key();
+            out.alt = e.alt_key();
+            out.meta = e.meta_key();
+        }
+        out.event = Some(e.clone());
+        return out;
+    }
+
+    // Copy of an event posted by the page, with the DOM property names.
+    // `now_ms` replaces its time stamp, which counts from the page's start.
+    pub fn from_message(message: &JsValue, now_ms: f64) -> Result<InputEvent, String> {
+        let kind: String = js_sys::Reflect::get(message, &JsValue::from_str("type")).ok()
+            .and_then(|value: JsValue| value.as_string())
+            .ok_or(String::from("Input event without a type"))?;
+        let number = |name: &str| -> f64 {
+            return js_sys::Reflect::get(message, &JsValue::from_str(name)).ok().and_then(|value: JsValue| value.as_f64()).unwrap_or(0.0);
+        };
+        let flag = |name: &str| -> bool {
+            return js_sys::Reflect::get(message, &JsValue::from_str(name)).is_ok_and(|value: JsValue| value.is_truthy());
+        };
+        let text = |name: &str| -> String {
+            return js_sys::Reflect::get(message, &JsValue::from_str(name)).ok().and_then(|value: JsValue| value.as_string()).unwrap_or_default();
+        };
+        let mut out: InputEvent = InputEvent::empty(kind, now_ms);
+        out.pointer_id = number("pointerId") as i32;
+        out.button = number("button") as i16;
+        out.pointer_type = text("pointerType");
+        out.x = number("clientX") as f32;
+        out.y = number("clientY") as f32;
+        out.delta = (number("deltaX"), number("deltaY"));
+        out.delta_mode = number("deltaMode") as u32;
+        out.key = text("key");
+        out.shift = flag("shiftKey");
+        out.ctrl = flag("ctrlKey");
+        out.alt = flag("altKey");
+        out.meta = flag("metaKey");
+        return Ok(out);
+    }
+
+    pub fn prevent_default(&self) {
+        if let Some(event) = &self.event {
+            event.prevent_default();
+        }
+    }
+
+    pub fn default_prevented(&self) -> bool {
+        return self.event.as_ref().is_some_and(|event: &Event| event.default_prevented());
+    }
+
+    // Element the event was dispatched to, none for copies
+    pub fn target(&self) -> Option<web_sys::Element> {
+        return self.event.as_ref().and_then(|event: &Event| event.target()).and_then(|target: EventTarget| target.dyn_into::<web_sys::Element>().ok());
+    }
+
+    ///////////////////////
+    // Private functions //
+    ///////////////////////
+
+    fn empty(kind: String, time_ms: f64) -> InputEvent {
+        return InputEvent {
+            kind,
+            pointer_id: 0,
+            button: 0,
+            pointer_type: String::new(),
+            x: 0.0,
+            y: 0.0,
+            delta: (0.0, 0.0),
+            delta_mode: 0,
+            key: String::new(),
+            shift: false,
+            ctrl: false,
+            alt: false,
+            meta: false,
+            time_ms,
+            event: None
+        };
+    }
+}
+
+impl RubberBand {
+    pub fn new(document: &Document, id: i32, press: (f32, f32), color: &str) -> RubberBand {
+        let element: Option<HtmlElement> = document.create_element("div").ok()
+            .and_then(|element: web_sys::Element| element.dyn_into::<HtmlElement>().ok());
+        if let (Some(element), Some(body)) = (&element, document.body()) {
+            let style: web_sys::CssStyleDeclaration = element.style();
+            let _ = style.set_property("position", "fixed");
+            let _ = style.set_property("pointer-events", "none");
+            let _ = style.set_property("border", &format!("1px dashed {}", color));
+            let _ = style.set_property("z-index", "10");
+            let _ = body.append_child(element);
+        }
+        let band: RubberBand = RubberBand {
+            id,
+            press,
+            element
+        };
+        band.update(press);
+        return band;
+    }
+
+    // Stretch the box to the client coordinates `to`
+    pub fn update(&self, to: (f32, f32)) {
+        let Some(element) = &self.element else {
+            return;
+        };
+        let style: web_sys::CssStyleDeclaration = element.style();
+        let _ = style.set_property("left", &format!("{}px", self.press.0.min(to.0)));
+        let _ = style.set_property("top", &format!("{}px", self.press.1.min(to.1)));
+        let _ = style.set_property("width", &format!("{}px", (to.0 - self.press.0).abs()));
+        let _ = style.set_property("height", &format!("{}px", (to.1 - self.press.1).abs()));
+    }
+
+    // Whether a box ending at `to` is big enough to zoom into
+    pub fn is_box(&self, to: (f32, f32)) -> bool {
+        return (to.0 - self.press.0).abs().max((to.1 - self.press.1).abs()) >= MIN_BOX;
+    }
+}
+
+impl Drop for RubberBand {
+    fn drop(&mut self) {
+        if let Some(element) = &self.element {
+            element.remove();
+        }
+    }
+}
+
+impl CanvasState {
+    // Gesture and action names are listed at the top of this file
+    pub fn set_gesture(&mut self, gesture: &str, action: &str) -> Result<(), ModmulError> {
+        let gesture: Gesture = Gesture::from_name(gesture).ok_or(ModmulError::unknown("gesture", gesture))?;
+        let action: GestureAction = GestureAction::from_name(action).ok_or(ModmulError::unknown("action", action))?;
+        return self.gestures.set(gesture, action).map_err(ModmulError::from);
+    }
+
+    pub fn get_gesture(&self, gesture: &str) -> Result<String, ModmulError> {
+        let gesture: Gesture = Gesture::from_name(gesture).ok_or(ModmulError::unknown("gesture", gesture))?;
+        return Ok(String::from(self.gestures.get(gesture).name()));
+    }
+
+    // Zoom by `factor` around the view position `anchor` over the next few
+    // frames, or at once with reduced motion
+    pub(crate) fn zoom_eased(&mut self, now_ms: f64, factor: f64, anchor: Point) {
+        let reduced_motion: bool = self.reduced_motion();
+        match self.motion.zoom_by(now_ms, factor, anchor, reduced_motion) {
+            Some(camera_move) => {
+                self.mutate(camera_move.change(), |scene: &mut Scene| camera_move.apply(&mut scene.camera));
+            }
+            None => self.frames.request()
+        }
+    }
+
+    // Copy of an input event the page posted to a worker, see worker.js
+    pub fn forward_input(&mut self, event: &JsValue) -> Result<(), ModmulError> {
+        let e: InputEvent = InputEvent::from_message(event, now_ms())?;
+        self.on_input(&e);
+        return Ok(());
+    }
+
+    // Handle an input event of any of the types listened to
+    pub(crate) fn on_input(&mut self, e: &InputEvent) {
+        match e.kind.as_str() {
+            "pointerdown" => self.on_pointer_down(e),
+            "pointermove" => self.on_pointer_move(e),
+            "pointerup" => self.on_pointer_up(e),
+            "pointercancel" => self.on_pointer_cancel(e),
+            "wheel" => self.on_wheel(e),
+            "contextmenu" => self.on_context_menu(e),
+            "keydown" => self.on_key_down(e),
+            _ => {}
+        }
+    }
+
+    // Keep sending the moves of pointer `id` to the canvas when it leaves it
+    pub(crate) fn capture_pointer(&self, id: i32) {
+        if let Some(canvas) = self.surface.element() {
+            let _ = canvas.set_pointer_capture(id);
+        }
+    }
+
+    ///////////////////////
+    // Private functions //
+    ///////////////////////
+
+    fn set_cursor(&self, cursor: Option<&str>) {
+        let Some(canvas) = self.surface.element() else {
+            return;
+        };
+        let _ = match cursor {
+            Some(cursor) => canvas.style().set_property("cursor", cursor),
+            None => canvas.style().remove_property("cursor").map(|_| ())
+        };
+    }
+
+    fn on_pointer_down(&mut self, e: &InputEvent) {
+        claim_keys(self.id, true);
+        if (self.gallery_pointer_down(e)) {
+            e.prevent_default();
+            return;
+        }
+        if (self.minimap_pointer_down(e)) {
+            return;
+        }
+        // Box zoom draws its box over the page, so there is none in a worker
+        if (e.shift && e.button == 0 && self.pointers.is_empty() && self.surface.element().is_some()) {
+            self.start_rubber_band(e);
+            return;
+        }
+        if (self.rubber_band.is_some()) {
+            return;
+        }
+        let Some(position) = self.client_to_view(e.x, e.y) else {
+            return;
+        };
+        let action: GestureAction = self.gestures.for_button(e.button);
+        self.pointers.down(e.pointer_id, action, position);
+
+        // Catch a glide still going on
+        self.motion.stop();
+
+        // Keep getting the moves when the pointer leaves the canvas
+        self.capture_pointer(e.pointer_id);
+        if (e.pointer_type == "mouse" && action != GestureAction::None) {
+            self.set_cursor(Some("move"));
+        }
+        e.prevent_default();
+    }
+
+    fn on_pointer_move(&mut self, e: &InputEvent) {
+        if (self.gallery.is_some()) {
+            return;
+        }
+        if (self.minimap_pointer_move(e)) {
+            return;
+        }
+        if let Some(band) = &self.rubber_band {
+            if (band.id == e.pointer_id) {
+                band.update((e.x, e.y));
+            }
+            return;
+        }
+        if (!self.pointers.contains(e.pointer_id)) {
+            return;
+        }
+        let Some(position) = self.client_to_view(e.x, e.y) else {
+            return;
+        };
+        if let Some(camera_move) = self.pointers.moved(e.pointer_id, position, &self.gestures) {
+            self.motion.track(e.time_ms, &camera_move);
+            self.mutate(camera_move.change(), |scene: &mut Scene| camera_move.apply(&mut scene.camera));
+        }
+    }
+
+    fn on_pointer_up(&mut self, e: &InputEvent) {
+        if (self.end_other_pointer(e)) {
+            return;
+        }
+        if (self.pointers.up(e.pointer_id)) {
+            self.set_cursor(None);
+            let reduced_motion: bool = self.reduced_motion();
+            self.motion.release(e.time_ms, reduced_motion);
+            if (self.motion.is_active()) {
+                self.frames.request();
+            } else {
+                // A drag is one undo step, however quickly the next one follows
+                self.checkpoint();
+            }
+        }
+    }
+
+    // The browser took the pointer over, e.g. to scroll. The drag ends where
+    // it is, without gliding on.
+    fn on_pointer_cancel(&mut self, e: &InputEvent) {
+        if (self.end_other_pointer(e)) {
+            return;
+        }
+        if (self.pointers.up(e.pointer_id)) {
+            self.set_cursor(None);
+            self.stop_motion();
+            self.checkpoint();
+        }
+    }
+
+    // End what pointer `e` does in the gallery, on the minimap or with a
+    // rubber band, if any of those has it. Only a pointerup zooms in to the
+    // band.
+    fn end_other_pointer(&mut self, e: &InputEvent) -> bool {
+        if (self.gallery_pointer_up(e)) {
+            return true;
+        }
+        if (self.minimap_pointer_up(e)) {
+            return true;
+        }
+        if let Some(band) = self.rubber_band.take_if(|band: &mut RubberBand| band.id == e.pointer_id) {
+            self.set_cursor(None);
+            let to: (f32, f32) = (e.x, e.y);
+            if (e.kind == "pointerup" && band.is_box(to)) {
+                // Pointer coordinates are always finite, nothing to report
+                let _ = self.zoom_to_box(band.press.0, band.press.1, to.0, to.1, FIT_DURATION_MS);
+            }
+            return true;
+        }
+        return false;
+    }
+
+    fn on_wheel(&mut self, e: &InputEvent) {
+        if (self.gallery.is_some()) {
+            return;
+        }
+        let gesture: Gesture = if (e.ctrl) {
+            Gesture::CtrlWheel
+        } else if (e.shift) {
+            Gesture::ShiftWheel
+        } else {
+            Gesture::Wheel
+        };
+        let action: GestureAction = self.gestures.get(gesture);
+        if (action == GestureAction::None) {
+            return;
+        }
+        // Only now, so an unmapped wheel still scrolls the page
+        e.prevent_default();
+
+        let scale: f64 = match e.delta_mode {
+            WheelEvent::DOM_DELTA_LINE => LINE_HEIGHT,
+            WheelEvent::DOM_DELTA_PAGE => self.surface.client_rect().3,
+            _ => 1.0
+        };
+        let delta: (f64, f64) = (e.delta.0 * scale, e.delta.1 * scale);
+        let (x, y): (f32, f32) = (e.x, e.y);
+        let (Some(anchor), Some(moved)) = (self.client_to_view(x, y), self.client_to_view(x + delta.0 as f32, y + delta.1 as f32)) else {
+            return;
+        };
+        let delta_view: Point = Point::new(moved.x - anchor.x, moved.y - anchor.y);
+        let Some(camera_move) = CameraMove::wheel(action, delta, delta_view, anchor) else {
+            return;
+        };
+
+        // Zoom eases toward where the wheel is taking it
+        if (action == GestureAction::Zoom) {
+            self.zoom_eased(e.time_ms, camera_move.zoom, anchor);
+        } else {
+            self.mutate(camera_move.change(), |scene: &mut Scene| camera_move.apply(&mut scene.camera));
+        }
+    }
+
+    // The context menu would get in the way of dragging with the right button
+    fn on_context_menu(&mut self, e: &InputEvent) {
+        if (self.gestures.get(Gesture::SecondaryDrag) != GestureAction::None) {
+            e.prevent_default();
+        }
+    }
+
+    fn start_rubber_band(&mut self, e: &InputEvent) {
+        let Some(document) = self.surface.element().and_then(|canvas: &HtmlCanvasElement| canvas.owner_document()) else {
+            return;
+        };
+        self.motion.stop();
+        let color: String = self.scene.shape.color.to_hex();
+        self.rubber_band = Some(RubberBand::new(&document, e.pointer_id, (e.x, e.y), &color));
+        self.capture_pointer(e.pointer_id);
+        self.set_cursor(Some("crosshair"));
+        e.prevent_default();
+    }
+
+    fn client_to_view(&self, x: f32, y: f32) -> Option<Point> {
+        let (px, _): (Point, f32) = self.client_to_pixels(x, y)?;
+        let (camera, px): (Camera, Point) = self.camera_at(px);
+        return Some(camera.pixels_to_view(px));
+    }
+}
+
+impl InputListeners {
+    pub fn attach(state: &Rc<RefCell<CanvasState>>) -> Result<InputListeners, JsValue> {
+        let canvas: HtmlCanvasElement = state.borrow().surface.element().cloned().ok_or(ModmulError::Invalid(String::from("Input needs a canvas element")))?;
+        let document: Document = canvas.owner_document().ok_or(ModmulError::NotInDocument)?;
+
+        // Leave touches to us instead of scrolling or zooming the page
+        canvas.style().set_property("touch-action", "none")?;
+
+        let mut listeners: InputListeners = InputListeners {
+            listeners: Vec::new()
+        };
+        for name in ["pointerdown", "pointermove", "pointerup", "pointercancel", "wheel", "contextmenu"] {
+            listeners.add(&canvas, name, state)?;
+        }
+
+        // Keys go to the page as a whole, see keymap.rs
+        listeners.add(&document, "keydown", state)?;
+        return Ok(listeners);
+    }
+
+    ///////////////////////
+    // Private functions //
+    ///////////////////////
+
+    fn add(&mut self, target: &EventTarget, name: &'static str, state: &Rc<RefCell<CanvasState>>) -> Result<(), JsValue> {
+        let state: Rc<RefCell<CanvasState>> = Rc::clone(state);
+        let closure: Listener = Closure::new(move |e: Event| {
+            // Already borrowed means the event fired from inside a call into
+            // the canvas, and is dropped
+            if let Ok(mut state) = state.try_borrow_mut() {
+                state.on_input(&InputEvent::from_event(&e));
+            }
+            dispatch_changes(&state);
+        });
+
+        // Not passive, the handlers need to be able to prevent scrolling
+        let options: AddEventListenerOptions = AddEventListenerOptions::new();
+        options.set_passive(false);
+        target.add_event_listener_with_callback_and_add_event_listener_options(name, closure.as_ref().unchecked_ref(), &options)?;
+        self.listeners.push((target.clone(), name, closure));
+        return Ok(());
+    }
+}
+
+impl Drop for InputListeners {
+    fn drop(&mut self) {
+        for (target, name, closure) in &self.listeners {
+            let _ = target.remove_event_listener_with_callback(name, closure.as_ref().unchecked_ref());
+        }
+    }
+}
+
+// Helper functions
+
+fn midpoint(a: Point, b: Point) -> Point {
+    return Point::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0);
+}
+
+// Counter-clockwise angle from direction `a` to direction `b`, -PI..PI
+fn angle_between(a: Point, b: Point) -> f64 {
+    let (ax, ay): (f64, f64) = (a.x as f64, a.y as f64);
+    let (bx, by): (f64, f64) = (b.x as f64, b.y as f64);
+    return (ax * by - ay * bx).atan2(ax * bx + ay * by);
+}
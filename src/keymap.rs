@@ -0,0 +1,331 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Keyboard shortcuts. Keys are matched by `KeyboardEvent.key`, with single
+// characters in lower case, so "q" is the Q key with or without Shift and
+// named keys are as the browser reports them, e.g. "ArrowUp". Holding Shift
+// makes steps ten times larger. Keys pressed with Ctrl, Alt or Meta, and
+// keys typed into form fields, are left alone.
+//
+// With several canvases on a page only one takes the keys: the one last
+// pressed on, or the first one created until then.
+//
+// Actions and default keys, as seen from JS:
+//   "multiplier_up"    ArrowUp, ArrowRight   "multiplier_down"  ArrowDown, ArrowLeft
+//   "points_up"        PageUp                "points_down"      PageDown
+//   "rotate_left"      q                     "rotate_right"     e   (the figure)
+//   "pan_up"           w                     "pan_down"         s
+//   "pan_left"         a                     "pan_right"        d
+//   "zoom_in"          +, =                  "zoom_out"         -, _
+//   "reset"            0                     "fit"              f
+//   "toggle_outline"   o                     "toggle_rects"     r
+
+use std::cell::Cell;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlCanvasElement, HtmlElement};
+
+use crate::canvas_state::CanvasState;
+use crate::error::ModmulError;
+use crate::fit::FIT_DURATION_MS;
+use crate::history::Change;
+use crate::input::InputEvent;
+use crate::scene::{Point, Scene};
+
+const PAN_STEP: f32 = 0.1; // view units
+const ROTATE_STEP: f32 = 5.0; // degrees
+const ZOOM_STEP: f64 = 1.2;
+const SHIFT_FACTOR: u32 = 10; // how much larger steps are with Shift held
+
+thread_local! {
+    // Id of the canvas taking the keys
+    static KEY_TARGET: Cell<Option<u32>> = const { Cell::new(None) };
+}
+
+// Struct declarations
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyAction {
+    MultiplierUp,
+    MultiplierDown,
+    PointsUp,
+    PointsDown,
+    RotateLeft,
+    RotateRight,
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    ZoomIn,
+    ZoomOut,
+    Reset,
+    Fit,
+    ToggleOutline,
+    ToggleRects
+}
+
+// Which key does what, at most one action per key
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keymap {
+    bindings: Vec<(String, KeyAction)>
+}
+
+// Struct implementations
+
+impl KeyAction {
+    pub fn from_name(name: &str) -> Option<KeyAction> {
+        return match name {
+            "multiplier_up" => Some(KeyAction::MultiplierUp),
+            "multiplier_down" => Some(KeyAction::MultiplierDown),
+            "points_up" => Some(KeyAction::PointsUp),
+            "points_down" => Some(KeyAction::PointsDown),
+            "rotate_left" => Some(KeyAction::RotateLeft),
+            "rotate_right" => Some(KeyAction::RotateRight),
+            "pan_up" => Some(KeyAction::PanUp),
+            "pan_down" => Some(KeyAction::PanDown),
+            "pan_left" => Some(KeyAction::PanLeft),
+            "pan_right" => Some(KeyAction::PanRight),
+            "zoom_in" => Some(KeyAction::ZoomIn),
+            "zoom_out" => Some(KeyAction::ZoomOut),
+            "reset" => Some(KeyAction::Reset),
+            "fit" => Some(KeyAction::Fit),
+            "toggle_outline" => Some(KeyAction::ToggleOutline),
+            "toggle_rects" => Some(KeyAction::ToggleRects),
+            _ => None
+        };
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            KeyAction::MultiplierUp => "multiplier_up",
+            KeyAction::MultiplierDown => "multiplier_down",
+            KeyAction::PointsUp => "points_up",
+            KeyAction::PointsDown => "points_down",
+            KeyAction::RotateLeft => "rotate_left",
+            KeyAction::RotateRight => "rotate_right",
+            KeyAction::PanUp => "pan_up",
+            KeyAction::PanDown => "pan_down",
+            KeyAction::PanLeft => "pan_left",
+            KeyAction::PanRight => "pan_right",
+            KeyAction::ZoomIn => "zoom_in",
+            KeyAction::ZoomOut => "zoom_out",
+            KeyAction::Reset => "reset",
+            KeyAction::Fit => "fit",
+            KeyAction::ToggleOutline => "toggle_outline",
+            KeyAction::ToggleRects => "toggle_rects"
+        };
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        let defaults: [(&str, KeyAction); 20] = [
+            ("ArrowUp", KeyAction::MultiplierUp),
+            ("ArrowRight", KeyAction::MultiplierUp),
+            ("ArrowDown", KeyAction::MultiplierDown),
+            ("ArrowLeft", KeyAction::MultiplierDown),
+            ("PageUp", KeyAction::PointsUp),
+            ("PageDown", KeyAction::PointsDown),
+            ("q", KeyAction::RotateLeft),
+            ("e", KeyAction::RotateRight),
+            ("w", KeyAction::PanUp),
+            ("s", KeyAction::PanDown),
+            ("a", KeyAction::PanLeft),
+            ("d", KeyAction::PanRight),
+            ("+", KeyAction::ZoomIn),
+            ("=", KeyAction::ZoomIn),
+            ("-", KeyAction::ZoomOut),
+            ("_", KeyAction::ZoomOut),
+            ("0", KeyAction::Reset),
+            ("f", KeyAction::Fit),
+            ("o", KeyAction::ToggleOutline),
+            ("r", KeyAction::ToggleRects)
+        ];
+        let mut keymap: Keymap = Keymap {
+            bindings: Vec::new()
+        };
+        for (key, action) in defaults {
+            keymap.bind(key, Some(action));
+        }
+        return keymap;
+    }
+}
+
+impl Keymap {
+    pub fn get(&self, key: &str) -> Option<KeyAction> {
+        let key: String = normalize_key(key);
+        return self.bindings.iter().find(|(k, _)| *k == key).map(|(_, action)| *action);
+    }
+
+    // Make `key` do `action`, or nothing with None
+    pub fn bind(&mut self, key: &str, action: Option<KeyAction>) {
+        let key: String = normalize_key(key);
+        self.bindings.retain(|(k, _)| *k != key);
+        if let Some(action) = action {
+            self.bindings.push((key, action));
+        }
+    }
+
+    pub fn bindings(&self) -> &[(String, KeyAction)] {
+        return &self.bindings;
+    }
+}
+
+impl CanvasState {
+    // Make `key` trigger `action`, where "none" unbinds it. Action names
+    // are listed at the top of this file.
+    pub fn bind_key(&mut self, key: &str, action: &str) -> Result<(), ModmulError> {
+        if (key.is_empty()) {
+            return Err(ModmulError::Invalid(String::from("Empty key")));
+        }
+        let action: Option<KeyAction> = match action {
+            "none" => None,
+            name => Some(KeyAction::from_name(name).ok_or(ModmulError::unknown("key action", name))?)
+        };
+        self.keymap.bind(key, action);
+        return Ok(());
+    }
+
+    // Bindings as an array of {key, action}
+    pub fn get_key_bindings(&self) -> js_sys::Array {
+        let list: js_sys::Array = js_sys::Array::new();
+        for (key, action) in self.keymap.bindings() {
+            let obj: js_sys::Object = js_sys::Object::new();
+            let _ = js_sys::Reflect::set(&obj, &JsValue::from_str("key"), &JsValue::from_str(key));
+            let _ = js_sys::Reflect::set(&obj, &JsValue::from_str("action"), &JsValue::from_str(action.name()));
+            list.push(&obj);
+        }
+        return list;
+    }
+
+    pub fn reset_key_bindings(&mut self) {
+        self.keymap = Keymap::default();
+    }
+
+    ///////////////////////
+    // Private functions //
+    ///////////////////////
+
+    pub(crate) fn on_key_down(&mut self, e: &InputEvent) {
+        if (!has_keys(self.id) || self.gallery.is_some()) {
+            return;
+        }
+        if (e.default_prevented() || e.ctrl || e.alt || e.meta) {
+            return;
+        }
+        if (e.target().is_some_and(|element: Element| takes_text(&element))) {
+            return;
+        }
+        if (self.surface.element().and_then(|canvas: &HtmlCanvasElement| canvas.owner_document()).and_then(|document| document.active_element()).is_some_and(|element: Element| takes_text(&element))) {
+            return;
+        }
+        let Some(action) = self.keymap.get(&e.key) else {
+            return;
+        };
+        e.prevent_default();
+
+        let steps: u32 = if (e.shift) { SHIFT_FACTOR } else { 1 };
+        let shape_points: u32 = self.scene.shape.points;
+        let shape_mul: u32 = self.scene.shape.mul;
+        match action {
+            // The scene clamps the point count to limits.rs
+            KeyAction::MultiplierUp => self.mutate(Change::Multiplier, |scene: &mut Scene| scene.set_multiplier(shape_mul.saturating_add(steps))),
+            KeyAction::MultiplierDown => self.mutate(Change::Multiplier, |scene: &mut Scene| scene.set_multiplier(shape_mul.saturating_sub(steps))),
+            KeyAction::PointsUp => self.mutate(Change::Points, |scene: &mut Scene| scene.set_points(shape_points.saturating_add(steps))),
+            KeyAction::PointsDown => self.mutate(Change::Points, |scene: &mut Scene| scene.set_points(shape_points.saturating_sub(steps))),
+            // Points sit at (-cos, sin) of their angle, so a larger angle
+            // turns the figure clockwise
+            KeyAction::RotateLeft => self.rotate_by(-ROTATE_STEP * steps as f32),
+            KeyAction::RotateRight => self.rotate_by(ROTATE_STEP * steps as f32),
+
+            // The view moves, so the picture goes the other way
+            KeyAction::PanUp => self.pan_by(0.0, -PAN_STEP),
+            KeyAction::PanDown => self.pan_by(0.0, PAN_STEP),
+            KeyAction::PanLeft => self.pan_by(PAN_STEP, 0.0),
+            KeyAction::PanRight => self.pan_by(-PAN_STEP, 0.0),
+
+            KeyAction::ZoomIn => self.zoom_eased(e.time_ms, ZOOM_STEP, Point::new(0.0, 0.0)),
+            KeyAction::ZoomOut => self.zoom_eased(e.time_ms, 1.0 / ZOOM_STEP, Point::new(0.0, 0.0)),
+            KeyAction::Reset => self.reset(),
+            KeyAction::Fit => {
+                // A fixed duration, nothing to report
+                let _ = self.fit_view(FIT_DURATION_MS);
+            }
+            KeyAction::ToggleOutline => {
+                let value: bool = !self.scene.enable_outline;
+                self.set_enable_outline(value);
+            }
+            KeyAction::ToggleRects => {
+                let value: bool = !self.scene.shape.use_rects_instead_of_lines;
+                self.set_use_rects(value);
+            }
+        }
+    }
+
+    // Turn the figure, like `set_rotation`, keeping it within -180..180 like
+    // the page's rotation slider
+    fn rotate_by(&mut self, deg: f32) {
+        let target: f32 = (self.scene.rotation_deg() + deg + 180.0).rem_euclid(360.0) - 180.0;
+        self.mutate(Change::Rotation, |scene: &mut Scene| scene.set_rotation(target));
+    }
+
+    fn pan_by(&mut self, dx: f32, dy: f32) {
+        self.mutate(Change::Move, |scene: &mut Scene| scene.move_shape(dx, dy));
+    }
+}
+
+// Helper functions
+
+// Make canvas `id` take the keys. Unless `force`, only if no other does.
+pub(crate) fn claim_keys(id: u32, force: bool) {
+    KEY_TARGET.with(|target: &Cell<Option<u32>>| {
+        if (force || target.get().is_none()) {
+            target.set(Some(id));
+        }
+    });
+}
+
+// Canvas `id` is going away
+pub(crate) fn release_keys(id: u32) {
+    KEY_TARGET.with(|target: &Cell<Option<u32>>| {
+        if (target.get() == Some(id)) {
+            target.set(None);
+        }
+    });
+}
+
+fn has_keys(id: u32) -> bool {
+    return KEY_TARGET.with(|target: &Cell<Option<u32>>| target.get() == Some(id));
+}
+
+// Single characters are matched in lower case
+fn normalize_key(key: &str) -> String {
+    if (key.chars().count() == 1) {
+        return key.to_lowercase();
+    }
+    return String::from(key);
+}
+
+// Whether typing into `element` would be taken as a shortcut otherwise
+fn takes_text(element: &Element) -> bool {
+    let tag: String = element.tag_name().to_ascii_uppercase();
+    if (tag == "INPUT" || tag == "TEXTAREA" || tag == "SELECT") {
+        return true;
+    }
+    // Also true inside an element made editable by one of its parents
+    return element.dyn_ref::<HtmlElement>().is_some_and(|element: &HtmlElement| element.is_content_editable());
+}
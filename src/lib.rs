@@ -15,456 +15,485 @@ Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
 */
 
 #![allow(unused_parens)]
+#![allow(clippy::needless_return)]
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use web_sys::{Window, Document, HtmlCanvasElement, WebGl2RenderingContext, WebGlShader, WebGlProgram};
+use web_sys::{Document, Element, HtmlCanvasElement, OffscreenCanvas, Window};
+
+mod backend;
+mod camera;
+mod camera_controller;
+mod canvas_state;
+mod compare;
+mod context_loss;
+mod error;
+mod events;
+mod fit;
+mod frame;
+mod gallery;
+pub mod geometry;
+mod glsl;
+mod history;
+mod input;
+mod keymap;
+pub mod limits;
+mod minimap;
+mod permalink;
+mod picking;
+mod presets;
+mod renderer;
+mod resize;
+mod scene;
+mod scene_json;
+mod surface;
+mod transition;
+mod webgl_renderer;
+mod webgl1_renderer;
+#[cfg(feature = "cli")]
+mod cpu_renderer;
+#[cfg(feature = "cli")]
+mod svg_renderer;
+#[cfg(feature = "cli")]
+mod pdf_renderer;
+
+pub use backend::{Backend, Capabilities};
+pub use camera::{Camera, WorldPoint, DEEP_ZOOM};
+pub use camera_controller::{CameraController, DEFAULT_FRICTION};
+pub use compare::{CompareMode, Comparison};
+pub use error::ModmulError;
+pub use events::{ChangeEvent, FieldValue, SceneField, scene_changes};
+pub use fit::{FIT_DURATION_MS, FIT_PADDING};
+pub use gallery::{Cell, Gallery};
+pub use history::{Change, History, DEFAULT_HISTORY_LIMIT};
+pub use input::{CameraMove, Gesture, GestureAction, GestureMap, PointerTracker};
+pub use keymap::{KeyAction, Keymap};
+pub use minimap::Minimap;
+pub use picking::{PickIndex, PickKind, PickResult};
+pub use presets::{Preset, PRESETS, find_preset};
+pub use renderer::{Dirty, Renderer, render};
+pub use scene::{Point, Color, Shape, Scene, normalize_u8_to_1};
+pub use scene_json::SCENE_VERSION;
+pub use surface::{Layout, Surface};
+pub use transition::Transition;
+pub use webgl_renderer::WebGlRenderer;
+pub use webgl1_renderer::WebGl1Renderer;
+#[cfg(feature = "cli")]
+pub use cpu_renderer::CpuRenderer;
+#[cfg(feature = "cli")]
+pub use svg_renderer::SvgRenderer;
+#[cfg(feature = "cli")]
+pub use pdf_renderer::PdfRenderer;
+
+use canvas_state::{CanvasState, dispatch_changes};
+use context_loss::ContextWatcher;
+use input::InputListeners;
+use resize::ResizeWatcher;
 
 // Struct declarations
 
-#[wasm_bindgen]
-pub struct Point {
-    x: f32,
-    y: f32
-}
-
-#[wasm_bindgen]
-pub struct Color {
-    r: u8,
-    g: u8,
-    b: u8
-}
-
-#[wasm_bindgen]
-pub struct Shape {
-    points: u32,
-    mul: u32, // multiplier
-    r: f32,
-    color: Color,
-    pos: Point,
-    point_size: f32,
-    dimensions: Point,
-    widescreen: bool,
-    rotation: f32,
-    outline_width: f32,
-    outline_segments: i32,
-    use_rects_instead_of_lines: bool,
-    rect_width: f32
-}
-
+// Handle JS holds on to. The state sits behind an `Rc` so that the event
+// listeners on the canvas element can reach it too, see `CanvasState` for
+// what each method does. In a worker there is no element to listen to, and
+// the page forwards input and layout instead.
 #[wasm_bindgen]
 pub struct Canvas {
-    context: WebGl2RenderingContext,
-    point_program: WebGlProgram,
-    line_program: WebGlProgram,
-    outline_program: WebGlProgram,
-    rect_program: WebGlProgram,
-    shape: Shape,
-    bg: Color,
-    enable_outline: bool
+    state: Rc<RefCell<CanvasState>>,
+    _input: Option<InputListeners>, // removes the listeners again when dropped
+    _resize: Option<ResizeWatcher>, // stops following the element's size when dropped
+    _context: ContextWatcher // stops waiting for context restores when dropped
 }
 
 // Struct implementations
 
 #[wasm_bindgen]
 impl Canvas {
+    // Viewer for the page's "webgl_canvas" element, starting from the view
+    // in the URL fragment if there is one
     #[wasm_bindgen(constructor)]
     pub fn new() -> Result<Canvas, JsValue> {
-        // Get elements
-        let window: Window = web_sys::window().unwrap();
-        let document: Document = window.document().unwrap();
-        let canvas: HtmlCanvasElement = document.get_element_by_id("webgl_canvas").unwrap().dyn_into::<HtmlCanvasElement>()?;
-        let gl: WebGl2RenderingContext = canvas.get_context("webgl2")?.unwrap().dyn_into()?;
-
-        // Compile point shader
-        let point_shader_src: &str = include_str!("point_shader.vert");
-        let point_shader: WebGlShader = compile_shader(&gl, point_shader_src, WebGl2RenderingContext::VERTEX_SHADER).map_err(|e: String| JsValue::from_str(&e))?;
-
-        // Compile line shader
-        let line_shader_src: &str = include_str!("line_shader.vert");
-        let line_shader: WebGlShader = compile_shader(&gl, line_shader_src, WebGl2RenderingContext::VERTEX_SHADER).map_err(|e: String| JsValue::from_str(&e))?;
-
-        // Compile outline shader
-        let outline_shader_src: &str = include_str!("outline_shader.vert");
-        let outline_shader: WebGlShader = compile_shader(&gl, outline_shader_src, WebGl2RenderingContext::VERTEX_SHADER).map_err(|e: String| JsValue::from_str(&e))?;
-
-        // Compile rectangle shader
-        let rect_shader_src: &str = include_str!("rect_shader.vert");
-        let rect_shader: WebGlShader = compile_shader(&gl, rect_shader_src, WebGl2RenderingContext::VERTEX_SHADER).map_err(|e: String| JsValue::from_str(&e))?;
-
-        // Compile color shader
-        let color_shader_src: &str = include_str!("color_shader.frag");
-        let color_shader: WebGlShader = compile_shader(&gl, color_shader_src, WebGl2RenderingContext::FRAGMENT_SHADER).map_err(|e: String| JsValue::from_str(&e))?;
-
-        // Link WebGL programs
-        let point_program: WebGlProgram = link_program(&gl, &point_shader, &color_shader).map_err(|e: String| JsValue::from_str(&e))?;
-        let line_program: WebGlProgram = link_program(&gl, &line_shader, &color_shader).map_err(|e: String| JsValue::from_str(&e))?;
-        let outline_program: WebGlProgram = link_program(&gl, &outline_shader, &color_shader).map_err(|e: String| JsValue::from_str(&e))?;
-        let rect_program: WebGlProgram = link_program(&gl, &rect_shader, &color_shader).map_err(|e: String| JsValue::from_str(&e))?;
-        gl.use_program(Some(&point_program)); // call from drawing function instead
-
-        // Adjust to window size
-        let dpr: f64 = window.device_pixel_ratio();
-        let visual_viewport: js_sys::Object = window.get("visualViewport").unwrap();
-        let w_css: f64 = js_sys::Reflect::get(&visual_viewport, &JsValue::from_str("width")).unwrap().as_f64().unwrap();
-        let h_css: f64 = js_sys::Reflect::get(&visual_viewport, &JsValue::from_str("height")).unwrap().as_f64().unwrap();
-
-        let w: u32 = (w_css * dpr) as u32;
-        let h: u32 = (h_css * dpr) as u32;
-        canvas.set_width(w);
-        canvas.set_height(h);
-        gl.viewport(0, 0, w as i32, h as i32);
-
-        // Create shape
-        let shape: Shape = Shape {
-            points: 500,
-            mul: 72,
-            r: 0.92,
-            color: Color {
-                r: 250,
-                g: 250,
-                b: 250
-            },
-            pos: Point {
-                x: 0.0,
-                y: 0.0
-            },
-            point_size: 2.0,
-            dimensions: Point {
-                x: canvas.width() as f32,
-                y: canvas.height() as f32
-            },
-            widescreen: (canvas.width() >= canvas.height()),
-            rotation: 0.0,
-            outline_width: 0.0035,
-            outline_segments: 1440,
-            use_rects_instead_of_lines: true,
-            rect_width: 0.002
-        };
-
-        // Return self
-        let canvas_obj: Canvas = Canvas {
-            context: gl,
-            point_program: point_program,
-            line_program: line_program,
-            outline_program: outline_program,
-            rect_program: rect_program,
-            shape,
-            bg: Color {
-                r: 24,
-                g: 24,
-                b: 24
-            },
-            enable_outline: false
-        };
+        let canvas: Canvas = Canvas::from_id("webgl_canvas")?;
+        canvas.update(|state: &mut CanvasState| state.load_url_fragment());
+        return Ok(canvas);
+    }
 
-        canvas_obj.set_fg_color();
+    // Viewer drawing into `canvas`, any number of them can share a page.
+    // The surface follows the element's laid out size.
+    pub fn from_element(canvas: HtmlCanvasElement) -> Result<Canvas, JsValue> {
+        let state: Rc<RefCell<CanvasState>> = Rc::new(RefCell::new(CanvasState::new(Surface::Element(canvas))?));
+        let input: InputListeners = InputListeners::attach(&state)?;
+        let resize: ResizeWatcher = ResizeWatcher::attach(&state)?;
+        return Canvas::start(state, Some(input), Some(resize));
+    }
 
-        return Ok(canvas_obj);
+    // Viewer drawing into `canvas` from a worker, whose element on the page
+    // is laid out at `css_width` by `css_height` CSS pixels. See worker.js
+    // for the page side.
+    pub fn from_offscreen(canvas: OffscreenCanvas, css_width: f64, css_height: f64, pixel_ratio: f64) -> Result<Canvas, JsValue> {
+        let layout: Layout = Layout {
+            css_width,
+            css_height,
+            pixel_ratio
+        };
+        let state: Rc<RefCell<CanvasState>> = Rc::new(RefCell::new(CanvasState::new(Surface::Offscreen(canvas, layout))?));
+        return Canvas::start(state, None, None);
     }
 
+    // Viewer for the canvas element with id `id`
+    pub fn from_id(id: &str) -> Result<Canvas, JsValue> {
+        let window: Window = web_sys::window().ok_or(ModmulError::NoWindow)?;
+        let document: Document = window.document().ok_or(ModmulError::NoDocument)?;
+        let element: Element = document.get_element_by_id(id).ok_or(ModmulError::ElementNotFound(String::from(id)))?;
+        let canvas: HtmlCanvasElement = element.dyn_into::<HtmlCanvasElement>()
+            .map_err(|_| ModmulError::NotACanvas(String::from(id)))?;
+        return Canvas::from_element(canvas);
+    }
 
     //////////////////////////////////////////////
     // Public Functions (can be called from JS) //
     //////////////////////////////////////////////
 
-    pub fn draw(&self) {
-        self.clear();
-        if (self.enable_outline) {
-            self.draw_outline();
-        }
-        if (self.shape.use_rects_instead_of_lines) {
-            self.draw_rects();
-        } else {
-            self.draw_lines();
-        }
-        self.draw_points();
+    pub fn draw(&mut self) {
+        self.update(|state: &mut CanvasState| state.draw());
     }
 
-    pub fn set_points(&mut self, value: u32) {
-        self.shape.points = value;
-        if (value >  1440) {
-            self.shape.outline_segments = value as i32;
-        } else {
-            self.shape.outline_segments = 1440;
-        }
-
-        // Draw again
-        self.draw();
+    // Whole numbers, 1 to MAX_POINTS
+    pub fn set_points(&mut self, value: f64) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.set_points(value));
     }
 
-    pub fn set_multiplier(&mut self, value: u32) {
-        self.shape.mul = value;
+    // Any whole number from 0 up that fits a u32
+    pub fn set_multiplier(&mut self, value: f64) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.set_multiplier(value));
+    }
 
-        // Draw again
-        self.draw();
+    pub fn set_rotation(&mut self, deg: f32) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.set_rotation(deg));
     }
 
-    pub fn set_rotation(&mut self, deg: f32) {
-        // first convert degrees to radians
-        let rad: f32 = deg * std::f32::consts::PI / 180.0;
-        self.shape.rotation = rad;
+    pub fn move_shape(&mut self, dx: f32, dy: f32) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.move_shape(dx, dy));
+    }
 
-        // Draw again
-        self.draw();
+    pub fn adjust_view(&mut self) -> Result<bool, ModmulError> {
+        return self.update(|state: &mut CanvasState| state.adjust_view());
     }
 
-    pub fn move_shape(&mut self, dx: f32, dy: f32) {
-        self.shape.pos.x += dx;
-        self.shape.pos.y += dy;
+    pub fn reset(&mut self) {
+        self.update(|state: &mut CanvasState| state.reset());
+    }
 
-        // Draw again
-        self.draw();
+    pub fn fit_view(&mut self, duration_ms: f64) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.fit_view(duration_ms));
     }
 
-    // Meant to be called when window gets resized
-    pub fn adjust_view(&mut self) -> Result<bool, JsValue> {
-        // Get elements
-        let window: Window = web_sys::window().unwrap();
-        let document: Document = window.document().unwrap();
-        let canvas: HtmlCanvasElement = document.get_element_by_id("webgl_canvas").unwrap().dyn_into::<HtmlCanvasElement>()?;
-        let gl: WebGl2RenderingContext = canvas.get_context("webgl2")?.unwrap().dyn_into()?;
+    pub fn fit_orbit(&mut self, start: u32, duration_ms: f64) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.fit_orbit(start, duration_ms));
+    }
 
-        // Adjust to window size
-        let dpr: f64 = window.device_pixel_ratio();
-        let visual_viewport: js_sys::Object = window.get("visualViewport").unwrap();
-        let w_css: f64 = js_sys::Reflect::get(&visual_viewport, &JsValue::from_str("width"))
-            .unwrap()
-            .as_f64()
-            .unwrap();
-        let h_css: f64 = js_sys::Reflect::get(&visual_viewport, &JsValue::from_str("height"))
-            .unwrap()
-            .as_f64()
-            .unwrap();
+    pub fn zoom_to_box(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, duration_ms: f64) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.zoom_to_box(x0, y0, x1, y1, duration_ms));
+    }
 
-        let w: u32 = (w_css * dpr) as u32;
-        let h: u32 = (h_css * dpr) as u32;
-        canvas.set_width(w);
-        canvas.set_height(h);
-        gl.viewport(0, 0, w as i32, h as i32);
+    pub fn add_to_r(&mut self, val: f32, mx: f32, my: f32) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.add_to_r(val, mx, my));
+    }
 
-        // Update object properties according to new demensions
-        self.shape.dimensions.x = canvas.width() as f32;
-        self.shape.dimensions.y = canvas.height() as f32;
-        self.shape.widescreen = (canvas.width() >= canvas.height());
+    pub fn zoom_at(&mut self, factor: f64, mx: f32, my: f32) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.zoom_at(factor, mx, my));
+    }
 
-        // Draw again
-        self.draw();
+    pub fn get_zoom(&self) -> f64 {
+        return self.state.borrow().get_zoom();
+    }
 
-        return Ok(true);
+    pub fn set_view_rotation(&mut self, deg: f64) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.set_view_rotation(deg));
     }
 
-    pub fn reset(&mut self) {
-        self.shape.r = 0.92;
-        self.shape.pos = Point {x: 0.0, y: 0.0};
-        self.shape.rotation = 0.0;
-
-        // Draw again
-        self.draw();
-    }
-
-    // Increase radius and adjust the new location of the center so that
-    // the zoom effect appears as originating from the cursor location
-    // mx = mouse x, my = mouse y
-    pub fn add_to_r(&mut self, val: f32, mx: f32, my: f32) {
-        let prev_r: f32 = self.shape.r; // previous value of r will be needed in calculations
-        self.shape.r += val; // calculate new r
-
-        // We must denormalize shape position because position is saved in normalized coordinates
-        // Note: normalization refers to widescreen or narrowscreen adjustment because
-        // WebGL assumed a value of 2.0 equals both the height and width of the screen
-        let mut denorm_x: f32 = self.shape.pos.x;
-        let mut denorm_y: f32 = self.shape.pos.y;
-        if (self.shape.widescreen) {
-            denorm_x = self.shape.pos.x * self.shape.dimensions.y / self.shape.dimensions.x;
-        } else {
-            denorm_y = self.shape.pos.y * self.shape.dimensions.x / self.shape.dimensions.y;
-        }
-
-        let prev_dx: f32 = (mx - denorm_x).abs();
-        let prev_dy: f32 = (my - denorm_y).abs();
-
-        let dx: f32 = self.shape.r * prev_dx / prev_r - prev_dx;
-        let dy: f32 = self.shape.r * prev_dy / prev_r - prev_dy;
-
-        if (mx > denorm_x) {
-            denorm_x -= dx;
-        } else {
-            denorm_x += dx;
-        }
-
-        if (my > denorm_y) {
-            denorm_y -= dy;
-        } else {
-            denorm_y += dy;
-        }
-
-        if (self.shape.widescreen) {
-            self.shape.pos.x = denorm_x * self.shape.dimensions.x / self.shape.dimensions.y;
-            self.shape.pos.y = denorm_y;
-        } else {
-            self.shape.pos.x = denorm_x;
-            self.shape.pos.y = denorm_y * self.shape.dimensions.y / self.shape.dimensions.x;
-        }
-        
-        // Draw again
-        self.draw();
+    pub fn get_view_rotation(&self) -> f64 {
+        return self.state.borrow().get_view_rotation();
     }
 
     pub fn get_r(&self) -> f32 {
-        return self.shape.r;
+        return self.state.borrow().get_r();
     }
 
-    pub fn set_enable_outline(&mut self, value: bool) {
-        self.enable_outline = value;
+    pub fn get_points(&self) -> u32 {
+        return self.state.borrow().get_points();
+    }
+
+    pub fn get_multiplier(&self) -> u32 {
+        return self.state.borrow().get_multiplier();
+    }
+
+    pub fn get_rotation(&self) -> f32 {
+        return self.state.borrow().get_rotation();
+    }
+
+    pub fn get_rect_width(&self) -> f32 {
+        return self.state.borrow().get_rect_width();
+    }
+
+    pub fn get_enable_outline(&self) -> bool {
+        return self.state.borrow().get_enable_outline();
+    }
+
+    pub fn get_use_rects(&self) -> bool {
+        return self.state.borrow().get_use_rects();
+    }
+
+    pub fn to_json(&self) -> String {
+        return self.state.borrow().to_json();
+    }
+
+    pub fn list_presets(&self) -> js_sys::Array {
+        return self.state.borrow().list_presets();
+    }
 
-        // Draw again
-        self.draw();
+    pub fn apply_preset(&mut self, id: &str, duration_ms: f64) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.apply_preset(id, duration_ms));
+    }
+
+    pub fn tick(&mut self, now_ms: f64) -> bool {
+        return self.update(|state: &mut CanvasState| state.tick(now_ms));
+    }
+
+    pub fn is_animating(&self) -> bool {
+        return self.state.borrow().is_animating();
+    }
+
+    pub fn undo(&mut self) -> bool {
+        return self.update(|state: &mut CanvasState| state.undo());
+    }
+
+    pub fn redo(&mut self) -> bool {
+        return self.update(|state: &mut CanvasState| state.redo());
+    }
+
+    pub fn can_undo(&self) -> bool {
+        return self.state.borrow().can_undo();
+    }
+
+    pub fn can_redo(&self) -> bool {
+        return self.state.borrow().can_redo();
+    }
+
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.update(|state: &mut CanvasState| state.set_history_limit(limit));
+    }
+
+    pub fn get_history_limit(&self) -> usize {
+        return self.state.borrow().get_history_limit();
+    }
+
+    pub fn checkpoint(&mut self) {
+        self.update(|state: &mut CanvasState| state.checkpoint());
+    }
+
+    pub fn clear_history(&mut self) {
+        self.update(|state: &mut CanvasState| state.clear_history());
+    }
+
+    pub fn pick(&mut self, x: f32, y: f32) -> Option<PickResult> {
+        return self.update(|state: &mut CanvasState| state.pick(x, y));
+    }
+
+    pub fn set_pick_tolerance(&mut self, pixels: f32) {
+        self.update(|state: &mut CanvasState| state.set_pick_tolerance(pixels));
+    }
+
+    pub fn get_pick_tolerance(&self) -> f32 {
+        return self.state.borrow().get_pick_tolerance();
+    }
+
+    pub fn add_change_listener(&mut self, callback: js_sys::Function) -> u32 {
+        return self.update(|state: &mut CanvasState| state.add_change_listener(callback));
+    }
+
+    pub fn remove_change_listener(&mut self, id: u32) -> bool {
+        return self.update(|state: &mut CanvasState| state.remove_change_listener(id));
+    }
+
+    pub fn to_fragment(&self) -> String {
+        return self.state.borrow().to_fragment();
+    }
+
+    pub fn load_fragment(&mut self, fragment: &str) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.load_fragment(fragment));
+    }
+
+    pub fn load_json(&mut self, json: &str) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.load_json(json));
+    }
+
+    pub fn set_enable_outline(&mut self, value: bool) {
+        self.update(|state: &mut CanvasState| state.set_enable_outline(value));
     }
 
     pub fn set_use_rects(&mut self, value: bool) {
-        self.shape.use_rects_instead_of_lines = value;
+        self.update(|state: &mut CanvasState| state.set_use_rects(value));
+    }
 
-        // Draw again
-        self.draw();
+    // MIN_LINE_WIDTH to MAX_LINE_WIDTH view units
+    pub fn set_rect_width(&mut self, value: f32) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.set_rect_width(value));
     }
 
-    pub fn set_rect_width(&mut self, value: f32) {
-        self.shape.rect_width = value;
-        self.shape.outline_width = value * 1.5;
+    pub fn set_gesture(&mut self, gesture: &str, action: &str) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.set_gesture(gesture, action));
+    }
 
-        // Draw again
-        self.draw();
-    }
+    pub fn get_gesture(&self, gesture: &str) -> Result<String, ModmulError> {
+        return self.state.borrow().get_gesture(gesture);
+    }
 
-    ///////////////////////
-    // Private functions //
-    ///////////////////////
+    pub fn bind_key(&mut self, key: &str, action: &str) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.bind_key(key, action));
+    }
 
-    fn clear(&self) {
-        let r: f32 = normalize_u8_to_1(self.bg.r);
-        let g: f32 = normalize_u8_to_1(self.bg.g);
-        let b: f32 = normalize_u8_to_1(self.bg.b);
-        self.context.clear_color(r, g, b, 1.0);
-        self.context.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
+    pub fn get_key_bindings(&self) -> js_sys::Array {
+        return self.state.borrow().get_key_bindings();
     }
 
-    fn set_fg_color(&self) {
-        let red: f32 = normalize_u8_to_1(self.shape.color.r);
-        let green: f32 = normalize_u8_to_1(self.shape.color.g);
-        let blue: f32 = normalize_u8_to_1(self.shape.color.b);
+    pub fn reset_key_bindings(&mut self) {
+        self.update(|state: &mut CanvasState| state.reset_key_bindings());
+    }
 
-        self.context.use_program(Some(&self.point_program));
-        self.context.uniform3f(Some(&self.context.get_uniform_location(&self.point_program, "u_color").expect("Error")), red, green, blue);
+    pub fn show_gallery(&mut self, first: u32, last: u32, columns: u32) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.show_gallery(first, last, columns));
+    }
 
-        self.context.use_program(Some(&self.line_program));
-        self.context.uniform3f(Some(&self.context.get_uniform_location(&self.line_program, "u_color").expect("Error")), red, green, blue);
+    pub fn close_gallery(&mut self) {
+        self.update(|state: &mut CanvasState| state.close_gallery());
+    }
 
-        self.context.use_program(Some(&self.outline_program));
-        self.context.uniform3f(Some(&self.context.get_uniform_location(&self.outline_program, "u_color").expect("Error")), red, green, blue);
+    pub fn is_gallery_shown(&self) -> bool {
+        return self.state.borrow().is_gallery_shown();
+    }
 
-        self.context.use_program(Some(&self.rect_program));
-        self.context.uniform3f(Some(&self.context.get_uniform_location(&self.rect_program, "u_color").expect("Error")), red, green, blue);
+    pub fn gallery_cell_at(&self, x: f32, y: f32) -> Option<u32> {
+        return self.state.borrow().gallery_cell_at(x, y);
     }
 
-    fn draw_outline(&self) {
-        self.context.use_program(Some(&self.outline_program));
+    pub fn open_gallery_cell(&mut self, multiplier: u32) {
+        self.update(|state: &mut CanvasState| state.open_gallery_cell(multiplier));
+    }
 
-        let fg_red: f32 = normalize_u8_to_1(self.shape.color.r);
-        let fg_green: f32 = normalize_u8_to_1(self.shape.color.g);
-        let fg_blue: f32 = normalize_u8_to_1(self.shape.color.b);
-        self.context.uniform3f(Some(&self.context.get_uniform_location(&self.outline_program, "u_color").expect("Error")), fg_red, fg_green, fg_blue);
+    pub fn show_comparison(&mut self, points: u32, multiplier: u32, mode: &str) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.show_comparison(points, multiplier, mode));
+    }
 
-        self.context.uniform1f(Some(&self.context.get_uniform_location(&self.outline_program, "u_segments").expect("Error")), self.shape.outline_segments as f32);
-        self.context.uniform2f(Some(&self.context.get_uniform_location(&self.outline_program, "u_center").expect("Error")), self.shape.pos.x, self.shape.pos.y);
+    pub fn close_comparison(&mut self) {
+        self.update(|state: &mut CanvasState| state.close_comparison());
+    }
 
-        let mut x_norm: f32 = 1.0;
-        let mut y_norm: f32 = 1.0;
-        if (self.shape.widescreen) {
-            x_norm = self.shape.dimensions.y / self.shape.dimensions.x;
-        } else {
-            y_norm = self.shape.dimensions.x / self.shape.dimensions.y;
-        }
-        self.context.uniform1f(Some(&self.context.get_uniform_location(&self.outline_program, "u_x_norm").expect("Error")), x_norm);
-        self.context.uniform1f(Some(&self.context.get_uniform_location(&self.outline_program, "u_y_norm").expect("Error")), y_norm);
+    pub fn get_comparison_mode(&self) -> Option<String> {
+        return self.state.borrow().get_comparison_mode();
+    }
 
+    pub fn set_comparison_colors(&mut self, only_a: &str, only_b: &str) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.set_comparison_colors(only_a, only_b));
+    }
 
-        self.context.uniform1f(Some(&self.context.get_uniform_location(&self.outline_program, "u_radius").expect("Error")), self.shape.r + self.shape.outline_width / 2.0);
-        self.context.draw_arrays(WebGl2RenderingContext::TRIANGLE_FAN, 0, self.shape.outline_segments + 2);
-        
-        let bg_red: f32 = normalize_u8_to_1(24);
-        let bg_green: f32 = normalize_u8_to_1(24);
-        let bg_blue: f32 = normalize_u8_to_1(24);
-        self.context.uniform3f(Some(&self.context.get_uniform_location(&self.outline_program, "u_color").expect("Error")), bg_red, bg_green, bg_blue);
+    pub fn get_comparison_counts(&mut self) -> Option<js_sys::Object> {
+        return self.update(|state: &mut CanvasState| state.get_comparison_counts());
+    }
 
-        self.context.uniform1f(Some(&self.context.get_uniform_location(&self.outline_program, "u_radius").expect("Error")), self.shape.r - self.shape.outline_width / 2.0);
-        self.context.draw_arrays(WebGl2RenderingContext::TRIANGLE_FAN, 0, self.shape.outline_segments + 2);
+    pub fn set_minimap(&mut self, enabled: bool) {
+        self.update(|state: &mut CanvasState| state.set_minimap(enabled));
     }
 
-    fn draw_lines(&self) {
-        self.context.use_program(Some(&self.line_program));
-        self.context.uniform1i(Some(&self.context.get_uniform_location(&self.line_program, "u_points").expect("Error")), self.shape.points as i32);
-        self.context.uniform1f(Some(&self.context.get_uniform_location(&self.line_program, "u_radius").expect("Error")), self.shape.r);
-        self.context.uniform1f(Some(&self.context.get_uniform_location(&self.line_program, "u_rotation").expect("Error")), self.shape.rotation);
-        self.context.uniform2f(Some(&self.context.get_uniform_location(&self.line_program, "u_position").expect("Error")), self.shape.pos.x, self.shape.pos.y);
-        self.context.uniform2f(Some(&self.context.get_uniform_location(&self.line_program, "u_dimensions").expect("Error")), self.shape.dimensions.x, self.shape.dimensions.y);
-        self.context.uniform1i(Some(&self.context.get_uniform_location(&self.line_program, "u_widescreen").expect("Error")), if self.shape.widescreen { 1 } else { 0 });
-        self.context.uniform1i(Some(&self.context.get_uniform_location(&self.line_program, "u_multiplier").expect("Error")), self.shape.mul as i32);
-        self.context.draw_arrays(WebGl2RenderingContext::LINES, 0, (self.shape.points * 2) as i32);
+    pub fn get_minimap(&self) -> bool {
+        return self.state.borrow().get_minimap();
     }
 
-    fn draw_points(&self) {
-        self.context.use_program(Some(&self.point_program));
-        self.context.uniform1f(Some(&self.context.get_uniform_location(&self.point_program, "u_points").expect("Error")), self.shape.points as f32);
-        self.context.uniform1f(Some(&self.context.get_uniform_location(&self.point_program, "u_radius").expect("Error")), self.shape.r);
-        self.context.uniform1f(Some(&self.context.get_uniform_location(&self.point_program, "u_rotation").expect("Error")), self.shape.rotation);
-        self.context.uniform2f(Some(&self.context.get_uniform_location(&self.point_program, "u_position").expect("Error")), self.shape.pos.x, self.shape.pos.y);
-        self.context.uniform2f(Some(&self.context.get_uniform_location(&self.point_program, "u_dimensions").expect("Error")), self.shape.dimensions.x, self.shape.dimensions.y);
-        self.context.uniform1i(Some(&self.context.get_uniform_location(&self.point_program, "u_widescreen").expect("Error")), if self.shape.widescreen {1} else {0});
-        self.context.uniform1f(Some(&self.context.get_uniform_location(&self.point_program, "u_point_size").expect("Error")), self.shape.point_size);
-        self.context.draw_arrays(WebGl2RenderingContext::POINTS, 0, self.shape.points as i32);
+    pub fn is_over_minimap(&self, x: f32, y: f32) -> bool {
+        return self.state.borrow().is_over_minimap(x, y);
     }
 
-    fn draw_rects(&self) {
-        self.context.use_program(Some(&self.rect_program));
-        self.context.uniform1i(Some(&self.context.get_uniform_location(&self.rect_program, "u_points").expect("Error")), self.shape.points as i32);
-        self.context.uniform1f(Some(&self.context.get_uniform_location(&self.rect_program, "u_radius").expect("Error")), self.shape.r);
-        self.context.uniform1f(Some(&self.context.get_uniform_location(&self.rect_program, "u_rotation").expect("Error")), self.shape.rotation);
-        self.context.uniform2f(Some(&self.context.get_uniform_location(&self.rect_program, "u_position").expect("Error")), self.shape.pos.x, self.shape.pos.y);
-        self.context.uniform2f(Some(&self.context.get_uniform_location(&self.rect_program, "u_dimensions").expect("Error")), self.shape.dimensions.x, self.shape.dimensions.y);
-        self.context.uniform1i(Some(&self.context.get_uniform_location(&self.rect_program, "u_widescreen").expect("Error")), if self.shape.widescreen {1} else {0});
-        self.context.uniform1i(Some(&self.context.get_uniform_location(&self.rect_program, "u_multiplier").expect("Error")), self.shape.mul as i32);
-        self.context.uniform1f(Some(&self.context.get_uniform_location(&self.rect_program, "u_rectw").expect("Error")), self.shape.rect_width);
-        self.context.draw_arrays(WebGl2RenderingContext::TRIANGLES, 0, (self.shape.points * 6) as i32);
+    pub fn set_friction(&mut self, friction: f64) {
+        self.update(|state: &mut CanvasState| state.set_friction(friction));
     }
-}
 
-// Helper functions
+    pub fn get_friction(&self) -> f64 {
+        return self.state.borrow().get_friction();
+    }
 
-fn compile_shader(gl: &WebGl2RenderingContext, source: &str, shader_type: u32) -> Result<WebGlShader, String> {
-    let shader: WebGlShader = gl.create_shader(shader_type).ok_or("Unable to create shader")?;
-    gl.shader_source(&shader, source);
-    gl.compile_shader(&shader);
+    pub fn set_reduced_motion(&mut self, value: Option<bool>) {
+        self.update(|state: &mut CanvasState| state.set_reduced_motion(value));
+    }
 
-    if (gl.get_shader_parameter(&shader, WebGl2RenderingContext::COMPILE_STATUS).as_bool().unwrap_or(false)) {
-        return Ok(shader);
-    } else {
-        return Err(gl.get_shader_info_log(&shader).unwrap_or_default());
+    pub fn set_layout(&mut self, css_width: f64, css_height: f64, pixel_ratio: f64) {
+        self.update(|state: &mut CanvasState| state.set_layout(css_width, css_height, pixel_ratio));
     }
-}
 
-fn link_program(gl: &WebGl2RenderingContext, vertex_shader: &WebGlShader, fragment_shader: &WebGlShader) -> Result<WebGlProgram, String> {
-    let program: WebGlProgram = gl.create_program().ok_or("Failed to create program")?;
-    gl.attach_shader(&program, vertex_shader);
-    gl.attach_shader(&program, fragment_shader);
-    gl.link_program(&program);
+    pub fn forward_input(&mut self, event: JsValue) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.forward_input(&event));
+    }
+
+    pub fn is_context_lost(&self) -> bool {
+        return self.state.borrow().is_context_lost();
+    }
 
-    if (gl.get_program_parameter(&program, WebGl2RenderingContext::LINK_STATUS).as_bool().unwrap_or(false)) {
-        Ok(program)
-    } else {
-        Err(gl.get_program_info_log(&program).unwrap_or_default())
+    pub fn debug_lose_context(&mut self) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.debug_lose_context());
+    }
+
+    pub fn debug_restore_context(&mut self) -> Result<(), ModmulError> {
+        return self.update(|state: &mut CanvasState| state.debug_restore_context());
+    }
+
+    // {backend, version, shading_language, fallback_reason, labels}, see backend.rs
+    pub fn get_capabilities(&self) -> JsValue {
+        return self.state.borrow().get_capabilities();
+    }
+
+    // Make canvases created from now on use "webgl2" or "webgl1" without
+    // detecting which the browser has, or detect it again with undefined
+    pub fn force_backend(name: Option<String>) -> Result<(), ModmulError> {
+        let backend: Option<Backend> = match name {
+            Some(name) => Some(Backend::from_name(&name).ok_or(ModmulError::unknown("backend", &name))?),
+            None => None
+        };
+        backend::force_backend(backend);
+        return Ok(());
     }
 }
 
-pub fn normalize_u8_to_1(arg: u8) -> f32 {
-    return (arg as f32) / 255.0;
-}
\ No newline at end of file
+impl Canvas {
+    // Run `f` on the state, then tell the change listeners what it changed.
+    // They are called once the state is free again, so they can call back
+    // into the canvas.
+    fn update<T>(&self, f: impl FnOnce(&mut CanvasState) -> T) -> T {
+        let out: T = f(&mut self.state.borrow_mut());
+        dispatch_changes(&self.state);
+        return out;
+    }
+
+    fn start(state: Rc<RefCell<CanvasState>>, input: Option<InputListeners>, resize: Option<ResizeWatcher>) -> Result<Canvas, JsValue> {
+        let context: ContextWatcher = ContextWatcher::attach(&state)?;
+
+        // The state owns its frame callback, which only holds on to it weakly
+        let weak: Weak<RefCell<CanvasState>> = Rc::downgrade(&state);
+        state.borrow_mut().frames.set_callback(move |now_ms: f64| {
+            if let Some(state) = weak.upgrade() {
+                if let Ok(mut state) = state.try_borrow_mut() {
+                    state.on_frame(now_ms);
+                }
+                dispatch_changes(&state);
+            }
+        });
+        state.borrow().frames.request();
+
+        // Return self
+        return Ok(Canvas {
+            state,
+            _input: input,
+            _resize: resize,
+            _context: context
+        });
+    }
+}
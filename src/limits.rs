@@ -0,0 +1,156 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Ranges of the values that make up a figure. Setters called from JS, URL
+// fragments, scene JSON and the command line check against these and fail
+// with "out_of_range" outside of them. The `Scene` setters clamp instead,
+// so nothing that skips the checks can break the shaders either.
+//
+//   points         whole number, 1..=MAX_POINTS. 0 divides by zero in the
+//                  shaders, and past 2^24 the point indices stop being exact
+//                  floats there, so neighbouring points would merge.
+//   multiplier     whole number, 0..=u32::MAX. Only k mod n shapes the figure,
+//                  and that's all the shaders get, so every k draws right.
+//   rotation       any finite number of degrees, of the figure or the view
+//   line width     MIN_LINE_WIDTH..=MAX_LINE_WIDTH view units, where the
+//                  radius of the circle is 1 at the reset view
+//   zoom factor    any finite number above 0, and the zoom it leads to too
+//   offsets        finite, the pan distances, cursor positions and the like
+//                  the camera moves take
+//   duration       any finite number of milliseconds, 0 or less changes at
+//                  once. An infinite one would animate forever.
+//   point size     MIN_POINT_SIZE..=MAX_POINT_SIZE pixels. WebGL only
+//                  promises sizes up to 1, most GPUs stop at 64 or more.
+//
+// Settings of the viewer rather than of the figure clamp: the friction to
+// 0.05..=1, the history limit to at least 1 and the pick tolerance to at
+// least 0 pixels.
+//
+// Degenerate figures are fine. A point connected to itself has no chord,
+// so k = 1 draws only the points, as does n = 1. k = 0 and k = n connect
+// every point to point 0, and n = 2 is at most one diameter.
+
+use crate::error::ModmulError;
+
+pub const MIN_POINTS: u32 = 1;
+pub const MAX_POINTS: u32 = 1 << 24;
+pub const MIN_LINE_WIDTH: f32 = 0.00001;
+pub const MAX_LINE_WIDTH: f32 = 1.0;
+pub const MIN_POINT_SIZE: f32 = 0.5;
+pub const MAX_POINT_SIZE: f32 = 64.0;
+
+// Helper functions
+
+pub fn check_points(value: f64) -> Result<u32, ModmulError> {
+    return whole("points", value, MIN_POINTS, MAX_POINTS);
+}
+
+pub fn check_multiplier(value: f64) -> Result<u32, ModmulError> {
+    return whole("multiplier", value, 0, u32::MAX);
+}
+
+pub fn check_degrees(name: &'static str, value: f64) -> Result<f64, ModmulError> {
+    if (!value.is_finite()) {
+        return Err(ModmulError::Invalid(format!("{} must be a finite number of degrees, got {}", name, value)));
+    }
+    return Ok(value);
+}
+
+pub fn check_finite(name: &'static str, value: f64) -> Result<f64, ModmulError> {
+    if (!value.is_finite()) {
+        return Err(ModmulError::Invalid(format!("{} must be a finite number, got {}", name, value)));
+    }
+    return Ok(value);
+}
+
+pub fn check_zoom_factor(value: f64) -> Result<f64, ModmulError> {
+    if (!value.is_finite() || value <= 0.0) {
+        return Err(ModmulError::Invalid(format!("zoom factor must be a finite number above 0, got {}", value)));
+    }
+    return Ok(value);
+}
+
+pub fn check_line_width(value: f32) -> Result<f32, ModmulError> {
+    if (!(MIN_LINE_WIDTH..=MAX_LINE_WIDTH).contains(&value)) {
+        return Err(ModmulError::OutOfRange {
+            name: "line width",
+            value: value as f64,
+            min: MIN_LINE_WIDTH as f64,
+            max: MAX_LINE_WIDTH as f64
+        });
+    }
+    return Ok(value);
+}
+
+pub fn check_duration(value: f64) -> Result<f64, ModmulError> {
+    if (!value.is_finite()) {
+        return Err(ModmulError::Invalid(format!("duration must be a finite number of milliseconds, got {}", value)));
+    }
+    return Ok(value);
+}
+
+pub fn check_point_size(value: f32) -> Result<f32, ModmulError> {
+    if (!(MIN_POINT_SIZE..=MAX_POINT_SIZE).contains(&value)) {
+        return Err(ModmulError::OutOfRange {
+            name: "point size",
+            value: value as f64,
+            min: MIN_POINT_SIZE as f64,
+            max: MAX_POINT_SIZE as f64
+        });
+    }
+    return Ok(value);
+}
+
+fn whole(name: &'static str, value: f64, min: u32, max: u32) -> Result<u32, ModmulError> {
+    if (!(min as f64..=max as f64).contains(&value)) {
+        return Err(ModmulError::OutOfRange {name, value, min: min as f64, max: max as f64});
+    }
+    if (value.fract() != 0.0) {
+        return Err(ModmulError::Invalid(format!("{} must be a whole number, got {}", name, value)));
+    }
+    return Ok(value as u32);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_outside_the_range_or_fractional_are_rejected() {
+        assert_eq!(check_points(1.0), Ok(1));
+        assert_eq!(check_points(MAX_POINTS as f64), Ok(MAX_POINTS));
+        assert_eq!(check_points(0.0).map_err(|e: ModmulError| e.code()), Err("out_of_range"));
+        assert_eq!(check_points(-1.0).map_err(|e: ModmulError| e.code()), Err("out_of_range"));
+        assert_eq!(check_points(f64::NAN).map_err(|e: ModmulError| e.code()), Err("out_of_range"));
+        assert_eq!(check_points(2.5).map_err(|e: ModmulError| e.code()), Err("invalid"));
+        assert_eq!(check_multiplier(0.0), Ok(0));
+        assert_eq!(check_multiplier(u32::MAX as f64), Ok(u32::MAX));
+        assert!(check_line_width(f32::NAN).is_err());
+        assert!(check_line_width(-0.002).is_err());
+        assert!(check_degrees("rotation", f64::INFINITY).is_err());
+        assert!(check_zoom_factor(0.0).is_err());
+        assert!(check_zoom_factor(-2.0).is_err());
+        assert!(check_zoom_factor(f64::NAN).is_err());
+        assert_eq!(check_zoom_factor(0.5), Ok(0.5));
+        assert!(check_point_size(0.0).is_err());
+        assert!(check_point_size(-1.0).is_err());
+        assert!(check_point_size(1e38).is_err());
+        assert_eq!(check_point_size(2.0), Ok(2.0));
+        assert!(check_duration(f64::NAN).is_err());
+        assert!(check_duration(f64::INFINITY).is_err());
+        assert_eq!(check_duration(0.0), Ok(0.0));
+    }
+}
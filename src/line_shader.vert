@@ -17,36 +17,42 @@ You should have received a copy of the GNU General Public License along with Mod
 Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
 */
 
+// WebGL1 draws it instanced with one line per chord, and works in floats
+// like chord.glsl
+#ifdef WEBGL1
+attribute float a_chord; // chord index, per instance
+attribute float a_end; // 0 at the source, 1 at the destination
+uniform float u_points;
+uniform float u_multiplier; // k mod n
+#else
 uniform int u_points;
-uniform float u_radius;
+uniform int u_multiplier; // k mod n
+#endif
 uniform float u_rotation;
-uniform vec2 u_position;
-uniform vec2 u_dimensions;
-uniform bool u_widescreen;
-uniform int u_multiplier;
+uniform mat3 u_view; // world to clip, see camera.rs
+uniform float u_multiplier_frac; // fractional part of the multiplier, only nonzero during transitions
+
+#include "circle.glsl"
+#include "chord.glsl"
 
 void main() {
-    float pi = 3.1415926535897932384626;
-    
+#ifdef WEBGL1
+    float line_i_float = a_chord;
+    if (a_end > 0.5) {
+        line_i_float = chord_end(a_chord, u_multiplier, u_multiplier_frac, u_points);
+    }
+#else
     int i = gl_VertexID;
     bool is_dst = ((i % 2) == 1);
     int line_i = i / 2;
+    float line_i_float = float(line_i);
     if (is_dst) {
-        line_i = (line_i * u_multiplier) %  u_points;
+        line_i_float = chord_end(line_i, u_multiplier, u_multiplier_frac, u_points);
     }
-    float line_i_float = float(line_i);
-    float points_float = float(u_points);
-    
-    float theta =  line_i_float * 2.0 * pi / points_float + u_rotation + (pi / 2.0);
-
-    float x = -1.0 * cos(theta) * u_radius + u_position.x;
-    float y = sin(theta) * u_radius + u_position.y;
+#endif
 
-    if (u_widescreen) {
-        x *= u_dimensions.y / u_dimensions.x;
-    } else {
-        y *= u_dimensions.x / u_dimensions.y;
-    }
+    float theta = point_angle(line_i_float, float(u_points), u_rotation);
+    vec2 world = circle_point(theta);
 
-    gl_Position = vec4(x, y, 0.0, 1.0);
+    gl_Position = vec4((u_view * vec3(world, 1.0)).xy, 0.0, 1.0);
 }
@@ -0,0 +1,215 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Overview inset in the bottom right corner of the surface. While zoomed
+// in it shows the whole circle, turned like the main view, with a frame
+// around the part of it on screen. Pressing or dragging inside the inset
+// centers the main view on that spot.
+//
+// The figure is drawn at low detail: thin lines instead of rectangles, no
+// points and a coarse circle instead of the outline ring.
+
+use crate::camera::{Camera, WorldPoint};
+use crate::canvas_state::CanvasState;
+use crate::history::Change;
+use crate::input::InputEvent;
+use crate::renderer::Renderer;
+use crate::scene::{Color, Point, Scene, Shape};
+
+// Only shown while zoomed in further than this, before there is nothing
+// off screen to find
+const MIN_ZOOM: f64 = 1.5;
+
+// Side of the inset and its distance from the corner, as fractions of the
+// shorter side of the surface
+const SIZE: f32 = 0.25;
+const MARGIN: f32 = 0.02;
+
+const CIRCLE_SEGMENTS: u32 = 64;
+
+// Struct declarations
+
+pub struct Minimap {
+    enabled: bool,
+    dragging: Option<i32> // pointer moving the view from inside the inset
+}
+
+// Struct implementations
+
+impl Minimap {
+    pub fn new() -> Minimap {
+        return Minimap {
+            enabled: false,
+            dragging: None
+        };
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.dragging = None;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        return self.enabled;
+    }
+
+    // Whether the inset is on screen with this camera
+    pub fn shows(&self, camera: &Camera) -> bool {
+        return self.enabled && camera.zoom() > MIN_ZOOM && inset_rect(camera).2 > 0;
+    }
+
+    // Draw the inset over what `renderer` already drew of `scene`
+    pub fn draw(&self, scene: &Scene, renderer: &mut dyn Renderer) {
+        let (x, y, side): (u32, u32, u32) = inset_rect(&scene.camera);
+        let camera: Camera = inset_camera(&scene.camera);
+        renderer.set_viewport(x, y, side, side);
+        renderer.clear(&scene.bg);
+
+        let circle: Vec<Point> = (0..CIRCLE_SEGMENTS).map(|i: u32| {
+            let theta: f64 = i as f64 * std::f64::consts::TAU / CIRCLE_SEGMENTS as f64;
+            return camera.world_to_view(WorldPoint::new(theta.cos(), theta.sin()));
+        }).collect();
+        let dim: Color = scene.bg.mix(&scene.shape.color, 0.4);
+        renderer.draw_polygon(&circle, &camera, &dim);
+
+        let mut shape: Shape = scene.shape.clone();
+        shape.use_rects_instead_of_lines = false;
+        renderer.draw_chords(&shape, &camera);
+
+        // The main surface's corners, as seen in the inset
+        let (ex, ey): (f32, f32) = scene.camera.view_extent();
+        let view: Vec<Point> = [(-ex, -ey), (ex, -ey), (ex, ey), (-ex, ey)].into_iter()
+            .map(|(vx, vy): (f32, f32)| camera.world_to_view(scene.camera.view_to_world(Point::new(vx, vy))))
+            .collect();
+        renderer.draw_polygon(&view, &camera, &scene.shape.color);
+
+        // Border of the inset itself, a pixel in from the edge
+        let inset: f32 = 2.0 / side as f32;
+        let (ix, iy): (f32, f32) = (camera.view_extent().0 - inset, camera.view_extent().1 - inset);
+        let border: [Point; 4] = [Point::new(-ix, -iy), Point::new(ix, -iy), Point::new(ix, iy), Point::new(-ix, iy)];
+        renderer.draw_polygon(&border, &camera, &dim);
+        renderer.reset_viewport();
+    }
+
+    // World point under the surface pixel `px`, if the inset is there
+    pub fn world_at(&self, camera: &Camera, px: Point) -> Option<WorldPoint> {
+        if (!self.shows(camera)) {
+            return None;
+        }
+        let (x, y, side): (u32, u32, u32) = inset_rect(camera);
+        let local: Point = Point::new(px.x - x as f32, px.y - y as f32);
+        if (local.x < 0.0 || local.y < 0.0 || local.x >= side as f32 || local.y >= side as f32) {
+            return None;
+        }
+        return Some(inset_camera(camera).pixels_to_world(local));
+    }
+}
+
+impl Default for Minimap {
+    fn default() -> Minimap {
+        return Minimap::new();
+    }
+}
+
+impl CanvasState {
+    // Show the overview inset while zoomed in
+    pub fn set_minimap(&mut self, enabled: bool) {
+        self.minimap.set_enabled(enabled);
+        self.redraw();
+    }
+
+    pub fn get_minimap(&self) -> bool {
+        return self.minimap.is_enabled();
+    }
+
+    // Whether the client coordinates `x`, `y` are over the inset
+    pub fn is_over_minimap(&self, x: f32, y: f32) -> bool {
+        return !self.is_split() && self.client_to_pixels(x, y)
+            .is_some_and(|(px, _): (Point, f32)| self.minimap.world_at(&self.scene.camera, px).is_some());
+    }
+
+    // A pointer went down, returns true if it was on the inset and is now
+    // moving the view
+    pub(crate) fn minimap_pointer_down(&mut self, e: &InputEvent) -> bool {
+        if (self.minimap.dragging.is_some() || !self.pointers.is_empty() || self.is_split()) {
+            return false;
+        }
+        let Some(target) = self.minimap_target(e) else {
+            return false;
+        };
+        self.minimap.dragging = Some(e.pointer_id);
+        self.motion.stop();
+        self.center_on(target);
+        self.capture_pointer(e.pointer_id);
+        e.prevent_default();
+        return true;
+    }
+
+    // Returns true if the move belonged to a drag on the inset
+    pub(crate) fn minimap_pointer_move(&mut self, e: &InputEvent) -> bool {
+        if (self.minimap.dragging != Some(e.pointer_id)) {
+            return false;
+        }
+        if let Some(target) = self.minimap_target(e) {
+            self.center_on(target);
+        }
+        return true;
+    }
+
+    // Returns true if the drag on the inset ended
+    pub(crate) fn minimap_pointer_up(&mut self, e: &InputEvent) -> bool {
+        if (self.minimap.dragging != Some(e.pointer_id)) {
+            return false;
+        }
+        self.minimap.dragging = None;
+        self.checkpoint();
+        return true;
+    }
+
+    ///////////////////////
+    // Private functions //
+    ///////////////////////
+
+    fn minimap_target(&self, e: &InputEvent) -> Option<WorldPoint> {
+        let (px, _): (Point, f32) = self.client_to_pixels(e.x, e.y)?;
+        return self.minimap.world_at(&self.scene.camera, px);
+    }
+
+    fn center_on(&mut self, target: WorldPoint) {
+        self.mutate(Change::Move, |scene: &mut Scene| scene.camera.center = target);
+    }
+}
+
+// Helper functions
+
+// Left, top and side of the inset in surface pixels
+fn inset_rect(camera: &Camera) -> (u32, u32, u32) {
+    let dimensions: Point = camera.dimensions();
+    let shorter: f32 = dimensions.x.min(dimensions.y);
+    let side: u32 = (shorter * SIZE).round() as u32;
+    let margin: u32 = (shorter * MARGIN).round() as u32;
+    let x: u32 = (dimensions.x as u32).saturating_sub(side + margin);
+    let y: u32 = (dimensions.y as u32).saturating_sub(side + margin);
+    return (x, y, side);
+}
+
+// Whole circle in the inset, turned like `main`
+fn inset_camera(main: &Camera) -> Camera {
+    let side: u32 = inset_rect(main).2;
+    let mut camera: Camera = Camera::new(side, side);
+    camera.rotation = main.rotation();
+    return camera;
+}
@@ -17,24 +17,29 @@ You should have received a copy of the GNU General Public License along with Mod
 Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
 */
 
+#ifdef WEBGL1
+attribute float a_index; // vertex of the fan, 0 is the center, WebGL1 has no gl_VertexID
+#endif
 uniform float u_segments;
-uniform float u_radius;
-uniform float u_x_norm;
-uniform float u_y_norm;
-uniform vec2 u_center;
+uniform float u_radius; // in world units, the circle itself has radius 1.0
+uniform mat3 u_view; // world to clip, see camera.rs
+
+#include "circle.glsl"
 
 void main() {
-    float pi = 3.1415926535897932384626;
+#ifdef WEBGL1
+    float index = a_index;
+#else
+    float index = float(gl_VertexID);
+#endif
 
-    if (gl_VertexID == 0) {
-        // Center of fan
-        gl_Position = vec4(u_center.x * u_x_norm, u_center.y * u_y_norm, 0.0, 1.0);
-    } else {
+    vec2 world = vec2(0.0, 0.0); // center of fan
+    if (index > 0.5) {
         // Perimeter vertex
-        float segment = float(gl_VertexID - 1);
-        float theta = (segment / u_segments) * 2.0 * pi;
-        float x = (u_radius * cos(theta) + u_center.x) * u_x_norm;
-        float y = (u_radius * sin(theta) + u_center.y) * u_y_norm;
-        gl_Position = vec4(x, y, 0.0, 1.0);
+        float segment = index - 1.0;
+        float theta = (segment / u_segments) * 2.0 * PI;
+        world = vec2(u_radius * cos(theta), u_radius * sin(theta));
     }
-}
\ No newline at end of file
+
+    gl_Position = vec4((u_view * vec3(world, 1.0)).xy, 0.0, 1.0);
+}
@@ -0,0 +1,165 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Vector backend that writes a single page PDF, one pixel per point.
+// PDF puts the origin at the bottom left, so y is flipped on the way out.
+
+use std::fmt::Write;
+
+use crate::camera::{Camera, WorldPoint};
+use crate::geometry;
+use crate::renderer::Renderer;
+use crate::scene::{Color, Point, Shape};
+
+// Control point distance for approximating a quarter circle with a cubic Bézier
+const KAPPA: f32 = 0.552_284_8;
+
+// Struct declarations
+
+pub struct PdfRenderer {
+    width: u32,
+    height: u32,
+    content: String // page content stream
+}
+
+// Struct implementations
+
+impl PdfRenderer {
+    pub fn new(width: u32, height: u32) -> PdfRenderer {
+        return PdfRenderer {
+            width,
+            height,
+            content: String::new()
+        };
+    }
+
+    // Complete PDF file of everything drawn so far
+    pub fn finish(&self) -> Vec<u8> {
+        let objects: [String; 5] = [
+            String::from("<< /Type /Catalog /Pages 2 0 R >>"),
+            String::from("<< /Type /Pages /Kids [3 0 R] /Count 1 >>"),
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 5 0 R >> >> /Contents 4 0 R >>",
+                self.width, self.height
+            ),
+            format!("<< /Length {} >>\nstream\n{}endstream", self.content.len(), self.content),
+            String::from("<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>")
+        ];
+
+        let mut out: String = String::from("%PDF-1.4\n");
+        let mut offsets: Vec<usize> = Vec::new();
+        for (i, obj) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            let _ = write!(out, "{} 0 obj\n{}\nendobj\n", i + 1, obj);
+        }
+
+        let xref_offset: usize = out.len();
+        let _ = write!(out, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(out, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            out,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref_offset
+        );
+        return out.into_bytes();
+    }
+
+    ///////////////////////
+    // Private functions //
+    ///////////////////////
+
+    fn flip(&self, p: Point) -> Point {
+        return Point::new(p.x, self.height as f32 - p.y);
+    }
+
+    // Append a closed circle path made of four Bézier arcs
+    fn circle_path(&mut self, c: Point, r: f32) {
+        let k: f32 = r * KAPPA;
+        let _ = writeln!(self.content, "{:.2} {:.2} m", c.x + r, c.y);
+        let _ = writeln!(self.content, "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c", c.x + r, c.y + k, c.x + k, c.y + r, c.x, c.y + r);
+        let _ = writeln!(self.content, "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c", c.x - k, c.y + r, c.x - r, c.y + k, c.x - r, c.y);
+        let _ = writeln!(self.content, "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c", c.x - r, c.y - k, c.x - k, c.y - r, c.x, c.y - r);
+        let _ = writeln!(self.content, "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c h", c.x + k, c.y - r, c.x + r, c.y - k, c.x + r, c.y);
+    }
+}
+
+impl Renderer for PdfRenderer {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+    }
+
+    fn clear(&mut self, color: &Color) {
+        let (r, g, b): (f32, f32, f32) = color.to_f32();
+        self.content.clear();
+        let _ = writeln!(self.content, "{:.3} {:.3} {:.3} rg 0 0 {} {} re f", r, g, b, self.width, self.height);
+    }
+
+    fn draw_outline(&mut self, shape: &Shape, camera: &Camera, bg: &Color) {
+        let center: Point = self.flip(geometry::to_pixels(camera, WorldPoint::new(0.0, 0.0)));
+        let r: f32 = geometry::length_to_pixels(camera, camera.scale() as f32);
+        let w: f32 = geometry::length_to_pixels(camera, shape.outline_width);
+        let (fr, fg, fb): (f32, f32, f32) = shape.color.to_f32();
+        let (br, bgg, bb): (f32, f32, f32) = bg.to_f32();
+        let _ = writeln!(self.content, "{:.3} {:.3} {:.3} RG {:.3} {:.3} {:.3} rg {:.3} w", fr, fg, fb, br, bgg, bb, w);
+        self.circle_path(center, r);
+        self.content.push_str("B\n");
+    }
+
+    fn draw_chords(&mut self, shape: &Shape, camera: &Camera) {
+        let width: f32 = if (shape.use_rects_instead_of_lines) {
+            geometry::length_to_pixels(camera, shape.rect_width)
+        } else {
+            1.0
+        };
+        let (r, g, b): (f32, f32, f32) = shape.color.to_f32();
+        // Butt caps to match the rectangles the shaders draw
+        let _ = writeln!(self.content, "{:.3} {:.3} {:.3} RG {:.3} w 0 J", r, g, b, width);
+        for i in 0..shape.points {
+            let (src, dst): (WorldPoint, WorldPoint) = geometry::chord(shape, i);
+            let a: Point = self.flip(geometry::to_pixels(camera, src));
+            let c: Point = self.flip(geometry::to_pixels(camera, dst));
+            let _ = writeln!(self.content, "{:.2} {:.2} m {:.2} {:.2} l", a.x, a.y, c.x, c.y);
+        }
+        self.content.push_str("S\n");
+    }
+
+    fn draw_points(&mut self, shape: &Shape, camera: &Camera) {
+        let s: f32 = shape.point_size;
+        let (r, g, b): (f32, f32, f32) = shape.color.to_f32();
+        let _ = writeln!(self.content, "{:.3} {:.3} {:.3} rg", r, g, b);
+        for i in 0..shape.points {
+            let p: Point = self.flip(geometry::to_pixels(camera, geometry::point_position(shape, i)));
+            let _ = writeln!(self.content, "{:.2} {:.2} {} {} re", p.x - s / 2.0, p.y - s / 2.0, s, s);
+        }
+        self.content.push_str("f\n");
+    }
+
+    fn draw_text(&mut self, text: &str, x: f32, y: f32, size: f32, color: &Color) {
+        // The built-in Helvetica only covers Latin-1, which is all the labels need
+        let escaped: String = text.replace('\\', "\\\\").replace('(', "\\(").replace(')', "\\)");
+        let (r, g, b): (f32, f32, f32) = color.to_f32();
+        let baseline: f32 = self.height as f32 - (y + size * 0.8);
+        let _ = writeln!(
+            self.content,
+            "BT {:.3} {:.3} {:.3} rg /F1 {} Tf {:.2} {:.2} Td ({}) Tj ET",
+            r, g, b, size, x, baseline, escaped
+        );
+    }
+}
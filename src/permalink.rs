@@ -0,0 +1,291 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Compact scene encoding for the URL fragment, e.g.
+// "n=500&k=72&rot=15&w=20&o=1&z=1.3"
+//
+// Keys:
+//   n    points              k    multiplier
+//   rot  rotation, degrees   w    line width, in the panel's units (1/10000)
+//   o    outline, 0 or 1     m    "r" for rectangles, "l" for lines
+//   z    zoom, 1 is the reset view
+//   vr   view rotation, degrees
+//   x, y where the center of the circle is drawn, in view units
+//   fg, bg colors as rrggbb
+//
+// n, k, w, rot and vr must be in the ranges of limits.rs, and fail with
+// the same errors as the setters outside of them.
+//
+// Only n and k are always written, the rest only when they differ from a
+// fresh scene. Unknown keys are ignored so newer links still open.
+
+use std::fmt::Write;
+
+use crate::camera::WorldPoint;
+use crate::error::ModmulError;
+use crate::limits;
+use crate::scene::{Color, Scene};
+
+// Struct declarations
+
+// Decoded fragment, only applied once every key has been validated
+#[derive(Default)]
+struct Fragment {
+    points: Option<u32>,
+    mul: Option<u32>,
+    rotation: Option<f32>,
+    line_width: Option<f32>,
+    outline: Option<bool>,
+    use_rects: Option<bool>,
+    zoom: Option<f64>,
+    view_rotation: Option<f64>,
+    x: Option<f64>,
+    y: Option<f64>,
+    fg: Option<Color>,
+    bg: Option<Color>
+}
+
+// Struct implementations
+
+impl Scene {
+    // Encode the scene as a URL fragment, without the leading '#'
+    pub fn to_fragment(&self) -> String {
+        let defaults: Scene = Scene::new(0, 0);
+        let mut out: String = format!("n={}&k={}", self.shape.points, self.shape.mul);
+
+        let rotation: f32 = self.rotation_deg();
+        if (rotation != defaults.rotation_deg()) {
+            let _ = write!(out, "&rot={}", fmt_num(rotation as f64));
+        }
+        if (self.shape.rect_width != defaults.shape.rect_width) {
+            let _ = write!(out, "&w={}", fmt_num(self.shape.rect_width as f64 * 10000.0));
+        }
+        if (self.enable_outline != defaults.enable_outline) {
+            let _ = write!(out, "&o={}", if self.enable_outline { 1 } else { 0 });
+        }
+        if (self.shape.use_rects_instead_of_lines != defaults.shape.use_rects_instead_of_lines) {
+            let _ = write!(out, "&m={}", if self.shape.use_rects_instead_of_lines { "r" } else { "l" });
+        }
+        if (self.camera.zoom != defaults.camera.zoom) {
+            let _ = write!(out, "&z={}", fmt_exact(self.camera.zoom));
+        }
+        if (self.camera.rotation != defaults.camera.rotation) {
+            let _ = write!(out, "&vr={}", fmt_num(self.view_rotation_deg()));
+        }
+        if (self.camera.center != defaults.camera.center) {
+            // f64, at a deep zoom the offset is large and needs every digit
+            let (x, y): (f64, f64) = self.camera.world_to_view_f64(WorldPoint::new(0.0, 0.0));
+            let _ = write!(out, "&x={}&y={}", fmt_exact(x), fmt_exact(y));
+        }
+        if (self.shape.color != defaults.shape.color) {
+            let _ = write!(out, "&fg={}", &self.shape.color.to_hex()[1..]);
+        }
+        if (self.bg != defaults.bg) {
+            let _ = write!(out, "&bg={}", &self.bg.to_hex()[1..]);
+        }
+        return out;
+    }
+
+    // Apply a fragment written by `to_fragment`. A leading '#' is allowed.
+    // Keys that are missing keep their current value. Nothing changes if
+    // any value is invalid.
+    pub fn load_fragment(&mut self, fragment: &str) -> Result<(), ModmulError> {
+        let frag: Fragment = parse_fragment(fragment)?;
+
+        if let Some(points) = frag.points {
+            self.set_points(points);
+        }
+        if let Some(mul) = frag.mul {
+            self.set_multiplier(mul);
+        }
+        if let Some(rotation) = frag.rotation {
+            self.set_rotation(rotation);
+        }
+        if let Some(line_width) = frag.line_width {
+            self.set_rect_width(line_width / 10000.0);
+        }
+        if let Some(outline) = frag.outline {
+            self.set_enable_outline(outline);
+        }
+        if let Some(use_rects) = frag.use_rects {
+            self.set_use_rects(use_rects);
+        }
+        if (frag.zoom.is_some() || frag.view_rotation.is_some() || frag.x.is_some() || frag.y.is_some()) {
+            // Keys left out keep the circle where it is drawn now
+            let (x, y): (f64, f64) = self.camera.world_to_view_f64(WorldPoint::new(0.0, 0.0));
+            if let Some(zoom) = frag.zoom {
+                self.camera.zoom = zoom;
+            }
+            if let Some(view_rotation) = frag.view_rotation {
+                self.camera.rotation = view_rotation * std::f64::consts::PI / 180.0;
+            }
+
+            // Undo the rotation and zoom of the offset to find the center
+            let (x, y): (f64, f64) = (frag.x.unwrap_or(x), frag.y.unwrap_or(y));
+            let s: f64 = self.camera.scale();
+            let (sin, cos): (f64, f64) = self.camera.rotation.sin_cos();
+            self.camera.center = WorldPoint::new(-(cos * x + sin * y) / s, -(-sin * x + cos * y) / s);
+        }
+        if let Some(fg) = frag.fg {
+            self.set_fg_color(fg);
+        }
+        if let Some(bg) = frag.bg {
+            self.set_bg_color(bg);
+        }
+        return Ok(());
+    }
+}
+
+// Helper functions
+
+fn parse_fragment(fragment: &str) -> Result<Fragment, ModmulError> {
+    let mut frag: Fragment = Fragment::default();
+    let body: &str = fragment.strip_prefix('#').unwrap_or(fragment);
+
+    for pair in body.split('&').filter(|p| !p.is_empty()) {
+        let (key, value): (&str, &str) = pair.split_once('=').ok_or(ModmulError::Invalid(format!("Missing value for {}", pair)))?;
+        match key {
+            "n" => {
+                frag.points = Some(limits::check_points(parse_value(key, value)?)?);
+            }
+            "k" => frag.mul = Some(limits::check_multiplier(parse_value(key, value)?)?),
+            "rot" => {
+                let rotation: f32 = parse_finite(key, value)? as f32;
+                limits::check_degrees("rotation", rotation as f64)?;
+                frag.rotation = Some(rotation);
+            }
+            "w" => {
+                let w: f64 = parse_finite(key, value)?;
+                limits::check_line_width(w as f32 / 10000.0)?;
+                frag.line_width = Some(w as f32);
+            }
+            "o" => frag.outline = Some(parse_flag(key, value)?),
+            "m" => frag.use_rects = match value {
+                "r" => Some(true),
+                "l" => Some(false),
+                _ => return Err(ModmulError::Invalid(format!("Invalid value {} for m", value)))
+            },
+            "z" => {
+                let z: f64 = parse_finite(key, value)?;
+                if (z <= 0.0) {
+                    return Err(ModmulError::Invalid(String::from("z must be positive")));
+                }
+                frag.zoom = Some(z);
+            }
+            "vr" => frag.view_rotation = Some(limits::check_degrees("view rotation", parse_value(key, value)?)?),
+            "x" => frag.x = Some(parse_finite(key, value)?),
+            "y" => frag.y = Some(parse_finite(key, value)?),
+            "fg" => frag.fg = Some(parse_color(key, value)?),
+            "bg" => frag.bg = Some(parse_color(key, value)?),
+            _ => {}
+        }
+    }
+    return Ok(frag);
+}
+
+fn parse_value<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, ModmulError> {
+    return value.parse::<T>().map_err(|_| ModmulError::Invalid(format!("Invalid value {} for {}", value, key)));
+}
+
+fn parse_finite(key: &str, value: &str) -> Result<f64, ModmulError> {
+    let v: f64 = parse_value(key, value)?;
+    if (!v.is_finite()) {
+        return Err(ModmulError::Invalid(format!("Invalid value {} for {}", value, key)));
+    }
+    return Ok(v);
+}
+
+fn parse_flag(key: &str, value: &str) -> Result<bool, ModmulError> {
+    return match value {
+        "1" => Ok(true),
+        "0" => Ok(false),
+        _ => Err(ModmulError::Invalid(format!("Invalid value {} for {}", value, key)))
+    };
+}
+
+fn parse_color(key: &str, value: &str) -> Result<Color, ModmulError> {
+    // Tolerate a percent encoded '#'
+    let hex: &str = value.strip_prefix("%23").unwrap_or(value);
+    return Color::from_hex(hex).ok_or(ModmulError::Invalid(format!("Invalid value {} for {}", value, key)));
+}
+
+// Shortest decimal form that parses back to exactly `v`, for the view,
+// which at deep or far zooms needs every digit
+fn fmt_exact(v: f64) -> String {
+    if (v == 0.0) {
+        return String::from("0");
+    }
+    return format!("{}", v);
+}
+
+// Shortest reasonable decimal form, "1.3" rather than "1.3000001", for
+// values that only need to look right
+fn fmt_num(v: f64) -> String {
+    let s: String = format!("{:.4}", v);
+    let trimmed: &str = s.trim_end_matches('0').trim_end_matches('.');
+    if (trimmed == "-0") {
+        return String::from("0");
+    }
+    return String::from(trimmed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(scene: &Scene) -> Scene {
+        let mut loaded: Scene = Scene::new(800, 600);
+        loaded.load_fragment(&scene.to_fragment()).unwrap();
+        return loaded;
+    }
+
+    #[test]
+    fn view_survives_the_fragment_at_small_and_large_zooms() {
+        for zoom in [1.2345e-7, 0.00012345, 0.75, 3.0e9] {
+            let mut scene: Scene = Scene::new(800, 600);
+            scene.camera.zoom = zoom;
+            scene.camera.center = WorldPoint::new(0.123456789, -0.987654321);
+            let loaded: Scene = round_trip(&scene);
+            assert_eq!(loaded.camera.zoom, zoom);
+            assert!((loaded.camera.center.x - scene.camera.center.x).abs() < 1e-12, "x at zoom {}", zoom);
+            assert!((loaded.camera.center.y - scene.camera.center.y).abs() < 1e-12, "y at zoom {}", zoom);
+        }
+    }
+
+    #[test]
+    fn shape_survives_the_fragment() {
+        let mut scene: Scene = Scene::new(800, 600);
+        scene.set_points(1234);
+        scene.set_multiplier(77);
+        scene.set_rotation(12.5);
+        scene.set_use_rects(true);
+        scene.set_fg_color(Color::new(1, 2, 3));
+        let loaded: Scene = round_trip(&scene);
+        assert_eq!(loaded.to_fragment(), scene.to_fragment());
+        assert_eq!(loaded.shape.points, 1234);
+        assert_eq!(loaded.shape.mul, 77);
+    }
+
+    #[test]
+    fn invalid_fragments_change_nothing() {
+        let mut scene: Scene = Scene::new(800, 600);
+        let before: String = scene.to_fragment();
+        for fragment in ["n=0", "k=-1", "z=0", "rot=1e300", "x=nan", "o=2", "fg=zzzzzz", "n=5&k"] {
+            assert!(scene.load_fragment(fragment).is_err(), "{} was accepted", fragment);
+            assert_eq!(scene.to_fragment(), before);
+        }
+    }
+}
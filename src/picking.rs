@@ -0,0 +1,335 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Finding the point or chord under the cursor.
+//
+// Points sit evenly on the unit circle, so the ones near the cursor follow
+// from its angle. Chords are bucketed by the line they lie on rather than by
+// where they are: a line through two points of the unit circle is every p
+// with p . n(m) = c, n(m) the unit vector at angle m and m in 0..PI. A grid
+// over m and c holds each chord exactly once, and the lines within a
+// distance of the cursor form a band of c around cursor . n(m), a few cells
+// per column. Both work in world space, so the index survives panning and
+// zooming. It depends on the figure alone and is rebuilt when that changes.
+
+use std::f64::consts::PI;
+
+use wasm_bindgen::prelude::*;
+
+use crate::camera::{Camera, WorldPoint};
+use crate::geometry;
+use crate::scene::{Point, Shape};
+
+// Grid cells per side are about sqrt(points) / 2, so a cell holds four
+// chords on average and the grid takes two u32 per chord
+const MIN_GRID: usize = 4;
+
+// Struct declarations
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PickKind {
+    Point = "point",
+    Chord = "chord"
+}
+
+// What `pick` found. For a point, `source` is its index and `destination`
+// the point its chord goes to. While a transition animates the multiplier,
+// `destination` is the point nearest to the drawn end of the chord.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PickResult {
+    kind: PickKind,
+    source: u32,
+    destination: u32,
+    distance: f32 // pixels from the query position
+}
+
+pub struct PickIndex {
+    // Figure the index was built for
+    points: u32,
+    mul: u32,
+    mul_frac: f32,
+    rotation: f32,
+
+    grid: usize, // cells per side, m along x and c along y
+    cell_start: Vec<u32>, // where each cell's chords start in `chords`, plus the end
+    chords: Vec<u32> // chord indices, sorted by cell
+}
+
+// Struct implementations
+
+#[wasm_bindgen]
+impl PickResult {
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> PickKind {
+        return self.kind;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn source(&self) -> u32 {
+        return self.source;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn destination(&self) -> u32 {
+        return self.destination;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn distance(&self) -> f32 {
+        return self.distance;
+    }
+}
+
+impl PickIndex {
+    pub fn new(shape: &Shape) -> PickIndex {
+        let grid: usize = (((shape.points as f64).sqrt() / 2.0).ceil() as usize).max(MIN_GRID);
+        let mut index: PickIndex = PickIndex {
+            points: shape.points,
+            mul: shape.mul,
+            mul_frac: shape.mul_frac,
+            rotation: shape.rotation,
+            grid,
+            cell_start: vec![0; grid * grid + 1],
+            chords: vec![0; shape.points as usize]
+        };
+
+        // Counting sort of the chords by cell
+        let cells: Vec<u32> = (0..shape.points).map(|i: u32| {
+            let (src, dst): (WorldPoint, WorldPoint) = geometry::chord(shape, i);
+            let (m, c): (f64, f64) = line_of(src, dst);
+            return (index.cell_coord(c, -1.0, 2.0) * grid + index.cell_coord(m, 0.0, PI)) as u32;
+        }).collect();
+        for &cell in &cells {
+            index.cell_start[cell as usize + 1] += 1;
+        }
+        for cell in 0..grid * grid {
+            index.cell_start[cell + 1] += index.cell_start[cell];
+        }
+        let mut next: Vec<u32> = index.cell_start[..grid * grid].to_vec();
+        for (i, &cell) in cells.iter().enumerate() {
+            index.chords[next[cell as usize] as usize] = i as u32;
+            next[cell as usize] += 1;
+        }
+        return index;
+    }
+
+    // Whether the index still describes `shape`
+    pub fn matches(&self, shape: &Shape) -> bool {
+        return self.points == shape.points
+            && self.mul == shape.mul
+            && self.mul_frac == shape.mul_frac
+            && self.rotation == shape.rotation;
+    }
+
+    // Nearest point within `tolerance` pixels of `px` (pixels from the top
+    // left corner of the surface), or failing that the nearest chord.
+    // Points win over chords since every point also has chords through it.
+    pub fn pick(&self, shape: &Shape, camera: &Camera, px: Point, tolerance: f32) -> Option<PickResult> {
+        let pixels_per_unit: f64 = camera.length_to_pixels(1.0) as f64 * camera.scale();
+        if (pixels_per_unit <= 0.0 || !pixels_per_unit.is_finite() || shape.points == 0) {
+            return None;
+        }
+        let target: WorldPoint = camera.pixels_to_world(px);
+        let radius: f64 = tolerance as f64 / pixels_per_unit;
+
+        if let Some((i, d)) = nearest_point(shape, target, radius) {
+            return Some(PickResult {
+                kind: PickKind::Point,
+                source: i,
+                destination: destination(shape, i),
+                distance: (d * pixels_per_unit) as f32
+            });
+        }
+
+        let mut best_chord: Option<(u32, f64)> = None;
+        for i in self.chords_near(target, radius) {
+            let (src, dst): (WorldPoint, WorldPoint) = geometry::chord(shape, i);
+            let d: f64 = distance_to_segment(target, src, dst);
+            if (d <= radius && best_chord.is_none_or(|(_, best)| d < best)) {
+                best_chord = Some((i, d));
+            }
+        }
+        return best_chord.map(|(i, d)| PickResult {
+            kind: PickKind::Chord,
+            source: i,
+            destination: destination(shape, i),
+            distance: (d * pixels_per_unit) as f32
+        });
+    }
+
+    ///////////////////////
+    // Private functions //
+    ///////////////////////
+
+    // Cell along an axis spanning `start..start + size`
+    fn cell_coord(&self, v: f64, start: f64, size: f64) -> usize {
+        let c: f64 = ((v - start) / size * self.grid as f64).floor();
+        return c.clamp(0.0, (self.grid - 1) as f64) as usize;
+    }
+
+    // Chords whose line passes within `radius` of `p`, and a few more
+    fn chords_near(&self, p: WorldPoint, radius: f64) -> impl Iterator<Item = u32> + '_ {
+        let rho: f64 = p.x.hypot(p.y);
+        let phi: f64 = p.y.atan2(p.x);
+        let width: f64 = PI / self.grid as f64;
+
+        // p . n(m) = rho * cos(m - phi) moves by at most rho * width / 2
+        // from its value in the middle of a column
+        let slack: f64 = rho * width / 2.0 + radius;
+        return (0..self.grid).flat_map(move |column: usize| {
+            let mid: f64 = rho * ((column as f64 + 0.5) * width - phi).cos();
+            let rows: std::ops::Range<usize> = if (mid + slack < -1.0 || mid - slack > 1.0) {
+                0..0
+            } else {
+                self.cell_coord(mid - slack, -1.0, 2.0)..self.cell_coord(mid + slack, -1.0, 2.0) + 1
+            };
+            return rows.flat_map(move |row: usize| {
+                let cell: usize = row * self.grid + column;
+                return self.chords[self.cell_start[cell] as usize..self.cell_start[cell + 1] as usize].iter().copied();
+            });
+        });
+    }
+}
+
+// Helper functions
+
+fn distance_to_segment(p: WorldPoint, a: WorldPoint, b: WorldPoint) -> f64 {
+    let (dx, dy): (f64, f64) = (b.x - a.x, b.y - a.y);
+    let len2: f64 = dx * dx + dy * dy;
+    let t: f64 = if (len2 > 0.0) {
+        (((p.x - a.x) * dx + (p.y - a.y) * dy) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    return (p.x - a.x - t * dx).hypot(p.y - a.y - t * dy);
+}
+
+// The point nearest to where chord `i` ends as drawn
+fn destination(shape: &Shape, i: u32) -> u32 {
+    return (geometry::chord_destination_index(shape, i).round() as u32) % shape.points;
+}
+
+// The line through `a` and `b`, both on the unit circle, as the m and c of
+// p . n(m) = c with m in 0..PI
+fn line_of(a: WorldPoint, b: WorldPoint) -> (f64, f64) {
+    let (alpha, beta): (f64, f64) = (a.y.atan2(a.x), b.y.atan2(b.x));
+    let m: f64 = (alpha + beta) / 2.0;
+    let c: f64 = ((beta - alpha) / 2.0).cos();
+
+    // n(m + PI) = -n(m), so the same line is m - PI and -c
+    let turns: f64 = (m / PI).floor();
+    let c: f64 = if (turns.rem_euclid(2.0) == 1.0) { -c } else { c };
+    return (m - turns * PI, c);
+}
+
+// Nearest point within `radius` of `p`, only looking at the points whose
+// angle is close enough to that of `p`
+fn nearest_point(shape: &Shape, p: WorldPoint, radius: f64) -> Option<(u32, f64)> {
+    let rho: f64 = p.x.hypot(p.y);
+    if ((rho - 1.0).abs() > radius) {
+        return None;
+    }
+    let points: f64 = shape.points as f64;
+
+    // Points sit at (-cos(theta), sin(theta)). With the unit vector q toward
+    // p, |point - p|^2 = (1 - rho)^2 + rho * |point - q|^2, so a point within
+    // `radius` is at most 2 * asin(radius / (2 * sqrt(rho))) around the circle
+    // from q.
+    let theta: f64 = p.y.atan2(-p.x);
+    let center: f64 = (theta - shape.rotation as f64 - PI / 2.0) * points / (2.0 * PI);
+    let spread: f64 = (radius / (2.0 * rho.sqrt())).min(1.0).asin() * points / PI;
+    let (first, last): (f64, f64) = ((center - spread).floor(), (center + spread).ceil());
+    let indices: Vec<u32> = if (last - first + 1.0 >= points || !spread.is_finite()) {
+        (0..shape.points).collect()
+    } else {
+        (first as i64..=last as i64).map(|k: i64| k.rem_euclid(shape.points as i64) as u32).collect()
+    };
+
+    let mut best: Option<(u32, f64)> = None;
+    for i in indices {
+        let q: WorldPoint = geometry::point_position(shape, i);
+        let d: f64 = (q.x - p.x).hypot(q.y - p.y);
+        if (d <= radius && best.is_none_or(|(_, best)| d < best)) {
+            best = Some((i, d));
+        }
+    }
+    return best;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Scene;
+
+    // What `pick` has to find, by measuring the distance to everything
+    fn pick_by_scan(shape: &Shape, camera: &Camera, px: Point, tolerance: f32) -> Option<(PickKind, u32)> {
+        let target: WorldPoint = camera.pixels_to_world(px);
+        let radius: f64 = tolerance as f64 / (camera.length_to_pixels(1.0) as f64 * camera.scale());
+        let nearest = |distance: &dyn Fn(u32) -> f64| -> Option<u32> {
+            return (0..shape.points)
+                .map(|i: u32| (i, distance(i)))
+                .filter(|&(_, d): &(u32, f64)| d <= radius)
+                .min_by(|a: &(u32, f64), b: &(u32, f64)| a.1.total_cmp(&b.1))
+                .map(|(i, _): (u32, f64)| i);
+        };
+        if let Some(i) = nearest(&|i: u32| {
+            let q: WorldPoint = geometry::point_position(shape, i);
+            return (q.x - target.x).hypot(q.y - target.y);
+        }) {
+            return Some((PickKind::Point, i));
+        }
+        return nearest(&|i: u32| {
+            let (src, dst): (WorldPoint, WorldPoint) = geometry::chord(shape, i);
+            return distance_to_segment(target, src, dst);
+        }).map(|i: u32| (PickKind::Chord, i));
+    }
+
+    #[test]
+    fn pick_finds_what_a_full_scan_finds() {
+        let mut scene: Scene = Scene::new(400, 300);
+        scene.shape.points = 500;
+        scene.shape.mul = 7;
+        scene.shape.mul_frac = 0.4;
+        scene.shape.rotation = 0.3;
+        let index: PickIndex = PickIndex::new(&scene.shape);
+
+        let mut found: usize = 0;
+        for y in (0..300).step_by(7) {
+            for x in (0..400).step_by(7) {
+                let px: Point = Point::new(x as f32, y as f32);
+                let picked: Option<PickResult> = index.pick(&scene.shape, &scene.camera, px, 3.0);
+                let expected: Option<(PickKind, u32)> = pick_by_scan(&scene.shape, &scene.camera, px, 3.0);
+                assert_eq!(picked.map(|r: PickResult| (r.kind, r.source)), expected, "at {}, {}", x, y);
+                found += picked.is_some() as usize;
+            }
+        }
+        assert!(found > 100);
+    }
+
+    #[test]
+    fn destination_follows_the_transition() {
+        let mut scene: Scene = Scene::new(400, 300);
+        scene.shape.points = 10;
+        scene.shape.mul = 2;
+        scene.shape.mul_frac = 0.9;
+        // Chord 3 goes to 6 at rest and is drawn to 6 + 3 * 0.9 = 8.7
+        assert_eq!(destination(&scene.shape, 3), 9);
+        scene.shape.mul_frac = 0.0;
+        assert_eq!(destination(&scene.shape, 3), 6);
+    }
+}
@@ -17,29 +17,25 @@ You should have received a copy of the GNU General Public License along with Mod
 Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
 */
 
+#ifdef WEBGL1
+attribute float a_index; // point index, 0 to u_points - 1, WebGL1 has no gl_VertexID
+#endif
 uniform float u_points;
-uniform float u_radius;
 uniform float u_rotation;
-uniform vec2 u_position;
-uniform vec2 u_dimensions;
-uniform bool u_widescreen;
+uniform mat3 u_view; // world to clip, see camera.rs
 uniform float u_point_size;
 
-void main() {
-    float pi = 3.1415926535897932384626;
-    float i = float(gl_VertexID);
-    float theta =  i * 2.0 * pi / u_points + u_rotation + (pi / 2.0);
-
-    float x = -1.0 * cos(theta) * u_radius + u_position.x;
-    float y = sin(theta) * u_radius + u_position.y;
+#include "circle.glsl"
 
-    // Aspect-ratio correction
-    if (u_widescreen) {
-        x *= u_dimensions.y / u_dimensions.x;
-    } else {
-        y *= u_dimensions.x / u_dimensions.y;
-    }
-
-    gl_Position = vec4(x, y, 0.0, 1.0);
+void main() {
+#ifdef WEBGL1
+    float index = a_index;
+#else
+    float index = float(gl_VertexID);
+#endif
+    float theta = point_angle(index, u_points, u_rotation);
+    vec2 world = circle_point(theta);
+
+    gl_Position = vec4((u_view * vec3(world, 1.0)).xy, 0.0, 1.0);
     gl_PointSize = u_point_size;
 }
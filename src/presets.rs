@@ -0,0 +1,157 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Curated times-table figures worth knowing about
+
+use crate::scene::{Color, Scene};
+
+// Struct declarations
+
+pub struct Preset {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub points: u32,
+    pub multiplier: u32,
+    pub rotation: f32, // degrees
+    pub line_width: f32, // same units as `Scene::set_rect_width`
+    pub use_rects: bool,
+    pub outline: bool,
+    pub fg: Color,
+    pub bg: Color
+}
+
+// Registry
+
+const FG: Color = Color {r: 250, g: 250, b: 250};
+const BG: Color = Color {r: 24, g: 24, b: 24};
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        id: "cardioid",
+        name: "Cardioid",
+        description: "k = 2. The chords envelop a cardioid, the epicycloid with one cusp.",
+        points: 200,
+        multiplier: 2,
+        rotation: 0.0,
+        line_width: 0.002,
+        use_rects: true,
+        outline: true,
+        fg: FG,
+        bg: BG
+    },
+    Preset {
+        id: "nephroid",
+        name: "Nephroid",
+        description: "k = 3. A kidney shaped curve, the epicycloid with two cusps.",
+        points: 200,
+        multiplier: 3,
+        rotation: 0.0,
+        line_width: 0.002,
+        use_rects: true,
+        outline: true,
+        fg: FG,
+        bg: BG
+    },
+    Preset {
+        id: "epicycloid_3",
+        name: "Three-cusped epicycloid",
+        description: "k = 4. In general, multiplier k gives an epicycloid with k - 1 cusps.",
+        points: 300,
+        multiplier: 4,
+        rotation: 0.0,
+        line_width: 0.0015,
+        use_rects: true,
+        outline: true,
+        fg: FG,
+        bg: BG
+    },
+    Preset {
+        id: "epicycloid_4",
+        name: "Four-cusped epicycloid",
+        description: "k = 5. Four cusps, with the envelope pulled further towards the center.",
+        points: 300,
+        multiplier: 5,
+        rotation: 0.0,
+        line_width: 0.0015,
+        use_rects: true,
+        outline: true,
+        fg: FG,
+        bg: BG
+    },
+    Preset {
+        id: "half_plus_one",
+        name: "k = n/2 + 1",
+        description: "Even points map to themselves and odd points jump to the opposite side, leaving a fan of diameters.",
+        points: 500,
+        multiplier: 251,
+        rotation: 0.0,
+        line_width: 0.002,
+        use_rects: true,
+        outline: true,
+        fg: FG,
+        bg: BG
+    },
+    Preset {
+        id: "n_minus_one",
+        name: "k = n - 1",
+        description: "Every point connects to its mirror image, i to -i, which draws a stack of parallel chords.",
+        points: 200,
+        multiplier: 199,
+        rotation: 0.0,
+        line_width: 0.002,
+        use_rects: true,
+        outline: true,
+        fg: FG,
+        bg: BG
+    },
+    Preset {
+        id: "default",
+        name: "500 × 72",
+        description: "The figure the page opens with, a dense web of overlapping envelopes.",
+        points: 500,
+        multiplier: 72,
+        rotation: 0.0,
+        line_width: 0.002,
+        use_rects: true,
+        outline: false,
+        fg: FG,
+        bg: BG
+    }
+];
+
+// Struct implementations
+
+impl Preset {
+    // Set every parameter of the preset on `scene` and reset the view
+    pub fn apply(&self, scene: &mut Scene) {
+        scene.set_points(self.points);
+        scene.set_multiplier(self.multiplier);
+        scene.reset_view();
+        scene.set_rotation(self.rotation);
+        scene.set_rect_width(self.line_width);
+        scene.set_use_rects(self.use_rects);
+        scene.set_enable_outline(self.outline);
+        scene.set_fg_color(self.fg);
+        scene.set_bg_color(self.bg);
+    }
+}
+
+// Helper functions
+
+pub fn find_preset(id: &str) -> Option<&'static Preset> {
+    return PRESETS.iter().find(|p| p.id == id);
+}
@@ -0,0 +1,98 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular 
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Corners of the rectangles drawn instead of lines
+
+// Vertex `corner`, 0 to 5, of the two triangles making up the rectangle
+// `width` wide from `src` to `dst`, all in view coordinates
+vec2 rect_vertex(vec2 src, vec2 dst, float width, int corner) {
+    float src_x = src.x;
+    float src_y = src.y;
+    float dst_x = dst.x;
+    float dst_y = dst.y;
+
+    // Calculate center of rectangle to be drawn
+    float rect_x = (src_x + dst_x) / 2.0;
+    float rect_y = (src_y + dst_y) / 2.0;
+
+    // Calculate sin and cos of rotation
+    // We don't need to use trigonometric functions because we can calculate
+    // the proportions of the lengths of the triangle
+    float b = src_x - dst_x;
+    float h = src_y - dst_y;
+    float hypotenuse = sqrt(b*b + h*h);
+    float cosrot = 1.0; // for if hypotenuse == 0
+    float sinrot = 0.0; // for if hypotenuse == 0
+    if (hypotenuse > 0.0) {
+        cosrot = b / hypotenuse;
+        sinrot = h / hypotenuse;
+    }
+    float cos90_minus_rot = sinrot; // cos(pi/2 - x) = sin(x)
+    float sin90_minus_rot = cosrot; // sin(pi/2 - x) = cos(x)
+
+    // Now that we know the center and trigonometric values of the rectangle we can draw it
+    vec2 res = vec2(0.0, 0.0);
+    
+    // 0 for top left of first triangle
+    if (corner == 0) {
+        float x_begin = rect_x - (cos90_minus_rot * width / 2.0);
+        float y_begin = rect_y + (sin90_minus_rot * width / 2.0);
+
+        float dx = hypotenuse / 2.0 * cosrot;
+        float dy = hypotenuse / 2.0 * sinrot;
+
+        res.x = x_begin - dx;
+        res.y = y_begin - dy;
+    }
+
+    // 1 and 5 for bottom left of first and second triangle
+    else if (corner == 1 || corner == 5) {
+        float x_begin = rect_x + (cos90_minus_rot * width / 2.0);
+        float y_begin = rect_y - (sin90_minus_rot * width / 2.0);
+
+        float dx = hypotenuse / 2.0 * cosrot;
+        float dy = hypotenuse / 2.0 * sinrot;
+
+        res.x = x_begin - dx;
+        res.y = y_begin - dy;
+    }
+    
+    // 2 and 3 for top right of first and second triangle
+    else if (corner == 2 || corner == 3) {
+        float x_begin = rect_x - (cos90_minus_rot * width / 2.0);
+        float y_begin = rect_y + (sin90_minus_rot * width / 2.0);
+
+        float dx = hypotenuse / 2.0 * cosrot;
+        float dy = hypotenuse / 2.0 * sinrot;
+        
+        res.x = x_begin + dx;
+        res.y = y_begin + dy;
+    }
+
+    // 4 for bottom right of second triangle
+    else if (corner == 4) {
+        float x_begin = rect_x + (cos90_minus_rot * width / 2.0);
+        float y_begin = rect_y - (sin90_minus_rot * width / 2.0);
+
+        float dx = hypotenuse / 2.0 * cosrot;
+        float dy = hypotenuse / 2.0 * sinrot;
+        
+        res.x = x_begin + dx;
+        res.y = y_begin + dy;
+    }
+
+    return res;
+}
@@ -17,114 +17,46 @@ You should have received a copy of the GNU General Public License along with Mod
 Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
 */
 
+// WebGL1 draws it instanced with one rectangle per chord, and works in
+// floats like chord.glsl
+#ifdef WEBGL1
+attribute float a_chord; // chord index, per instance
+attribute float a_corner; // vertex of the rectangle, 0 to 5
+uniform float u_points;
+uniform float u_multiplier; // k mod n
+#else
 uniform int u_points;
-uniform float u_radius;
+uniform int u_multiplier; // k mod n
+#endif
 uniform float u_rotation;
-uniform vec2 u_position;
+uniform mat3 u_view; // world to clip, see camera.rs
 uniform vec2 u_dimensions;
-uniform bool u_widescreen;
-uniform int u_multiplier;
-uniform float u_rectw;
+uniform float u_multiplier_frac; // fractional part of the multiplier, only nonzero during transitions
+uniform float u_rectw; // in view units, so the width doesn't change with the zoom
 
-void main() {
-    float pi = 3.1415926535897932384626;
-    int vi = gl_VertexID;
+#include "circle.glsl"
+#include "chord.glsl"
+#include "rect.glsl"
 
+void main() {
     // Calculate necessary parameters
+#ifdef WEBGL1
+    float src_i = a_chord;
+    int local_vi = int(a_corner + 0.5); // index of vertex within current rectangle
+#else
+    int vi = gl_VertexID;
     int src_i = vi / 6;
-    int dst_i = (src_i * u_multiplier) %  u_points;
     int local_vi = vi % 6; // index of vertex within current rectangle
-
-    float fsrc_i = float(src_i);
-    float fdst_i = float(dst_i);
+#endif
     float fpoints = float(u_points);
-    float src_theta = fsrc_i * 2.0 * pi / fpoints + u_rotation + (pi / 2.0);
-    float dst_theta = fdst_i * 2.0 * pi / fpoints + u_rotation + (pi / 2.0);
-
-    // Calculate source vertex position
-    float src_x = -1.0 * cos(src_theta) * u_radius + u_position.x;
-    float src_y = sin(src_theta) * u_radius + u_position.y;
-
-    // Calculate destination vertex position
-    float dst_x = -1.0 * cos(dst_theta) * u_radius + u_position.x;
-    float dst_y = sin(dst_theta) * u_radius + u_position.y;
-
-    // Calculate center of rectangle to be drawn
-    float rect_x = (src_x + dst_x) / 2.0;
-    float rect_y = (src_y + dst_y) / 2.0;
-
-    // Calculate sin and cos of rotation
-    // We don't need to use trigonometric functions because we can calculate
-    // the proportions of the lengths of the triangle
-    float b = src_x - dst_x;
-    float h = src_y - dst_y;
-    float hypotenuse = sqrt(b*b + h*h);
-    float cosrot = 1.0; // for if hypotenuse == 0
-    float sinrot = 0.0; // for if hypotenuse == 0
-    if (hypotenuse > 0.0) {
-        cosrot = b / hypotenuse;
-        sinrot = h / hypotenuse;
-    }
-    float cos90_minus_rot = sinrot; // cos(pi/2 - x) = sin(x)
-    float sin90_minus_rot = cosrot; // sin(pi/2 - x) = cos(x)
-
-    // Now that we know the center and trigonometric values of the rectangle we can draw it
-    vec2 res = vec2(0.0, 0.0);
-    
-    // 0 for top left of first triangle
-    if (local_vi == 0) {
-        float x_begin = rect_x - (cos90_minus_rot * u_rectw / 2.0);
-        float y_begin = rect_y + (sin90_minus_rot * u_rectw / 2.0);
-
-        float dx = hypotenuse / 2.0 * cosrot;
-        float dy = hypotenuse / 2.0 * sinrot;
-
-        res.x = x_begin - dx;
-        res.y = y_begin - dy;
-    }
-
-    // 1 and 5 for bottom left of first and second triangle
-    else if (local_vi == 1 || local_vi == 5) {
-        float x_begin = rect_x + (cos90_minus_rot * u_rectw / 2.0);
-        float y_begin = rect_y - (sin90_minus_rot * u_rectw / 2.0);
-
-        float dx = hypotenuse / 2.0 * cosrot;
-        float dy = hypotenuse / 2.0 * sinrot;
-
-        res.x = x_begin - dx;
-        res.y = y_begin - dy;
-    }
-    
-    // 2 and 3 for top right of first and second triangle
-    else if (local_vi == 2 || local_vi == 3) {
-        float x_begin = rect_x - (cos90_minus_rot * u_rectw / 2.0);
-        float y_begin = rect_y + (sin90_minus_rot * u_rectw / 2.0);
-
-        float dx = hypotenuse / 2.0 * cosrot;
-        float dy = hypotenuse / 2.0 * sinrot;
-        
-        res.x = x_begin + dx;
-        res.y = y_begin + dy;
-    }
-
-    // 4 for bottom right of second triangle
-    else if (local_vi == 4) {
-        float x_begin = rect_x + (cos90_minus_rot * u_rectw / 2.0);
-        float y_begin = rect_y - (sin90_minus_rot * u_rectw / 2.0);
-
-        float dx = hypotenuse / 2.0 * cosrot;
-        float dy = hypotenuse / 2.0 * sinrot;
-        
-        res.x = x_begin + dx;
-        res.y = y_begin + dy;
-    }
+    float src_theta = point_angle(float(src_i), fpoints, u_rotation);
+    float dst_theta = point_angle(chord_end(src_i, u_multiplier, u_multiplier_frac, u_points), fpoints, u_rotation);
 
-    // Normalize for non-square viewport
-    if (u_widescreen) {
-        res.x *= u_dimensions.y / u_dimensions.x;
-    } else {
-        res.y *= u_dimensions.x / u_dimensions.y;
-    }
+    // Both ends in view coordinates
+    vec2 to_view = clip_to_view(u_dimensions);
+    vec2 src = (u_view * vec3(circle_point(src_theta), 1.0)).xy * to_view;
+    vec2 dst = (u_view * vec3(circle_point(dst_theta), 1.0)).xy * to_view;
 
-    gl_Position = vec4(res, 0.0, 1.0);
-}
\ No newline at end of file
+    // Back to clip coordinates
+    gl_Position = vec4(rect_vertex(src, dst, u_rectw, local_vi) / to_view, 0.0, 1.0);
+}
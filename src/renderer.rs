@@ -0,0 +1,141 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::camera::Camera;
+use crate::scene::{Color, Point, Scene, Shape};
+
+// Struct declarations
+
+// What changed since a renderer last drew, so it can skip uploading what
+// is still current on the GPU. Any flag set means a new frame is needed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Dirty {
+    pub figure: bool, // uniforms describing the figure, points, multiplier, widths, mode
+    pub view: bool, // the view matrix
+    pub colors: bool, // foreground and background
+    pub surface: bool, // size of the drawing surface
+    pub buffers: bool // vertices computed on the CPU at deep zoom, see `Camera::is_deep`
+}
+
+// A drawing backend. Shapes are in world coordinates and get placed on the
+// surface by the camera, see camera.rs. Line widths are in view units so
+// they look the same at any zoom. Text is placed in pixels from the top left
+// corner of the surface instead, since labels should not move with the view.
+pub trait Renderer {
+    // Called whenever the drawing surface changes size
+    fn resize(&mut self, width: u32, height: u32);
+
+    // Called before drawing a frame with what changed since the last one.
+    // Renderers that keep nothing between frames can ignore it.
+    fn invalidate(&mut self, _dirty: Dirty) {}
+
+    // Draw into the `width` x `height` pixel part of the surface with its
+    // top left corner at `x`, `y` until `reset_viewport`, clipping anything
+    // outside. Cameras passed in the meantime should have that size too.
+    // A different figure still has to be passed to `invalidate`. Renderers
+    // that only ever draw whole surfaces can ignore it.
+    fn set_viewport(&mut self, _x: u32, _y: u32, _width: u32, _height: u32) {}
+
+    // Back to the whole surface
+    fn reset_viewport(&mut self) {}
+
+    // Clears the viewport only
+    fn clear(&mut self, color: &Color);
+
+    // Ring around the circle, with the inside filled with `bg`
+    fn draw_outline(&mut self, shape: &Shape, camera: &Camera, bg: &Color);
+
+    // Lines or rectangles depending on `Shape::use_rects_instead_of_lines`
+    fn draw_chords(&mut self, shape: &Shape, camera: &Camera);
+
+    fn draw_points(&mut self, shape: &Shape, camera: &Camera);
+
+    // Thin closed line through `points` in view coordinates, for overlays
+    // such as the minimap. Renderers without overlays can ignore it.
+    fn draw_polygon(&mut self, _points: &[Point], _camera: &Camera, _color: &Color) {}
+
+    // Thin lines between pairs of view coordinates, see `draw_polygon`
+    fn draw_segments(&mut self, _segments: &[(Point, Point)], _camera: &Camera, _color: &Color) {}
+
+    // `x` and `y` are the top left corner of the text, `size` is in pixels
+    fn draw_text(&mut self, text: &str, x: f32, y: f32, size: f32, color: &Color);
+}
+
+// Struct implementations
+
+impl Dirty {
+    pub fn all() -> Dirty {
+        return Dirty {
+            figure: true,
+            view: true,
+            colors: true,
+            surface: true,
+            buffers: true
+        };
+    }
+
+    // What has to be redrawn to go from showing `before` to `after`
+    pub fn between(before: &Scene, after: &Scene) -> Dirty {
+        let (b, a) = (&before.shape, &after.shape);
+        let figure: bool = b.points != a.points
+            || b.mul != a.mul
+            || b.mul_frac != a.mul_frac
+            || b.rotation != a.rotation
+            || b.point_size != a.point_size
+            || b.outline_width != a.outline_width
+            || b.outline_segments != a.outline_segments
+            || b.use_rects_instead_of_lines != a.use_rects_instead_of_lines
+            || b.rect_width != a.rect_width
+            || before.enable_outline != after.enable_outline;
+        let surface: bool = before.camera.dimensions != after.camera.dimensions;
+        let view: bool = surface
+            || before.camera.center != after.camera.center
+            || before.camera.zoom != after.camera.zoom
+            || before.camera.rotation != after.camera.rotation;
+        let colors: bool = b.color != a.color || before.bg != after.bg;
+        return Dirty {
+            figure,
+            view,
+            colors,
+            surface,
+            buffers: (figure || view) && after.camera.is_deep()
+        };
+    }
+
+    pub fn any(&self) -> bool {
+        return self.figure || self.view || self.colors || self.surface || self.buffers;
+    }
+
+    pub fn merge(&mut self, other: Dirty) {
+        self.figure |= other.figure;
+        self.view |= other.view;
+        self.colors |= other.colors;
+        self.surface |= other.surface;
+        self.buffers |= other.buffers;
+    }
+}
+
+// Helper functions
+
+// Draw a complete frame of `scene`
+pub fn render(scene: &Scene, renderer: &mut dyn Renderer) {
+    renderer.clear(&scene.bg);
+    if (scene.enable_outline) {
+        renderer.draw_outline(&scene.shape, &scene.camera, &scene.bg);
+    }
+    renderer.draw_chords(&scene.shape, &scene.camera);
+    renderer.draw_points(&scene.shape, &scene.camera);
+}
@@ -0,0 +1,67 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Keeps the drawing surface the size the canvas element is laid out at,
+// whatever changes it: the window, the page layout or the CSS. The element
+// decides its own size, so a viewer can sit anywhere on a page next to
+// others.
+
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlCanvasElement, ResizeObserver};
+
+use crate::canvas_state::{CanvasState, dispatch_changes};
+use crate::error::ModmulError;
+
+// Struct declarations
+
+// Observer on the canvas element, disconnected again on drop
+pub struct ResizeWatcher {
+    observer: ResizeObserver,
+    _callback: Closure<dyn FnMut()>
+}
+
+// Struct implementations
+
+impl ResizeWatcher {
+    pub fn attach(state: &Rc<RefCell<CanvasState>>) -> Result<ResizeWatcher, JsValue> {
+        let canvas: HtmlCanvasElement = state.borrow().surface.element().cloned().ok_or(ModmulError::Invalid(String::from("Resizing needs a canvas element")))?;
+        let weak: Weak<RefCell<CanvasState>> = Rc::downgrade(state);
+        let callback: Closure<dyn FnMut()> = Closure::new(move || {
+            if let Some(state) = weak.upgrade() {
+                if let Ok(mut state) = state.try_borrow_mut() {
+                    state.resize_to_element();
+                }
+                dispatch_changes(&state);
+            }
+        });
+        let observer: ResizeObserver = ResizeObserver::new(callback.as_ref().unchecked_ref())?;
+        observer.observe(&canvas);
+        return Ok(ResizeWatcher {
+            observer,
+            _callback: callback
+        });
+    }
+}
+
+impl Drop for ResizeWatcher {
+    fn drop(&mut self) {
+        self.observer.disconnect();
+    }
+}
@@ -0,0 +1,322 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Scene state shared by every renderer backend. Nothing in here knows
+// about WebGL, so the same scene can be drawn by any `Renderer`.
+//
+// The shape only describes the figure, which always sits on the unit circle
+// around the origin. Where it ends up on the surface is up to the camera.
+
+use wasm_bindgen::prelude::*;
+
+use crate::camera::Camera;
+use crate::limits::{MAX_LINE_WIDTH, MAX_POINTS, MIN_LINE_WIDTH, MIN_POINTS};
+
+// Radius of the circle in view units when the view is reset, leaves a small
+// margin around it
+pub const DEFAULT_RADIUS: f32 = 0.92;
+
+// Struct declarations
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Point {
+    pub(crate) x: f32,
+    pub(crate) y: f32
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Color {
+    pub(crate) r: u8,
+    pub(crate) g: u8,
+    pub(crate) b: u8
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shape {
+    pub(crate) points: u32,
+    pub(crate) mul: u32, // multiplier
+    pub(crate) mul_frac: f32, // fractional part of the multiplier, only used while animating
+    pub(crate) color: Color,
+    pub(crate) point_size: f32,
+    pub(crate) rotation: f32, // where point 0 sits, the figure rotates with it
+    pub(crate) outline_width: f32,
+    pub(crate) outline_segments: i32,
+    pub(crate) use_rects_instead_of_lines: bool,
+    pub(crate) rect_width: f32
+}
+
+// Everything a renderer needs to draw one frame
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scene {
+    pub(crate) shape: Shape,
+    pub(crate) camera: Camera,
+    pub(crate) bg: Color,
+    pub(crate) enable_outline: bool
+}
+
+// Struct implementations
+
+impl Point {
+    pub fn new(x: f32, y: f32) -> Point {
+        return Point {x, y};
+    }
+
+    pub fn x(&self) -> f32 {
+        return self.x;
+    }
+
+    pub fn y(&self) -> f32 {
+        return self.y;
+    }
+}
+
+impl Color {
+    pub fn new(r: u8, g: u8, b: u8) -> Color {
+        return Color {r, g, b};
+    }
+
+    pub fn r(&self) -> u8 {
+        return self.r;
+    }
+
+    pub fn g(&self) -> u8 {
+        return self.g;
+    }
+
+    pub fn b(&self) -> u8 {
+        return self.b;
+    }
+
+    // Components scaled to 0.0..=1.0 for the graphics APIs
+    pub fn to_f32(&self) -> (f32, f32, f32) {
+        return (normalize_u8_to_1(self.r), normalize_u8_to_1(self.g), normalize_u8_to_1(self.b));
+    }
+
+    // `t` of the way from this color to `other`
+    pub fn mix(&self, other: &Color, t: f32) -> Color {
+        let channel = |x: u8, y: u8| -> u8 { (x as f32 + (y as f32 - x as f32) * t).round() as u8 };
+        return Color::new(channel(self.r, other.r), channel(self.g, other.g), channel(self.b, other.b));
+    }
+
+    // CSS style "#rrggbb"
+    pub fn to_hex(&self) -> String {
+        return format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b);
+    }
+
+    // Parse "#rrggbb" or "rrggbb"
+    pub fn from_hex(hex: &str) -> Option<Color> {
+        let digits: &str = hex.strip_prefix('#').unwrap_or(hex);
+        if (digits.len() != 6 || !digits.is_ascii()) {
+            return None;
+        }
+        let r: u8 = u8::from_str_radix(&digits[0..2], 16).ok()?;
+        let g: u8 = u8::from_str_radix(&digits[2..4], 16).ok()?;
+        let b: u8 = u8::from_str_radix(&digits[4..6], 16).ok()?;
+        return Some(Color {r, g, b});
+    }
+}
+
+impl Shape {
+    pub fn points(&self) -> u32 {
+        return self.points;
+    }
+
+    pub fn multiplier(&self) -> u32 {
+        return self.mul;
+    }
+
+    pub fn color(&self) -> Color {
+        return self.color;
+    }
+
+    pub fn point_size(&self) -> f32 {
+        return self.point_size;
+    }
+
+    // Radians
+    pub fn rotation(&self) -> f32 {
+        return self.rotation;
+    }
+
+    pub fn use_rects(&self) -> bool {
+        return self.use_rects_instead_of_lines;
+    }
+
+    pub fn rect_width(&self) -> f32 {
+        return self.rect_width;
+    }
+}
+
+impl Scene {
+    // Default figure for a drawing surface of `width` x `height` pixels
+    pub fn new(width: u32, height: u32) -> Scene {
+        let shape: Shape = Shape {
+            points: 500,
+            mul: 72,
+            mul_frac: 0.0,
+            color: Color {
+                r: 250,
+                g: 250,
+                b: 250
+            },
+            point_size: 2.0,
+            rotation: 0.0,
+            outline_width: 0.0035,
+            outline_segments: 1440,
+            use_rects_instead_of_lines: true,
+            rect_width: 0.002
+        };
+
+        return Scene {
+            shape,
+            camera: Camera::new(width, height),
+            bg: Color {
+                r: 24,
+                g: 24,
+                b: 24
+            },
+            enable_outline: false
+        };
+    }
+
+    pub fn shape(&self) -> &Shape {
+        return &self.shape;
+    }
+
+    pub fn camera(&self) -> &Camera {
+        return &self.camera;
+    }
+
+    pub fn bg(&self) -> Color {
+        return self.bg;
+    }
+
+    pub fn enable_outline(&self) -> bool {
+        return self.enable_outline;
+    }
+
+    // Rotation in degrees, the unit `set_rotation` takes
+    pub fn rotation_deg(&self) -> f32 {
+        return self.shape.rotation * 180.0 / std::f32::consts::PI;
+    }
+
+    // Clamped to MIN_POINTS..=MAX_POINTS, see limits.rs
+    pub fn set_points(&mut self, value: u32) {
+        let value: u32 = value.clamp(MIN_POINTS, MAX_POINTS);
+        self.shape.points = value;
+        if (value >  1440) {
+            self.shape.outline_segments = value as i32;
+        } else {
+            self.shape.outline_segments = 1440;
+        }
+    }
+
+    pub fn set_multiplier(&mut self, value: u32) {
+        self.shape.mul = value;
+        self.shape.mul_frac = 0.0;
+    }
+
+    // Ignored unless finite
+    pub fn set_rotation(&mut self, deg: f32) {
+        if (!deg.is_finite()) {
+            return;
+        }
+        // first convert degrees to radians
+        let rad: f32 = deg * std::f32::consts::PI / 180.0;
+        self.shape.rotation = rad;
+    }
+
+    // Move the picture by `dx`, `dy` view units
+    pub fn move_shape(&mut self, dx: f32, dy: f32) {
+        self.camera.pan(dx, dy);
+    }
+
+    pub fn set_dimensions(&mut self, width: u32, height: u32) {
+        self.camera.set_dimensions(width, height);
+    }
+
+    pub fn set_enable_outline(&mut self, value: bool) {
+        self.enable_outline = value;
+    }
+
+    pub fn set_use_rects(&mut self, value: bool) {
+        self.shape.use_rects_instead_of_lines = value;
+    }
+
+    // Clamped to MIN_LINE_WIDTH..=MAX_LINE_WIDTH, NaN is ignored
+    pub fn set_rect_width(&mut self, value: f32) {
+        if (value.is_nan()) {
+            return;
+        }
+        let value: f32 = value.clamp(MIN_LINE_WIDTH, MAX_LINE_WIDTH);
+        self.shape.rect_width = value;
+        self.shape.outline_width = value * 1.5;
+    }
+
+    pub fn set_fg_color(&mut self, color: Color) {
+        self.shape.color = color;
+    }
+
+    pub fn set_bg_color(&mut self, color: Color) {
+        self.bg = color;
+    }
+
+    pub fn reset_view(&mut self) {
+        self.camera.reset();
+        self.shape.rotation = 0.0;
+    }
+
+    // Zoom by `factor` keeping the point under the cursor fixed. `mx`, `my`
+    // are in clip coordinates, -1.0..1.0 across both sides of the surface.
+    pub fn zoom_at(&mut self, factor: f64, mx: f32, my: f32) {
+        let (x_norm, y_norm): (f32, f32) = self.camera.normalization();
+        self.camera.zoom_at(factor, Point::new(mx / x_norm, my / y_norm));
+    }
+
+    // Grow the radius of the circle by `val` view units, see `zoom_at`
+    pub fn add_to_r(&mut self, val: f32, mx: f32, my: f32) {
+        let r: f64 = self.camera.scale();
+        self.zoom_at((r + val as f64) / r, mx, my);
+    }
+
+    // Radius of the circle in view units
+    pub fn get_r(&self) -> f32 {
+        return self.camera.scale() as f32;
+    }
+
+    // Rotation of the view in degrees, around the middle of the surface.
+    // Ignored unless finite.
+    pub fn set_view_rotation(&mut self, deg: f64) {
+        if (!deg.is_finite()) {
+            return;
+        }
+        let rad: f64 = deg * std::f64::consts::PI / 180.0;
+        self.camera.rotate_at(rad - self.camera.rotation, Point::new(0.0, 0.0));
+    }
+
+    pub fn view_rotation_deg(&self) -> f64 {
+        return self.camera.rotation * 180.0 / std::f64::consts::PI;
+    }
+}
+
+// Helper functions
+
+pub fn normalize_u8_to_1(arg: u8) -> f32 {
+    return (arg as f32) / 255.0;
+}
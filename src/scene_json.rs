@@ -0,0 +1,277 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Saving and loading scenes as versioned JSON documents.
+//
+// Every document carries a "version" field. When the layout changes, bump
+// SCENE_VERSION and append a migration that rewrites the previous layout
+// into the new one, so files saved by older versions keep loading.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::camera::WorldPoint;
+use crate::error::ModmulError;
+use crate::limits;
+use crate::scene::{Color, DEFAULT_RADIUS, Scene};
+
+pub const SCENE_VERSION: u64 = 2;
+
+type Migration = fn(Value) -> Result<Value, String>;
+
+// MIGRATIONS[i] upgrades a document from version i + 1 to version i + 2
+const MIGRATIONS: &[Migration] = &[migrate_v1_view_to_camera];
+
+// Struct declarations
+
+#[derive(Serialize, Deserialize)]
+struct SceneDocument {
+    version: u64,
+    #[serde(default)]
+    shape: ShapeSection,
+    #[serde(default)]
+    colors: ColorSection,
+    #[serde(default)]
+    view: ViewSection,
+    #[serde(default)]
+    render: RenderSection
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct ShapeSection {
+    points: u32,
+    multiplier: u32,
+    rotation: f32, // degrees
+    line_width: f32,
+    point_size: f32
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct ColorSection {
+    foreground: String,
+    background: String
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct ViewSection {
+    x: f64, // world point in the middle of the surface
+    y: f64,
+    zoom: f64, // 1 is the reset view
+    rotation: f64 // degrees
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+enum RenderMode {
+    #[default]
+    Rects,
+    Lines
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct RenderSection {
+    mode: RenderMode,
+    outline: bool
+}
+
+// Struct implementations
+
+impl SceneDocument {
+    fn from_scene(scene: &Scene) -> SceneDocument {
+        return SceneDocument {
+            version: SCENE_VERSION,
+            shape: ShapeSection {
+                points: scene.shape.points,
+                multiplier: scene.shape.mul,
+                rotation: scene.rotation_deg(),
+                line_width: scene.shape.rect_width,
+                point_size: scene.shape.point_size
+            },
+            colors: ColorSection {
+                foreground: scene.shape.color.to_hex(),
+                background: scene.bg.to_hex()
+            },
+            view: ViewSection {
+                x: scene.camera.center.x,
+                y: scene.camera.center.y,
+                zoom: scene.camera.zoom,
+                rotation: scene.view_rotation_deg()
+            },
+            render: RenderSection {
+                mode: if (scene.shape.use_rects_instead_of_lines) { RenderMode::Rects } else { RenderMode::Lines },
+                outline: scene.enable_outline
+            }
+        };
+    }
+}
+
+// Missing sections and fields fall back to the values of a fresh scene
+impl Default for ShapeSection {
+    fn default() -> ShapeSection {
+        return SceneDocument::from_scene(&Scene::new(0, 0)).shape;
+    }
+}
+
+impl Default for ColorSection {
+    fn default() -> ColorSection {
+        return SceneDocument::from_scene(&Scene::new(0, 0)).colors;
+    }
+}
+
+impl Default for ViewSection {
+    fn default() -> ViewSection {
+        return SceneDocument::from_scene(&Scene::new(0, 0)).view;
+    }
+}
+
+impl Default for RenderSection {
+    fn default() -> RenderSection {
+        return SceneDocument::from_scene(&Scene::new(0, 0)).render;
+    }
+}
+
+impl Scene {
+    pub fn to_json(&self) -> String {
+        // Serializing plain structs of numbers and strings cannot fail
+        return serde_json::to_string_pretty(&SceneDocument::from_scene(self)).unwrap_or_default();
+    }
+
+    // Replace the scene state with the one in `json`. The drawing surface
+    // dimensions are kept. Nothing changes if the document is invalid.
+    pub fn load_json(&mut self, json: &str) -> Result<(), String> {
+        let raw: Value = serde_json::from_str(json).map_err(|e| format!("Invalid scene JSON: {}", e))?;
+        let doc: SceneDocument = serde_json::from_value(migrate(raw)?).map_err(|e| format!("Invalid scene JSON: {}", e))?;
+
+        let fg: Color = Color::from_hex(&doc.colors.foreground).ok_or(format!("Invalid foreground color {}", doc.colors.foreground))?;
+        let bg: Color = Color::from_hex(&doc.colors.background).ok_or(format!("Invalid background color {}", doc.colors.background))?;
+        if (!(doc.view.zoom > 0.0 && doc.view.zoom.is_finite())) {
+            return Err(format!("Invalid zoom {}", doc.view.zoom));
+        }
+        check(limits::check_points(doc.shape.points as f64))?;
+        check(limits::check_line_width(doc.shape.line_width))?;
+        check(limits::check_point_size(doc.shape.point_size))?;
+        check(limits::check_degrees("rotation", doc.shape.rotation as f64))?;
+        check(limits::check_degrees("view rotation", doc.view.rotation))?;
+
+        self.set_points(doc.shape.points);
+        self.set_multiplier(doc.shape.multiplier);
+        self.set_rotation(doc.shape.rotation);
+        self.set_rect_width(doc.shape.line_width);
+        self.shape.point_size = doc.shape.point_size;
+        self.set_fg_color(fg);
+        self.set_bg_color(bg);
+        self.camera.center = WorldPoint::new(doc.view.x, doc.view.y);
+        self.camera.zoom = doc.view.zoom;
+        self.set_view_rotation(doc.view.rotation);
+        self.set_use_rects(matches!(doc.render.mode, RenderMode::Rects));
+        self.set_enable_outline(doc.render.outline);
+        return Ok(());
+    }
+}
+
+// Helper functions
+
+// A value out of the ranges in limits.rs
+fn check<T>(result: Result<T, ModmulError>) -> Result<T, String> {
+    return result.map_err(|e: ModmulError| format!("Invalid scene JSON: {}", e));
+}
+
+// Bring a document of any supported version up to SCENE_VERSION
+fn migrate(mut doc: Value) -> Result<Value, String> {
+    let mut version: u64 = doc.get("version").and_then(Value::as_u64).ok_or("Scene JSON has no version")?;
+    if (version == 0) {
+        return Err(String::from("Scene JSON has an invalid version 0"));
+    }
+    if (version > SCENE_VERSION) {
+        return Err(format!("Scene JSON version {} was saved by a newer version, this one reads up to {}", version, SCENE_VERSION));
+    }
+
+    while (version < SCENE_VERSION) {
+        let migration: &Migration = MIGRATIONS.get((version - 1) as usize).ok_or(format!("No migration from scene JSON version {}", version))?;
+        doc = migration(doc)?;
+        version += 1;
+        if let Some(fields) = doc.as_object_mut() {
+            fields.insert(String::from("version"), Value::from(version));
+        }
+    }
+    return Ok(doc);
+}
+
+// Version 1 stored where the circle was drawn, as the position of its center
+// and its radius in view units. Version 2 stores the camera instead.
+fn migrate_v1_view_to_camera(mut doc: Value) -> Result<Value, String> {
+    let Some(view) = doc.get_mut("view").and_then(Value::as_object_mut) else {
+        return Ok(doc);
+    };
+    let x: f64 = view.get("x").and_then(Value::as_f64).unwrap_or(0.0);
+    let y: f64 = view.get("y").and_then(Value::as_f64).unwrap_or(0.0);
+    let radius: f64 = view.get("radius").and_then(Value::as_f64).unwrap_or(DEFAULT_RADIUS as f64);
+    if (radius <= 0.0) {
+        return Err(format!("Invalid view radius {}", radius));
+    }
+
+    let mut camera: Map<String, Value> = Map::new();
+    camera.insert(String::from("x"), Value::from(-x / radius));
+    camera.insert(String::from("y"), Value::from(-y / radius));
+    camera.insert(String::from("zoom"), Value::from(radius / DEFAULT_RADIUS as f64));
+    camera.insert(String::from("rotation"), Value::from(0.0));
+    *view = camera;
+    return Ok(doc);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_1_views_become_cameras() {
+        let mut scene: Scene = Scene::new(800, 600);
+        let json: &str = r#"{"version": 1, "shape": {"points": 300, "multiplier": 5}, "view": {"x": 0.2, "y": -0.1, "radius": 1.8}}"#;
+        scene.load_json(json).unwrap();
+
+        // The circle's center was drawn at (0.2, -0.1), so the camera looks
+        // at the world point that far the other way, scaled by the radius
+        assert_eq!(scene.shape.points, 300);
+        assert_eq!(scene.shape.mul, 5);
+        assert!((scene.camera.center.x - -0.2 / 1.8).abs() < 1e-12);
+        assert!((scene.camera.center.y - 0.1 / 1.8).abs() < 1e-12);
+        assert!((scene.camera.zoom - 1.8 / DEFAULT_RADIUS as f64).abs() < 1e-12);
+        assert_eq!(scene.camera.rotation, 0.0);
+
+        // Saving writes the current version, which loads back the same
+        let saved: String = scene.to_json();
+        assert_eq!(serde_json::from_str::<Value>(&saved).unwrap()["version"], Value::from(SCENE_VERSION));
+        let mut loaded: Scene = Scene::new(800, 600);
+        loaded.load_json(&saved).unwrap();
+        assert_eq!(loaded.camera.center, scene.camera.center);
+        assert!((loaded.camera.zoom / scene.camera.zoom - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn versions_from_the_future_are_rejected() {
+        let mut scene: Scene = Scene::new(800, 600);
+        let before: String = scene.to_json();
+        let json: String = format!(r#"{{"version": {}, "shape": {{"points": 300}}}}"#, SCENE_VERSION + 1);
+        let err: String = scene.load_json(&json).unwrap_err();
+        assert!(err.contains("newer version"), "{}", err);
+        assert!(scene.load_json(r#"{"shape": {"points": 300}}"#).is_err());
+        assert_eq!(scene.to_json(), before);
+    }
+}
@@ -0,0 +1,136 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// What a viewer draws into: a canvas element on the page, or an
+// OffscreenCanvas the page transferred to a worker. Only the element has a
+// layout, events and a document of its own. An offscreen surface is told the
+// size its element is laid out at, and gets client coordinates relative to
+// that element's top left corner, see worker.js.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{EventTarget, HtmlCanvasElement, OffscreenCanvas, WebGl2RenderingContext, WebGlRenderingContext, Window};
+
+use crate::error::ModmulError;
+
+// Struct declarations
+
+// Where the element of an offscreen surface sits on the page
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layout {
+    pub css_width: f64,
+    pub css_height: f64,
+    pub pixel_ratio: f64 // device pixels per CSS pixel
+}
+
+pub enum Surface {
+    Element(HtmlCanvasElement),
+    Offscreen(OffscreenCanvas, Layout)
+}
+
+// Struct implementations
+
+impl Surface {
+    // Drawing buffer size in pixels
+    pub fn width(&self) -> u32 {
+        return match self {
+            Surface::Element(canvas) => canvas.width(),
+            Surface::Offscreen(canvas, _) => canvas.width()
+        };
+    }
+
+    pub fn height(&self) -> u32 {
+        return match self {
+            Surface::Element(canvas) => canvas.height(),
+            Surface::Offscreen(canvas, _) => canvas.height()
+        };
+    }
+
+    pub fn set_size(&self, width: u32, height: u32) {
+        match self {
+            Surface::Element(canvas) => {
+                canvas.set_width(width);
+                canvas.set_height(height);
+            }
+            Surface::Offscreen(canvas, _) => {
+                canvas.set_width(width);
+                canvas.set_height(height);
+            }
+        }
+    }
+
+    pub fn get_context(&self, name: &str) -> Result<Option<js_sys::Object>, JsValue> {
+        return match self {
+            Surface::Element(canvas) => canvas.get_context(name),
+            Surface::Offscreen(canvas, _) => canvas.get_context(name)
+        };
+    }
+
+    // The WebGL2 context, the same one on every call
+    pub fn webgl2(&self) -> Result<WebGl2RenderingContext, ModmulError> {
+        return self.get_context("webgl2").ok().flatten()
+            .and_then(|context: js_sys::Object| context.dyn_into::<WebGl2RenderingContext>().ok())
+            .ok_or(ModmulError::WebGl2Unsupported);
+    }
+
+    // The WebGL1 context, only there if `webgl2` was never asked for
+    pub fn webgl1(&self) -> Result<WebGlRenderingContext, ModmulError> {
+        return self.get_context("webgl").ok().flatten()
+            .and_then(|context: js_sys::Object| context.dyn_into::<WebGlRenderingContext>().ok())
+            .ok_or(ModmulError::WebGlUnsupported);
+    }
+
+    // The canvas element, none in a worker
+    pub fn element(&self) -> Option<&HtmlCanvasElement> {
+        return match self {
+            Surface::Element(canvas) => Some(canvas),
+            Surface::Offscreen(..) => None
+        };
+    }
+
+    // Where the WebGL context events fire
+    pub fn event_target(&self) -> EventTarget {
+        return match self {
+            Surface::Element(canvas) => canvas.clone().into(),
+            Surface::Offscreen(canvas, _) => canvas.clone().into()
+        };
+    }
+
+    // Left, top, width and height of the element in client coordinates
+    pub fn client_rect(&self) -> (f64, f64, f64, f64) {
+        return match self {
+            Surface::Element(canvas) => {
+                let rect: web_sys::DomRect = canvas.get_bounding_client_rect();
+                (rect.left(), rect.top(), rect.width(), rect.height())
+            }
+            Surface::Offscreen(_, layout) => (0.0, 0.0, layout.css_width, layout.css_height)
+        };
+    }
+
+    pub fn pixel_ratio(&self) -> f64 {
+        return match self {
+            Surface::Element(_) => web_sys::window().map_or(1.0, |window: Window| window.device_pixel_ratio()),
+            Surface::Offscreen(_, layout) => layout.pixel_ratio
+        };
+    }
+
+    // The page laid the element of an offscreen surface out again
+    pub fn set_layout(&mut self, layout: Layout) {
+        if let Surface::Offscreen(_, current) = self {
+            *current = layout;
+        }
+    }
+}
@@ -0,0 +1,119 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Vector backend that writes an SVG document, one pixel per user unit
+
+use std::fmt::Write;
+
+use crate::camera::{Camera, WorldPoint};
+use crate::geometry;
+use crate::renderer::Renderer;
+use crate::scene::{Color, Point, Shape};
+
+// Struct declarations
+
+pub struct SvgRenderer {
+    width: u32,
+    height: u32,
+    body: String
+}
+
+// Struct implementations
+
+impl SvgRenderer {
+    pub fn new(width: u32, height: u32) -> SvgRenderer {
+        return SvgRenderer {
+            width,
+            height,
+            body: String::new()
+        };
+    }
+
+    // Complete SVG document of everything drawn so far
+    pub fn finish(&self) -> String {
+        return format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n{body}</svg>\n",
+            w = self.width,
+            h = self.height,
+            body = self.body
+        );
+    }
+}
+
+impl Renderer for SvgRenderer {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+    }
+
+    fn clear(&mut self, color: &Color) {
+        // Nothing drawn before a clear can show through it
+        self.body.clear();
+        let _ = writeln!(self.body, "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>", color.to_hex());
+    }
+
+    fn draw_outline(&mut self, shape: &Shape, camera: &Camera, bg: &Color) {
+        let center: Point = geometry::to_pixels(camera, WorldPoint::new(0.0, 0.0));
+        let r: f32 = geometry::length_to_pixels(camera, camera.scale() as f32);
+        let w: f32 = geometry::length_to_pixels(camera, shape.outline_width);
+        let _ = writeln!(
+            self.body,
+            "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"{}\" stroke=\"{}\" stroke-width=\"{:.3}\"/>",
+            center.x, center.y, r, bg.to_hex(), shape.color.to_hex(), w
+        );
+    }
+
+    fn draw_chords(&mut self, shape: &Shape, camera: &Camera) {
+        let width: f32 = if (shape.use_rects_instead_of_lines) {
+            geometry::length_to_pixels(camera, shape.rect_width)
+        } else {
+            1.0
+        };
+
+        // A single path keeps large figures reasonably small
+        let mut d: String = String::new();
+        for i in 0..shape.points {
+            let (src, dst): (WorldPoint, WorldPoint) = geometry::chord(shape, i);
+            let a: Point = geometry::to_pixels(camera, src);
+            let b: Point = geometry::to_pixels(camera, dst);
+            let _ = write!(d, "M{:.2} {:.2}L{:.2} {:.2}", a.x, a.y, b.x, b.y);
+        }
+        let _ = writeln!(
+            self.body,
+            "<path d=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{:.3}\"/>",
+            d, shape.color.to_hex(), width
+        );
+    }
+
+    fn draw_points(&mut self, shape: &Shape, camera: &Camera) {
+        let s: f32 = shape.point_size;
+        let mut d: String = String::new();
+        for i in 0..shape.points {
+            let p: Point = geometry::to_pixels(camera, geometry::point_position(shape, i));
+            let _ = write!(d, "M{:.2} {:.2}h{s}v{s}h-{s}z", p.x - s / 2.0, p.y - s / 2.0, s = s);
+        }
+        let _ = writeln!(self.body, "<path d=\"{}\" fill=\"{}\"/>", d, shape.color.to_hex());
+    }
+
+    fn draw_text(&mut self, text: &str, x: f32, y: f32, size: f32, color: &Color) {
+        let escaped: String = text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+        let _ = writeln!(
+            self.body,
+            "<text x=\"{:.2}\" y=\"{:.2}\" font-family=\"Noto Sans, sans-serif\" font-size=\"{}\" dominant-baseline=\"hanging\" fill=\"{}\">{}</text>",
+            x, y, size, color.to_hex(), escaped
+        );
+    }
+}
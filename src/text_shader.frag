@@ -0,0 +1,37 @@
+#version 300 es
+precision mediump float;
+uniform vec3 u_color;
+uniform sampler2D u_texture;
+#ifdef WEBGL1
+varying vec2 v_uv;
+#else
+in vec2 v_uv;
+out vec4 outColor;
+#endif
+
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular 
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+void main() {
+    // Glyphs are rasterized white, so alpha alone carries the coverage
+#ifdef WEBGL1
+    float alpha = texture2D(u_texture, v_uv).a;
+    gl_FragColor = vec4(u_color, alpha);
+#else
+    float alpha = texture(u_texture, v_uv).a;
+    outColor = vec4(u_color, alpha);
+#endif
+}
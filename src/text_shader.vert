@@ -0,0 +1,46 @@
+#version 300 es
+precision highp float;
+
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular 
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+#ifdef WEBGL1
+attribute float a_corner; // 0 to 3, WebGL1 has no gl_VertexID
+#endif
+uniform vec4 u_rect; // x, y, width, height in pixels from the top left corner
+uniform vec2 u_dimensions;
+
+#ifdef WEBGL1
+varying vec2 v_uv;
+#else
+out vec2 v_uv;
+#endif
+
+void main() {
+    // Triangle strip: top left, top right, bottom left, bottom right
+#ifdef WEBGL1
+    vec2 corner = vec2(mod(a_corner, 2.0), floor(a_corner / 2.0));
+#else
+    vec2 corner = vec2(float(gl_VertexID % 2), float(gl_VertexID / 2));
+#endif
+    v_uv = corner;
+
+    vec2 px = u_rect.xy + corner * u_rect.zw;
+    float x = px.x / u_dimensions.x * 2.0 - 1.0;
+    float y = 1.0 - px.y / u_dimensions.y * 2.0;
+
+    gl_Position = vec4(x, y, 0.0, 1.0);
+}
@@ -0,0 +1,141 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Animated change from one scene to another. Continuous parameters are
+// interpolated, the multiplier sweeps through fractional values so the
+// figure morphs, and discrete switches (points, line mode, outline) happen
+// right away.
+
+use crate::camera::WorldPoint;
+use crate::scene::Scene;
+
+// Struct declarations
+
+pub struct Transition {
+    from: Scene,
+    to: Scene,
+    start_ms: Option<f64>, // set by the first frame
+    duration_ms: f64
+}
+
+// Struct implementations
+
+impl Transition {
+    pub fn new(from: Scene, to: Scene, duration_ms: f64) -> Transition {
+        return Transition {
+            from,
+            to,
+            start_ms: None,
+            duration_ms
+        };
+    }
+
+    pub fn target(&self) -> &Scene {
+        return &self.to;
+    }
+
+    // Scene to show at `now_ms`, and whether the transition has finished
+    pub fn sample(&mut self, now_ms: f64) -> (Scene, bool) {
+        let start: f64 = *self.start_ms.get_or_insert(now_ms);
+        let t: f64 = if (self.duration_ms > 0.0) {
+            ((now_ms - start) / self.duration_ms).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        if (t >= 1.0) {
+            return (self.to.clone(), true);
+        }
+        return (interpolate(&self.from, &self.to, ease_in_out(t as f32)), false);
+    }
+}
+
+// Helper functions
+
+pub fn interpolate(from: &Scene, to: &Scene, t: f32) -> Scene {
+    let mut out: Scene = to.clone();
+
+    // Multipliers that are the same mod n draw the same figure, so sweep
+    // the shortest way around between them
+    let points: f64 = to.shape.points as f64;
+    if (points > 0.0) {
+        let from_mul: f64 = (from.shape.mul as f64).rem_euclid(points);
+        let mut delta: f64 = (to.shape.mul as f64).rem_euclid(points) - from_mul;
+        if (delta > points / 2.0) {
+            delta -= points;
+        } else if (delta < -points / 2.0) {
+            delta += points;
+        }
+        let mul: f64 = (from_mul + delta * t as f64).rem_euclid(points);
+        out.shape.mul = mul.floor() as u32;
+        out.shape.mul_frac = mul.fract() as f32;
+    }
+
+    out.shape.rotation = lerp(from.shape.rotation, to.shape.rotation, t);
+    let t64: f64 = t as f64;
+    out.camera.center = WorldPoint::new(lerp_f64(from.camera.center.x, to.camera.center.x, t64), lerp_f64(from.camera.center.y, to.camera.center.y, t64));
+    // Geometric, so zooming by 10x takes as long as zooming back
+    out.camera.zoom = lerp_f64(from.camera.zoom.ln(), to.camera.zoom.ln(), t64).exp();
+    out.camera.rotation = lerp_f64(from.camera.rotation, to.camera.rotation, t64);
+    out.shape.rect_width = lerp(from.shape.rect_width, to.shape.rect_width, t);
+    out.shape.outline_width = lerp(from.shape.outline_width, to.shape.outline_width, t);
+    out.shape.point_size = lerp(from.shape.point_size, to.shape.point_size, t);
+    out.shape.color = from.shape.color.mix(&to.shape.color, t);
+    out.bg = from.bg.mix(&to.bg, t);
+    return out;
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    return a + (b - a) * t;
+}
+
+fn lerp_f64(a: f64, b: f64, t: f64) -> f64 {
+    return a + (b - a) * t;
+}
+
+// Cubic ease-in-out
+fn ease_in_out(t: f32) -> f32 {
+    if (t < 0.5) {
+        return 4.0 * t * t * t;
+    } else {
+        let u: f32 = -2.0 * t + 2.0;
+        return 1.0 - u * u * u / 2.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_multiplier(points: u32, mul: u32) -> Scene {
+        let mut scene: Scene = Scene::new(800, 600);
+        scene.set_points(points);
+        scene.set_multiplier(mul);
+        return scene;
+    }
+
+    #[test]
+    fn multiplier_takes_the_shortest_way_around() {
+        // 502 draws what 2 draws at 500 points, nothing to sweep through
+        let frame: Scene = interpolate(&with_multiplier(500, 2), &with_multiplier(500, 502), 0.5);
+        assert_eq!((frame.shape.mul, frame.shape.mul_frac), (2, 0.0));
+
+        // 498 is 4 back from 2, not 496 ahead
+        let frame: Scene = interpolate(&with_multiplier(500, 2), &with_multiplier(500, 498), 0.25);
+        assert_eq!((frame.shape.mul, frame.shape.mul_frac), (1, 0.0));
+        let frame: Scene = interpolate(&with_multiplier(500, 2), &with_multiplier(500, 498), 0.75);
+        assert_eq!((frame.shape.mul, frame.shape.mul_frac), (499, 0.0));
+    }
+}
@@ -0,0 +1,33 @@
+#version 300 es
+precision highp float;
+
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular 
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Geometry computed on the CPU for deep zoom, already relative to the camera
+
+#ifdef WEBGL1
+attribute vec2 a_position; // view coordinates, see camera.rs
+#else
+in vec2 a_position; // view coordinates, see camera.rs
+#endif
+uniform vec2 u_norm; // view to clip scale
+uniform float u_point_size;
+
+void main() {
+    gl_Position = vec4(a_position * u_norm, 0.0, 1.0);
+    gl_PointSize = u_point_size;
+}
@@ -0,0 +1,551 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Renderer for browsers that only offer WebGL1, see backend.rs for when it
+// is used. It draws exactly what `WebGlRenderer` draws, with the same
+// shader files expanded with the WEBGL1 switch, which makes them GLSL 100.
+//
+// GLSL 100 has no gl_VertexID, so every index the shaders need comes from a
+// buffer instead. `indices` holds 0.0, 1.0, 2.0 and so on, one per point or
+// outline vertex. Lines and rectangles are drawn instanced through
+// ANGLE_instanced_arrays, one instance per chord reading its index from the
+// same buffer, with the corner within the chord from `corners`. Buffers so
+// stay at four bytes per point, whatever the figure.
+//
+// Labels are rasterized on an OffscreenCanvas, browsers without one get no
+// labels.
+
+use std::rc::Rc;
+
+use wasm_bindgen::JsCast;
+use web_sys::{AngleInstancedArrays, OffscreenCanvas, WebGlBuffer, WebGlProgram, WebGlRenderingContext, WebGlShader, WebGlTexture, WebGlUniformLocation};
+
+use crate::camera::Camera;
+use crate::error::ModmulError;
+use crate::glsl::{ShaderSource, shader_source};
+use crate::renderer::{Dirty, Renderer};
+use crate::scene::{Color, Point, Shape};
+use crate::webgl_renderer::{deep_chord_vertices, deep_outline_vertices, deep_point_vertices, rasterize_text, shader_multiplier};
+
+type Gl = WebGlRenderingContext;
+
+// Struct declarations
+
+// A linked program and where its uniforms and attributes are, looked up once
+struct Program {
+    program: WebGlProgram,
+    uniforms: Vec<(&'static str, WebGlUniformLocation)>,
+    attributes: Vec<(&'static str, u32)>
+}
+
+// Vertices in view coordinates for the deep zoom program, kept on the GPU
+// until the view or the figure changes
+struct ViewVertices {
+    buffer: WebGlBuffer,
+    count: i32
+}
+
+pub struct WebGl1Renderer {
+    context: Gl,
+    instancing: AngleInstancedArrays,
+    point_program: Program,
+    line_program: Program,
+    outline_program: Program,
+    rect_program: Program,
+    text_program: Program,
+    view_program: Program, // draws the `ViewVertices` at deep zoom
+    indices: WebGlBuffer, // 0.0, 1.0, 2.0, ... in place of gl_VertexID
+    index_count: u32, // how many `indices` holds
+    corners: WebGlBuffer, // 0.0 to 5.0, the vertex within a line, rectangle or label
+    deep_outline: ViewVertices,
+    deep_chords: ViewVertices,
+    deep_points: ViewVertices,
+    overlay: ViewVertices, // minimap frame and the like, uploaded on every draw
+    point_stale: Dirty,
+    line_stale: Dirty,
+    rect_stale: Dirty,
+    outline_stale: Dirty,
+    deep_stale: Dirty,
+    text_canvas: Option<OffscreenCanvas>,
+    text_texture: WebGlTexture,
+    width: u32,
+    height: u32
+}
+
+// Struct implementations
+
+impl WebGl1Renderer {
+    pub fn new(gl: Gl) -> Result<WebGl1Renderer, ModmulError> {
+        let instancing: AngleInstancedArrays = gl.get_extension("ANGLE_instanced_arrays").ok().flatten()
+            .and_then(|extension: js_sys::Object| extension.dyn_into::<AngleInstancedArrays>().ok())
+            .ok_or(ModmulError::ExtensionUnsupported("ANGLE_instanced_arrays"))?;
+
+        // Compile shaders
+        let point_shader: WebGlShader = compile_shader(&gl, "point_shader.vert", Gl::VERTEX_SHADER)?;
+        let line_shader: WebGlShader = compile_shader(&gl, "line_shader.vert", Gl::VERTEX_SHADER)?;
+        let outline_shader: WebGlShader = compile_shader(&gl, "outline_shader.vert", Gl::VERTEX_SHADER)?;
+        let rect_shader: WebGlShader = compile_shader(&gl, "rect_shader.vert", Gl::VERTEX_SHADER)?;
+        let text_vert: WebGlShader = compile_shader(&gl, "text_shader.vert", Gl::VERTEX_SHADER)?;
+        let text_frag: WebGlShader = compile_shader(&gl, "text_shader.frag", Gl::FRAGMENT_SHADER)?;
+        let view_shader: WebGlShader = compile_shader(&gl, "view_shader.vert", Gl::VERTEX_SHADER)?;
+        let color_shader: WebGlShader = compile_shader(&gl, "color_shader.frag", Gl::FRAGMENT_SHADER)?;
+
+        // Link WebGL programs, with every uniform and attribute the drawing code sets
+        let point_program: Program = Program::new(&gl, "point", &point_shader, &color_shader,
+            &["u_color", "u_points", "u_rotation", "u_point_size", "u_view"], &["a_index"])?;
+        let line_program: Program = Program::new(&gl, "line", &line_shader, &color_shader,
+            &["u_color", "u_points", "u_rotation", "u_multiplier", "u_multiplier_frac", "u_view"], &["a_chord", "a_end"])?;
+        let outline_program: Program = Program::new(&gl, "outline", &outline_shader, &color_shader,
+            &["u_color", "u_segments", "u_radius", "u_view"], &["a_index"])?;
+        let rect_program: Program = Program::new(&gl, "rect", &rect_shader, &color_shader,
+            &["u_color", "u_points", "u_rotation", "u_multiplier", "u_multiplier_frac", "u_rectw", "u_view", "u_dimensions"], &["a_chord", "a_corner"])?;
+        let text_program: Program = Program::new(&gl, "text", &text_vert, &text_frag,
+            &["u_color", "u_texture", "u_rect", "u_dimensions"], &["a_corner"])?;
+        let view_program: Program = Program::new(&gl, "view", &view_shader, &color_shader,
+            &["u_color", "u_norm", "u_point_size"], &["a_position"])?;
+
+        let indices: WebGlBuffer = gl.create_buffer().ok_or(ModmulError::CreateFailed("buffer"))?;
+        let corners: WebGlBuffer = gl.create_buffer().ok_or(ModmulError::CreateFailed("buffer"))?;
+        upload_floats(&gl, &corners, &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0], Gl::STATIC_DRAW);
+        let deep_outline: ViewVertices = ViewVertices::new(&gl)?;
+        let deep_chords: ViewVertices = ViewVertices::new(&gl)?;
+        let deep_points: ViewVertices = ViewVertices::new(&gl)?;
+        let overlay: ViewVertices = ViewVertices::new(&gl)?;
+
+        // Labels are rasterized by the browser on a 2D canvas and uploaded as a texture
+        let text_canvas: Option<OffscreenCanvas> = OffscreenCanvas::new(1, 1).ok();
+        let text_texture: WebGlTexture = gl.create_texture().ok_or(ModmulError::CreateFailed("texture"))?;
+        gl.bind_texture(Gl::TEXTURE_2D, Some(&text_texture));
+        gl.tex_parameteri(Gl::TEXTURE_2D, Gl::TEXTURE_MIN_FILTER, Gl::LINEAR as i32);
+        gl.tex_parameteri(Gl::TEXTURE_2D, Gl::TEXTURE_WRAP_S, Gl::CLAMP_TO_EDGE as i32);
+        gl.tex_parameteri(Gl::TEXTURE_2D, Gl::TEXTURE_WRAP_T, Gl::CLAMP_TO_EDGE as i32);
+
+        return Ok(WebGl1Renderer {
+            context: gl,
+            instancing,
+            point_program,
+            line_program,
+            outline_program,
+            rect_program,
+            text_program,
+            view_program,
+            indices,
+            index_count: 0,
+            corners,
+            deep_outline,
+            deep_chords,
+            deep_points,
+            overlay,
+            point_stale: Dirty::all(),
+            line_stale: Dirty::all(),
+            rect_stale: Dirty::all(),
+            outline_stale: Dirty::all(),
+            deep_stale: Dirty::all(),
+            text_canvas,
+            text_texture,
+            width: 0,
+            height: 0
+        });
+    }
+
+    // Whether `draw_text` draws anything
+    pub fn has_labels(&self) -> bool {
+        return self.text_canvas.is_some();
+    }
+
+    ///////////////////////
+    // Private functions //
+    ///////////////////////
+
+    // Uniforms set in another viewport were for another camera, see
+    // `WebGlRenderer::forget_view`
+    fn forget_view(&mut self) {
+        for stale in [&mut self.point_stale, &mut self.line_stale, &mut self.rect_stale, &mut self.outline_stale] {
+            stale.merge(Dirty {
+                view: true,
+                surface: true,
+                ..Dirty::default()
+            });
+        }
+    }
+
+    // Make `indices` hold at least `count` of them. It grows by doubling so
+    // that stepping through point counts doesn't upload on every step.
+    fn reserve_indices(&mut self, count: u32) {
+        if (count <= self.index_count) {
+            return;
+        }
+        let capacity: u32 = count.max(self.index_count.saturating_mul(2));
+        let indices: Vec<f32> = (0..capacity).map(|i: u32| i as f32).collect();
+        upload_floats(&self.context, &self.indices, &indices, Gl::STATIC_DRAW);
+        self.index_count = capacity;
+    }
+
+    // Feed `attribute` of `program` one float per vertex from `buffer`, or
+    // one per instance with `divisor` 1
+    fn bind_floats(&self, program: &Program, attribute: &str, buffer: &WebGlBuffer, divisor: u32) {
+        let Some(location) = program.attribute(attribute) else {
+            return;
+        };
+        self.context.bind_buffer(Gl::ARRAY_BUFFER, Some(buffer));
+        self.context.enable_vertex_attrib_array(location);
+        self.context.vertex_attrib_pointer_with_i32(location, 1, Gl::FLOAT, false, 0, 0);
+        self.instancing.vertex_attrib_divisor_angle(location, divisor);
+    }
+
+    // Undo `bind_floats` for every attribute of `program`. Locations are
+    // shared between programs, and a divisor left behind would turn the
+    // next program's vertices into instances.
+    fn release_attributes(&self, program: &Program) {
+        for (_, location) in &program.attributes {
+            self.instancing.vertex_attrib_divisor_angle(*location, 0);
+            self.context.disable_vertex_attrib_array(*location);
+        }
+        self.context.bind_buffer(Gl::ARRAY_BUFFER, None);
+    }
+
+    // Draw `vertices` with the deep zoom program
+    fn draw_view_vertices(&self, mode: u32, vertices: &ViewVertices, camera: &Camera, color: &Color, point_size: f32) {
+        if (vertices.count == 0) {
+            return;
+        }
+        let Some(location) = self.view_program.attribute("a_position") else {
+            return;
+        };
+        let (red, green, blue): (f32, f32, f32) = color.to_f32();
+        let (x_norm, y_norm): (f32, f32) = camera.normalization();
+        self.context.use_program(Some(&self.view_program.program));
+        self.context.uniform3f(self.view_program.uniform("u_color"), red, green, blue);
+        self.context.uniform2f(self.view_program.uniform("u_norm"), x_norm, y_norm);
+        self.context.uniform1f(self.view_program.uniform("u_point_size"), point_size);
+
+        self.context.bind_buffer(Gl::ARRAY_BUFFER, Some(&vertices.buffer));
+        self.context.enable_vertex_attrib_array(location);
+        self.context.vertex_attrib_pointer_with_i32(location, 2, Gl::FLOAT, false, 0, 0);
+        self.context.draw_arrays(mode, 0, vertices.count);
+        self.release_attributes(&self.view_program);
+    }
+
+    fn draw_deep_chords(&mut self, shape: &Shape, camera: &Camera) {
+        if (self.deep_stale.buffers) {
+            self.deep_chords.upload(&self.context, &deep_chord_vertices(shape, camera));
+        }
+        let mode: u32 = if (shape.use_rects_instead_of_lines) { Gl::TRIANGLES } else { Gl::LINES };
+        self.draw_view_vertices(mode, &self.deep_chords, camera, &shape.color, 1.0);
+    }
+
+    fn draw_lines(&mut self, shape: &Shape, camera: &Camera) {
+        self.reserve_indices(shape.points);
+        self.context.use_program(Some(&self.line_program.program));
+        let stale: Dirty = std::mem::take(&mut self.line_stale);
+        if (stale.colors) {
+            let (red, green, blue): (f32, f32, f32) = shape.color.to_f32();
+            self.context.uniform3f(self.line_program.uniform("u_color"), red, green, blue);
+        }
+        if (stale.figure) {
+            self.context.uniform1f(self.line_program.uniform("u_points"), shape.points as f32);
+            self.context.uniform1f(self.line_program.uniform("u_rotation"), shape.rotation);
+            self.context.uniform1f(self.line_program.uniform("u_multiplier"), shader_multiplier(shape) as f32);
+            self.context.uniform1f(self.line_program.uniform("u_multiplier_frac"), shape.mul_frac);
+        }
+        if (stale.view) {
+            self.context.uniform_matrix3fv_with_f32_array(self.line_program.uniform("u_view"), false, &camera.view_matrix());
+        }
+        self.bind_floats(&self.line_program, "a_chord", &self.indices, 1);
+        self.bind_floats(&self.line_program, "a_end", &self.corners, 0);
+        self.instancing.draw_arrays_instanced_angle(Gl::LINES, 0, 2, shape.points as i32);
+        self.release_attributes(&self.line_program);
+    }
+
+    fn draw_rects(&mut self, shape: &Shape, camera: &Camera) {
+        self.reserve_indices(shape.points);
+        self.context.use_program(Some(&self.rect_program.program));
+        let stale: Dirty = std::mem::take(&mut self.rect_stale);
+        if (stale.colors) {
+            let (red, green, blue): (f32, f32, f32) = shape.color.to_f32();
+            self.context.uniform3f(self.rect_program.uniform("u_color"), red, green, blue);
+        }
+        if (stale.figure) {
+            self.context.uniform1f(self.rect_program.uniform("u_points"), shape.points as f32);
+            self.context.uniform1f(self.rect_program.uniform("u_rotation"), shape.rotation);
+            self.context.uniform1f(self.rect_program.uniform("u_multiplier"), shader_multiplier(shape) as f32);
+            self.context.uniform1f(self.rect_program.uniform("u_multiplier_frac"), shape.mul_frac);
+            self.context.uniform1f(self.rect_program.uniform("u_rectw"), shape.rect_width);
+        }
+        if (stale.view) {
+            self.context.uniform_matrix3fv_with_f32_array(self.rect_program.uniform("u_view"), false, &camera.view_matrix());
+        }
+        if (stale.surface) {
+            self.context.uniform2f(self.rect_program.uniform("u_dimensions"), camera.dimensions.x, camera.dimensions.y);
+        }
+        self.bind_floats(&self.rect_program, "a_chord", &self.indices, 1);
+        self.bind_floats(&self.rect_program, "a_corner", &self.corners, 0);
+        self.instancing.draw_arrays_instanced_angle(Gl::TRIANGLES, 0, 6, shape.points as i32);
+        self.release_attributes(&self.rect_program);
+    }
+}
+
+impl Program {
+    fn new(gl: &Gl, name: &'static str, vertex: &WebGlShader, fragment: &WebGlShader, uniforms: &[&'static str], attributes: &[&'static str]) -> Result<Program, ModmulError> {
+        let program: WebGlProgram = link_program(gl, name, vertex, fragment)?;
+        let mut uniform_locations: Vec<(&'static str, WebGlUniformLocation)> = Vec::new();
+        for uniform in uniforms {
+            let location: WebGlUniformLocation = gl.get_uniform_location(&program, uniform)
+                .ok_or(ModmulError::MissingUniform {program: name, uniform})?;
+            uniform_locations.push((uniform, location));
+        }
+        let mut attribute_locations: Vec<(&'static str, u32)> = Vec::new();
+        for attribute in attributes {
+            let location: i32 = gl.get_attrib_location(&program, attribute);
+            if (location < 0) {
+                return Err(ModmulError::MissingAttribute {program: name, attribute});
+            }
+            attribute_locations.push((attribute, location as u32));
+        }
+        return Ok(Program {
+            program,
+            uniforms: uniform_locations,
+            attributes: attribute_locations
+        });
+    }
+
+    // Location of `name`, which `new` made sure exists
+    fn uniform(&self, name: &str) -> Option<&WebGlUniformLocation> {
+        return self.uniforms.iter().find(|(n, _)| *n == name).map(|(_, location)| location);
+    }
+
+    fn attribute(&self, name: &str) -> Option<u32> {
+        return self.attributes.iter().find(|(n, _)| *n == name).map(|(_, location)| *location);
+    }
+}
+
+impl ViewVertices {
+    fn new(gl: &Gl) -> Result<ViewVertices, ModmulError> {
+        return Ok(ViewVertices {
+            buffer: gl.create_buffer().ok_or(ModmulError::CreateFailed("buffer"))?,
+            count: 0
+        });
+    }
+
+    // Replace the contents with `vertices`, pairs of view coordinates
+    fn upload(&mut self, gl: &Gl, vertices: &[f32]) {
+        upload_floats(gl, &self.buffer, vertices, Gl::DYNAMIC_DRAW);
+        self.count = (vertices.len() / 2) as i32;
+    }
+}
+
+impl Renderer for WebGl1Renderer {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.context.viewport(0, 0, width as i32, height as i32);
+    }
+
+    fn invalidate(&mut self, dirty: Dirty) {
+        for stale in [&mut self.point_stale, &mut self.line_stale, &mut self.rect_stale, &mut self.outline_stale, &mut self.deep_stale] {
+            stale.merge(dirty);
+        }
+    }
+
+    fn set_viewport(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        // GL counts rows from the bottom
+        let bottom: i32 = self.height as i32 - (y + height) as i32;
+        self.context.viewport(x as i32, bottom, width as i32, height as i32);
+        self.context.scissor(x as i32, bottom, width as i32, height as i32);
+        self.context.enable(Gl::SCISSOR_TEST);
+        self.forget_view();
+    }
+
+    fn reset_viewport(&mut self) {
+        self.context.viewport(0, 0, self.width as i32, self.height as i32);
+        self.context.disable(Gl::SCISSOR_TEST);
+        self.forget_view();
+    }
+
+    fn clear(&mut self, color: &Color) {
+        let (r, g, b): (f32, f32, f32) = color.to_f32();
+        self.context.clear_color(r, g, b, 1.0);
+        self.context.clear(Gl::COLOR_BUFFER_BIT);
+    }
+
+    fn draw_outline(&mut self, shape: &Shape, camera: &Camera, bg: &Color) {
+        if (camera.is_deep()) {
+            if (self.deep_stale.buffers) {
+                self.deep_outline.upload(&self.context, &deep_outline_vertices(shape, camera));
+            }
+            self.draw_view_vertices(Gl::TRIANGLE_STRIP, &self.deep_outline, camera, &shape.color, 1.0);
+            return;
+        }
+
+        self.reserve_indices(shape.outline_segments as u32 + 2);
+        self.context.use_program(Some(&self.outline_program.program));
+        let stale: Dirty = std::mem::take(&mut self.outline_stale);
+
+        // Both passes set their own color and radius
+        let (fg_red, fg_green, fg_blue): (f32, f32, f32) = shape.color.to_f32();
+        self.context.uniform3f(self.outline_program.uniform("u_color"), fg_red, fg_green, fg_blue);
+
+        if (stale.figure) {
+            self.context.uniform1f(self.outline_program.uniform("u_segments"), shape.outline_segments as f32);
+        }
+        if (stale.view) {
+            self.context.uniform_matrix3fv_with_f32_array(self.outline_program.uniform("u_view"), false, &camera.view_matrix());
+        }
+        self.bind_floats(&self.outline_program, "a_index", &self.indices, 0);
+
+        // The ring width is in view units, convert it to world units
+        let half_width: f32 = shape.outline_width / 2.0 / camera.scale() as f32;
+        self.context.uniform1f(self.outline_program.uniform("u_radius"), 1.0 + half_width);
+        self.context.draw_arrays(Gl::TRIANGLE_FAN, 0, shape.outline_segments + 2);
+
+        // Punch out the inside with the background color to leave a ring
+        let (bg_red, bg_green, bg_blue): (f32, f32, f32) = bg.to_f32();
+        self.context.uniform3f(self.outline_program.uniform("u_color"), bg_red, bg_green, bg_blue);
+
+        self.context.uniform1f(self.outline_program.uniform("u_radius"), 1.0 - half_width);
+        self.context.draw_arrays(Gl::TRIANGLE_FAN, 0, shape.outline_segments + 2);
+        self.release_attributes(&self.outline_program);
+    }
+
+    fn draw_chords(&mut self, shape: &Shape, camera: &Camera) {
+        if (camera.is_deep()) {
+            self.draw_deep_chords(shape, camera);
+        } else if (shape.use_rects_instead_of_lines) {
+            self.draw_rects(shape, camera);
+        } else {
+            self.draw_lines(shape, camera);
+        }
+    }
+
+    fn draw_points(&mut self, shape: &Shape, camera: &Camera) {
+        if (camera.is_deep()) {
+            if (self.deep_stale.buffers) {
+                self.deep_points.upload(&self.context, &deep_point_vertices(shape, camera));
+            }
+            self.draw_view_vertices(Gl::POINTS, &self.deep_points, camera, &shape.color, shape.point_size);
+
+            // Points are drawn last, all deep buffers are current now
+            self.deep_stale = Dirty::default();
+            return;
+        }
+
+        self.reserve_indices(shape.points);
+        self.context.use_program(Some(&self.point_program.program));
+        let stale: Dirty = std::mem::take(&mut self.point_stale);
+        if (stale.colors) {
+            let (red, green, blue): (f32, f32, f32) = shape.color.to_f32();
+            self.context.uniform3f(self.point_program.uniform("u_color"), red, green, blue);
+        }
+        if (stale.figure) {
+            self.context.uniform1f(self.point_program.uniform("u_points"), shape.points as f32);
+            self.context.uniform1f(self.point_program.uniform("u_rotation"), shape.rotation);
+            self.context.uniform1f(self.point_program.uniform("u_point_size"), shape.point_size);
+        }
+        if (stale.view) {
+            self.context.uniform_matrix3fv_with_f32_array(self.point_program.uniform("u_view"), false, &camera.view_matrix());
+        }
+        self.bind_floats(&self.point_program, "a_index", &self.indices, 0);
+        self.context.draw_arrays(Gl::POINTS, 0, shape.points as i32);
+        self.release_attributes(&self.point_program);
+    }
+
+    fn draw_polygon(&mut self, points: &[Point], camera: &Camera, color: &Color) {
+        let vertices: Vec<f32> = points.iter().flat_map(|p: &Point| [p.x, p.y]).collect();
+        self.overlay.upload(&self.context, &vertices);
+        self.draw_view_vertices(Gl::LINE_LOOP, &self.overlay, camera, color, 1.0);
+    }
+
+    fn draw_segments(&mut self, segments: &[(Point, Point)], camera: &Camera, color: &Color) {
+        let vertices: Vec<f32> = segments.iter().flat_map(|(a, b): &(Point, Point)| [a.x, a.y, b.x, b.y]).collect();
+        self.overlay.upload(&self.context, &vertices);
+        self.draw_view_vertices(Gl::LINES, &self.overlay, camera, color, 1.0);
+    }
+
+    fn draw_text(&mut self, text: &str, x: f32, y: f32, size: f32, color: &Color) {
+        if (text.is_empty() || self.width == 0 || self.height == 0) {
+            return;
+        }
+        let Some(text_canvas) = &self.text_canvas else {
+            return;
+        };
+        let Some((bitmap, tex_w, tex_h)) = rasterize_text(text_canvas, text, size) else {
+            return;
+        };
+        self.context.active_texture(Gl::TEXTURE0);
+        self.context.bind_texture(Gl::TEXTURE_2D, Some(&self.text_texture));
+        let uploaded = self.context.tex_image_2d_with_u32_and_u32_and_image_bitmap(
+            Gl::TEXTURE_2D,
+            0,
+            Gl::RGBA as i32,
+            Gl::RGBA,
+            Gl::UNSIGNED_BYTE,
+            &bitmap
+        );
+        bitmap.close();
+        if (uploaded.is_err()) {
+            return;
+        }
+
+        let (red, green, blue): (f32, f32, f32) = color.to_f32();
+        self.context.use_program(Some(&self.text_program.program));
+        self.context.uniform3f(self.text_program.uniform("u_color"), red, green, blue);
+        self.context.uniform1i(self.text_program.uniform("u_texture"), 0);
+        self.context.uniform4f(self.text_program.uniform("u_rect"), x, y, tex_w as f32, tex_h as f32);
+        self.context.uniform2f(self.text_program.uniform("u_dimensions"), self.width as f32, self.height as f32);
+        self.bind_floats(&self.text_program, "a_corner", &self.corners, 0);
+
+        self.context.enable(Gl::BLEND);
+        self.context.blend_func(Gl::SRC_ALPHA, Gl::ONE_MINUS_SRC_ALPHA);
+        self.context.draw_arrays(Gl::TRIANGLE_STRIP, 0, 4);
+        self.context.disable(Gl::BLEND);
+        self.release_attributes(&self.text_program);
+    }
+}
+
+// Helper functions
+
+fn upload_floats(gl: &Gl, buffer: &WebGlBuffer, values: &[f32], usage: u32) {
+    gl.bind_buffer(Gl::ARRAY_BUFFER, Some(buffer));
+    gl.buffer_data_with_array_buffer_view(Gl::ARRAY_BUFFER, &js_sys::Float32Array::from(values), usage);
+    gl.bind_buffer(Gl::ARRAY_BUFFER, None);
+}
+
+// `name` is the file to expand with the WEBGL1 switch, see glsl.rs
+fn compile_shader(gl: &Gl, name: &'static str, shader_type: u32) -> Result<WebGlShader, ModmulError> {
+    let source: Rc<ShaderSource> = shader_source(name, &["WEBGL1"])?;
+    let shader: WebGlShader = gl.create_shader(shader_type).ok_or(ModmulError::CreateFailed("shader"))?;
+    gl.shader_source(&shader, &source.text);
+    gl.compile_shader(&shader);
+
+    if (gl.get_shader_parameter(&shader, Gl::COMPILE_STATUS).as_bool().unwrap_or(false)) {
+        return Ok(shader);
+    } else {
+        return Err(ModmulError::ShaderCompile {shader: name, log: source.map_log(&gl.get_shader_info_log(&shader).unwrap_or_default())});
+    }
+}
+
+fn link_program(gl: &Gl, name: &'static str, vertex_shader: &WebGlShader, fragment_shader: &WebGlShader) -> Result<WebGlProgram, ModmulError> {
+    let program: WebGlProgram = gl.create_program().ok_or(ModmulError::CreateFailed("program"))?;
+    gl.attach_shader(&program, vertex_shader);
+    gl.attach_shader(&program, fragment_shader);
+    gl.link_program(&program);
+
+    if (gl.get_program_parameter(&program, Gl::LINK_STATUS).as_bool().unwrap_or(false)) {
+        return Ok(program);
+    } else {
+        return Err(ModmulError::Link {program: name, log: gl.get_program_info_log(&program).unwrap_or_default()});
+    }
+}
@@ -0,0 +1,560 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::rc::Rc;
+
+use wasm_bindgen::JsCast;
+use web_sys::{ImageBitmap, OffscreenCanvas, OffscreenCanvasRenderingContext2d, WebGl2RenderingContext, WebGlBuffer, WebGlShader, WebGlProgram, WebGlTexture, WebGlUniformLocation};
+
+use crate::camera::Camera;
+use crate::error::ModmulError;
+use crate::geometry;
+use crate::glsl::{ShaderSource, shader_source};
+use crate::renderer::{Dirty, Renderer};
+use crate::scene::{Color, Point, Shape};
+
+// Pieces the visible part of the outline is split into at deep zoom
+const DEEP_OUTLINE_SEGMENTS: u32 = 256;
+
+// Struct declarations
+
+// A linked program and where its uniforms are, looked up once
+struct Program {
+    program: WebGlProgram,
+    uniforms: Vec<(&'static str, WebGlUniformLocation)>
+}
+
+// Vertices in view coordinates for the deep zoom program, kept on the GPU
+// until the view or the figure changes
+struct ViewVertices {
+    buffer: WebGlBuffer,
+    count: i32 // vertices, two floats each
+}
+
+pub struct WebGlRenderer {
+    context: WebGl2RenderingContext,
+    point_program: Program,
+    line_program: Program,
+    outline_program: Program,
+    rect_program: Program,
+    text_program: Program,
+    view_program: Program, // draws the `ViewVertices` at deep zoom
+    view_position: u32, // its a_position attribute
+    deep_outline: ViewVertices,
+    deep_chords: ViewVertices,
+    deep_points: ViewVertices,
+    overlay: ViewVertices, // lines from `draw_polygon` and `draw_segments`
+
+    // What changed since each program last drew. GL keeps uniform values
+    // with the program, so only these need uploading again.
+    point_stale: Dirty,
+    line_stale: Dirty,
+    rect_stale: Dirty,
+    outline_stale: Dirty,
+    deep_stale: Dirty,
+
+    text_canvas: OffscreenCanvas, // 2D canvas used to rasterize labels, there is no page in a worker
+    text_texture: WebGlTexture,
+    width: u32,
+    height: u32
+}
+
+// Struct implementations
+
+impl WebGlRenderer {
+    pub fn new(gl: WebGl2RenderingContext) -> Result<WebGlRenderer, ModmulError> {
+        // Compile point shader
+        let point_shader: WebGlShader = compile_shader(&gl, "point_shader.vert", WebGl2RenderingContext::VERTEX_SHADER)?;
+
+        // Compile line shader
+        let line_shader: WebGlShader = compile_shader(&gl, "line_shader.vert", WebGl2RenderingContext::VERTEX_SHADER)?;
+
+        // Compile outline shader
+        let outline_shader: WebGlShader = compile_shader(&gl, "outline_shader.vert", WebGl2RenderingContext::VERTEX_SHADER)?;
+
+        // Compile rectangle shader
+        let rect_shader: WebGlShader = compile_shader(&gl, "rect_shader.vert", WebGl2RenderingContext::VERTEX_SHADER)?;
+
+        // Compile text shaders
+        let text_vert: WebGlShader = compile_shader(&gl, "text_shader.vert", WebGl2RenderingContext::VERTEX_SHADER)?;
+        let text_frag: WebGlShader = compile_shader(&gl, "text_shader.frag", WebGl2RenderingContext::FRAGMENT_SHADER)?;
+
+        // Compile deep zoom shader
+        let view_shader: WebGlShader = compile_shader(&gl, "view_shader.vert", WebGl2RenderingContext::VERTEX_SHADER)?;
+
+        // Compile color shader
+        let color_shader: WebGlShader = compile_shader(&gl, "color_shader.frag", WebGl2RenderingContext::FRAGMENT_SHADER)?;
+
+        // Link WebGL programs, with every uniform the drawing code sets
+        let point_program: Program = Program::new(&gl, "point", &point_shader, &color_shader, &["u_color", "u_points", "u_rotation", "u_point_size", "u_view"])?;
+        let line_program: Program = Program::new(&gl, "line", &line_shader, &color_shader, &["u_color", "u_points", "u_rotation", "u_multiplier", "u_multiplier_frac", "u_view"])?;
+        let outline_program: Program = Program::new(&gl, "outline", &outline_shader, &color_shader, &["u_color", "u_segments", "u_radius", "u_view"])?;
+        let rect_program: Program = Program::new(&gl, "rect", &rect_shader, &color_shader, &["u_color", "u_points", "u_rotation", "u_multiplier", "u_multiplier_frac", "u_rectw", "u_view", "u_dimensions"])?;
+        let text_program: Program = Program::new(&gl, "text", &text_vert, &text_frag, &["u_color", "u_texture", "u_rect", "u_dimensions"])?;
+        let view_program: Program = Program::new(&gl, "view", &view_shader, &color_shader, &["u_color", "u_norm", "u_point_size"])?;
+        let view_position: i32 = gl.get_attrib_location(&view_program.program, "a_position");
+        if (view_position < 0) {
+            return Err(ModmulError::MissingAttribute {program: "view", attribute: "a_position"});
+        }
+        let deep_outline: ViewVertices = ViewVertices::new(&gl)?;
+        let deep_chords: ViewVertices = ViewVertices::new(&gl)?;
+        let deep_points: ViewVertices = ViewVertices::new(&gl)?;
+        let overlay: ViewVertices = ViewVertices::new(&gl)?;
+
+        // Labels are rasterized by the browser on a 2D canvas and uploaded as a texture
+        let text_canvas: OffscreenCanvas = OffscreenCanvas::new(1, 1).map_err(|_| ModmulError::CreateFailed("text canvas"))?;
+        let text_texture: WebGlTexture = gl.create_texture().ok_or(ModmulError::CreateFailed("texture"))?;
+        gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&text_texture));
+        gl.tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, WebGl2RenderingContext::TEXTURE_MIN_FILTER, WebGl2RenderingContext::LINEAR as i32);
+        gl.tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, WebGl2RenderingContext::TEXTURE_WRAP_S, WebGl2RenderingContext::CLAMP_TO_EDGE as i32);
+        gl.tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, WebGl2RenderingContext::TEXTURE_WRAP_T, WebGl2RenderingContext::CLAMP_TO_EDGE as i32);
+
+        return Ok(WebGlRenderer {
+            context: gl,
+            point_program,
+            line_program,
+            outline_program,
+            rect_program,
+            text_program,
+            view_program,
+            view_position: view_position as u32,
+            deep_outline,
+            deep_chords,
+            deep_points,
+            overlay,
+            point_stale: Dirty::all(),
+            line_stale: Dirty::all(),
+            rect_stale: Dirty::all(),
+            outline_stale: Dirty::all(),
+            deep_stale: Dirty::all(),
+            text_canvas,
+            text_texture,
+            width: 0,
+            height: 0
+        });
+    }
+
+    ///////////////////////
+    // Private functions //
+    ///////////////////////
+
+    // Uniforms set in another viewport were for another camera. Other
+    // differences come through `invalidate` as usual. The deep zoom program
+    // sets its own on every draw.
+    fn forget_view(&mut self) {
+        for stale in [&mut self.point_stale, &mut self.line_stale, &mut self.rect_stale, &mut self.outline_stale] {
+            stale.merge(Dirty {
+                view: true,
+                surface: true,
+                ..Dirty::default()
+            });
+        }
+    }
+
+    // Draw `vertices` with the deep zoom program
+    fn draw_view_vertices(&self, mode: u32, vertices: &ViewVertices, camera: &Camera, color: &Color, point_size: f32) {
+        if (vertices.count == 0) {
+            return;
+        }
+        let (red, green, blue): (f32, f32, f32) = color.to_f32();
+        let (x_norm, y_norm): (f32, f32) = camera.normalization();
+        self.context.use_program(Some(&self.view_program.program));
+        self.context.uniform3f(self.view_program.uniform("u_color"), red, green, blue);
+        self.context.uniform2f(self.view_program.uniform("u_norm"), x_norm, y_norm);
+        self.context.uniform1f(self.view_program.uniform("u_point_size"), point_size);
+
+        self.context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&vertices.buffer));
+        let location: u32 = self.view_position;
+        self.context.enable_vertex_attrib_array(location);
+        self.context.vertex_attrib_pointer_with_i32(location, 2, WebGl2RenderingContext::FLOAT, false, 0, 0);
+        self.context.draw_arrays(mode, 0, vertices.count);
+
+        // The other programs draw from gl_VertexID alone
+        self.context.disable_vertex_attrib_array(location);
+        self.context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, None);
+    }
+
+    fn draw_deep_chords(&mut self, shape: &Shape, camera: &Camera) {
+        if (self.deep_stale.buffers) {
+            self.deep_chords.upload(&self.context, &deep_chord_vertices(shape, camera));
+        }
+        let mode: u32 = if (shape.use_rects_instead_of_lines) { WebGl2RenderingContext::TRIANGLES } else { WebGl2RenderingContext::LINES };
+        self.draw_view_vertices(mode, &self.deep_chords, camera, &shape.color, 1.0);
+    }
+
+    fn draw_lines(&mut self, shape: &Shape, camera: &Camera) {
+        self.context.use_program(Some(&self.line_program.program));
+        let stale: Dirty = std::mem::take(&mut self.line_stale);
+        if (stale.colors) {
+            let (red, green, blue): (f32, f32, f32) = shape.color.to_f32();
+            self.context.uniform3f(self.line_program.uniform("u_color"), red, green, blue);
+        }
+        if (stale.figure) {
+            self.context.uniform1i(self.line_program.uniform("u_points"), shape.points as i32);
+            self.context.uniform1f(self.line_program.uniform("u_rotation"), shape.rotation);
+            self.context.uniform1i(self.line_program.uniform("u_multiplier"), shader_multiplier(shape));
+            self.context.uniform1f(self.line_program.uniform("u_multiplier_frac"), shape.mul_frac);
+        }
+        if (stale.view) {
+            self.context.uniform_matrix3fv_with_f32_array(self.line_program.uniform("u_view"), false, &camera.view_matrix());
+        }
+        self.context.draw_arrays(WebGl2RenderingContext::LINES, 0, (shape.points * 2) as i32);
+    }
+
+    fn draw_rects(&mut self, shape: &Shape, camera: &Camera) {
+        self.context.use_program(Some(&self.rect_program.program));
+        let stale: Dirty = std::mem::take(&mut self.rect_stale);
+        if (stale.colors) {
+            let (red, green, blue): (f32, f32, f32) = shape.color.to_f32();
+            self.context.uniform3f(self.rect_program.uniform("u_color"), red, green, blue);
+        }
+        if (stale.figure) {
+            self.context.uniform1i(self.rect_program.uniform("u_points"), shape.points as i32);
+            self.context.uniform1f(self.rect_program.uniform("u_rotation"), shape.rotation);
+            self.context.uniform1i(self.rect_program.uniform("u_multiplier"), shader_multiplier(shape));
+            self.context.uniform1f(self.rect_program.uniform("u_multiplier_frac"), shape.mul_frac);
+            self.context.uniform1f(self.rect_program.uniform("u_rectw"), shape.rect_width);
+        }
+        if (stale.view) {
+            self.context.uniform_matrix3fv_with_f32_array(self.rect_program.uniform("u_view"), false, &camera.view_matrix());
+        }
+        if (stale.surface) {
+            self.context.uniform2f(self.rect_program.uniform("u_dimensions"), camera.dimensions.x, camera.dimensions.y);
+        }
+        self.context.draw_arrays(WebGl2RenderingContext::TRIANGLES, 0, (shape.points * 6) as i32);
+    }
+}
+
+impl Program {
+    fn new(gl: &WebGl2RenderingContext, name: &'static str, vertex: &WebGlShader, fragment: &WebGlShader, uniforms: &[&'static str]) -> Result<Program, ModmulError> {
+        let program: WebGlProgram = link_program(gl, name, vertex, fragment)?;
+        let mut locations: Vec<(&'static str, WebGlUniformLocation)> = Vec::new();
+        for uniform in uniforms {
+            let location: WebGlUniformLocation = gl.get_uniform_location(&program, uniform)
+                .ok_or(ModmulError::MissingUniform {program: name, uniform})?;
+            locations.push((uniform, location));
+        }
+        return Ok(Program {
+            program,
+            uniforms: locations
+        });
+    }
+
+    // Location of `name`, which `new` made sure exists
+    fn uniform(&self, name: &str) -> Option<&WebGlUniformLocation> {
+        return self.uniforms.iter().find(|(n, _)| *n == name).map(|(_, location)| location);
+    }
+}
+
+impl ViewVertices {
+    fn new(gl: &WebGl2RenderingContext) -> Result<ViewVertices, ModmulError> {
+        return Ok(ViewVertices {
+            buffer: gl.create_buffer().ok_or(ModmulError::CreateFailed("buffer"))?,
+            count: 0
+        });
+    }
+
+    // Replace the contents with `vertices`, pairs of view coordinates
+    fn upload(&mut self, gl: &WebGl2RenderingContext, vertices: &[f32]) {
+        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&self.buffer));
+        gl.buffer_data_with_array_buffer_view(WebGl2RenderingContext::ARRAY_BUFFER, &js_sys::Float32Array::from(vertices), WebGl2RenderingContext::DYNAMIC_DRAW);
+        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, None);
+        self.count = (vertices.len() / 2) as i32;
+    }
+}
+
+impl Renderer for WebGlRenderer {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.context.viewport(0, 0, width as i32, height as i32);
+    }
+
+    fn invalidate(&mut self, dirty: Dirty) {
+        for stale in [&mut self.point_stale, &mut self.line_stale, &mut self.rect_stale, &mut self.outline_stale, &mut self.deep_stale] {
+            stale.merge(dirty);
+        }
+    }
+
+    fn set_viewport(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        // GL counts rows from the bottom
+        let bottom: i32 = self.height as i32 - (y + height) as i32;
+        self.context.viewport(x as i32, bottom, width as i32, height as i32);
+        self.context.scissor(x as i32, bottom, width as i32, height as i32);
+        self.context.enable(WebGl2RenderingContext::SCISSOR_TEST);
+        self.forget_view();
+    }
+
+    fn reset_viewport(&mut self) {
+        self.context.viewport(0, 0, self.width as i32, self.height as i32);
+        self.context.disable(WebGl2RenderingContext::SCISSOR_TEST);
+        self.forget_view();
+    }
+
+    fn clear(&mut self, color: &Color) {
+        let (r, g, b): (f32, f32, f32) = color.to_f32();
+        self.context.clear_color(r, g, b, 1.0);
+        self.context.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
+    }
+
+    fn draw_outline(&mut self, shape: &Shape, camera: &Camera, bg: &Color) {
+        if (camera.is_deep()) {
+            // Only a sliver of the ring is on screen, as a strip along the arc
+            if (self.deep_stale.buffers) {
+                self.deep_outline.upload(&self.context, &deep_outline_vertices(shape, camera));
+            }
+            self.draw_view_vertices(WebGl2RenderingContext::TRIANGLE_STRIP, &self.deep_outline, camera, &shape.color, 1.0);
+            return;
+        }
+
+        self.context.use_program(Some(&self.outline_program.program));
+        let stale: Dirty = std::mem::take(&mut self.outline_stale);
+
+        // Both passes set their own color and radius
+        let (fg_red, fg_green, fg_blue): (f32, f32, f32) = shape.color.to_f32();
+        self.context.uniform3f(self.outline_program.uniform("u_color"), fg_red, fg_green, fg_blue);
+
+        if (stale.figure) {
+            self.context.uniform1f(self.outline_program.uniform("u_segments"), shape.outline_segments as f32);
+        }
+        if (stale.view) {
+            self.context.uniform_matrix3fv_with_f32_array(self.outline_program.uniform("u_view"), false, &camera.view_matrix());
+        }
+
+        // The ring width is in view units, convert it to world units
+        let half_width: f32 = shape.outline_width / 2.0 / camera.scale() as f32;
+        self.context.uniform1f(self.outline_program.uniform("u_radius"), 1.0 + half_width);
+        self.context.draw_arrays(WebGl2RenderingContext::TRIANGLE_FAN, 0, shape.outline_segments + 2);
+
+        // Punch out the inside with the background color to leave a ring
+        let (bg_red, bg_green, bg_blue): (f32, f32, f32) = bg.to_f32();
+        self.context.uniform3f(self.outline_program.uniform("u_color"), bg_red, bg_green, bg_blue);
+
+        self.context.uniform1f(self.outline_program.uniform("u_radius"), 1.0 - half_width);
+        self.context.draw_arrays(WebGl2RenderingContext::TRIANGLE_FAN, 0, shape.outline_segments + 2);
+    }
+
+    fn draw_chords(&mut self, shape: &Shape, camera: &Camera) {
+        if (camera.is_deep()) {
+            self.draw_deep_chords(shape, camera);
+        } else if (shape.use_rects_instead_of_lines) {
+            self.draw_rects(shape, camera);
+        } else {
+            self.draw_lines(shape, camera);
+        }
+    }
+
+    fn draw_points(&mut self, shape: &Shape, camera: &Camera) {
+        if (camera.is_deep()) {
+            if (self.deep_stale.buffers) {
+                self.deep_points.upload(&self.context, &deep_point_vertices(shape, camera));
+            }
+            self.draw_view_vertices(WebGl2RenderingContext::POINTS, &self.deep_points, camera, &shape.color, shape.point_size);
+
+            // Points are drawn last, all deep buffers are current now
+            self.deep_stale = Dirty::default();
+            return;
+        }
+
+        self.context.use_program(Some(&self.point_program.program));
+        let stale: Dirty = std::mem::take(&mut self.point_stale);
+        if (stale.colors) {
+            let (red, green, blue): (f32, f32, f32) = shape.color.to_f32();
+            self.context.uniform3f(self.point_program.uniform("u_color"), red, green, blue);
+        }
+        if (stale.figure) {
+            self.context.uniform1f(self.point_program.uniform("u_points"), shape.points as f32);
+            self.context.uniform1f(self.point_program.uniform("u_rotation"), shape.rotation);
+            self.context.uniform1f(self.point_program.uniform("u_point_size"), shape.point_size);
+        }
+        if (stale.view) {
+            self.context.uniform_matrix3fv_with_f32_array(self.point_program.uniform("u_view"), false, &camera.view_matrix());
+        }
+        self.context.draw_arrays(WebGl2RenderingContext::POINTS, 0, shape.points as i32);
+    }
+
+    fn draw_polygon(&mut self, points: &[Point], camera: &Camera, color: &Color) {
+        let vertices: Vec<f32> = points.iter().flat_map(|p: &Point| [p.x, p.y]).collect();
+        self.overlay.upload(&self.context, &vertices);
+        self.draw_view_vertices(WebGl2RenderingContext::LINE_LOOP, &self.overlay, camera, color, 1.0);
+    }
+
+    fn draw_segments(&mut self, segments: &[(Point, Point)], camera: &Camera, color: &Color) {
+        let vertices: Vec<f32> = segments.iter().flat_map(|(a, b): &(Point, Point)| [a.x, a.y, b.x, b.y]).collect();
+        self.overlay.upload(&self.context, &vertices);
+        self.draw_view_vertices(WebGl2RenderingContext::LINES, &self.overlay, camera, color, 1.0);
+    }
+
+    fn draw_text(&mut self, text: &str, x: f32, y: f32, size: f32, color: &Color) {
+        if (text.is_empty() || self.width == 0 || self.height == 0) {
+            return;
+        }
+
+        let Some((bitmap, tex_w, tex_h)) = rasterize_text(&self.text_canvas, text, size) else {
+            return;
+        };
+        self.context.active_texture(WebGl2RenderingContext::TEXTURE0);
+        self.context.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&self.text_texture));
+        let uploaded = self.context.tex_image_2d_with_u32_and_u32_and_image_bitmap(
+            WebGl2RenderingContext::TEXTURE_2D,
+            0,
+            WebGl2RenderingContext::RGBA as i32,
+            WebGl2RenderingContext::RGBA,
+            WebGl2RenderingContext::UNSIGNED_BYTE,
+            &bitmap
+        );
+        bitmap.close();
+        if (uploaded.is_err()) {
+            return;
+        }
+
+        let (red, green, blue): (f32, f32, f32) = color.to_f32();
+        self.context.use_program(Some(&self.text_program.program));
+        self.context.uniform3f(self.text_program.uniform("u_color"), red, green, blue);
+        self.context.uniform1i(self.text_program.uniform("u_texture"), 0);
+        self.context.uniform4f(self.text_program.uniform("u_rect"), x, y, tex_w as f32, tex_h as f32);
+        self.context.uniform2f(self.text_program.uniform("u_dimensions"), self.width as f32, self.height as f32);
+
+        self.context.enable(WebGl2RenderingContext::BLEND);
+        self.context.blend_func(WebGl2RenderingContext::SRC_ALPHA, WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA);
+        self.context.draw_arrays(WebGl2RenderingContext::TRIANGLE_STRIP, 0, 4);
+        self.context.disable(WebGl2RenderingContext::BLEND);
+    }
+}
+
+// Helper functions
+
+// Only k mod n shapes the figure, and unlike k itself it always fits the
+// shaders' int, see limits.rs
+pub(crate) fn shader_multiplier(shape: &Shape) -> i32 {
+    return shape.mul.checked_rem(shape.points).unwrap_or(0) as i32;
+}
+
+// Strip along the part of the ring on the surface at deep zoom, in view
+// coordinates
+pub(crate) fn deep_outline_vertices(shape: &Shape, camera: &Camera) -> Vec<f32> {
+    let half_width: f32 = shape.outline_width / 2.0;
+    let arc: Vec<Point> = geometry::visible_arc(camera, shape.outline_width, DEEP_OUTLINE_SEGMENTS);
+    let mut vertices: Vec<f32> = Vec::new();
+    for i in 0..arc.len() {
+        let prev: Point = arc[i.saturating_sub(1)];
+        let next: Point = arc[(i + 1).min(arc.len() - 1)];
+        let (nx, ny): (f32, f32) = unit_normal(prev, next);
+        vertices.extend_from_slice(&[
+            arc[i].x + nx * half_width, arc[i].y + ny * half_width,
+            arc[i].x - nx * half_width, arc[i].y - ny * half_width
+        ]);
+    }
+    return vertices;
+}
+
+// Chords on the surface at deep zoom, as triangles or lines
+pub(crate) fn deep_chord_vertices(shape: &Shape, camera: &Camera) -> Vec<f32> {
+    let mut vertices: Vec<f32> = Vec::new();
+    if (shape.use_rects_instead_of_lines) {
+        for (a, b) in geometry::visible_chords(shape, camera, shape.rect_width) {
+            push_segment_quad(&mut vertices, a, b, shape.rect_width);
+        }
+    } else {
+        for (a, b) in geometry::visible_chords(shape, camera, 0.0) {
+            vertices.extend_from_slice(&[a.x, a.y, b.x, b.y]);
+        }
+    }
+    return vertices;
+}
+
+pub(crate) fn deep_point_vertices(shape: &Shape, camera: &Camera) -> Vec<f32> {
+    let margin: f32 = shape.point_size / camera.length_to_pixels(1.0);
+    let mut vertices: Vec<f32> = Vec::new();
+    for p in geometry::visible_points(shape, camera, margin) {
+        vertices.extend_from_slice(&[p.x, p.y]);
+    }
+    return vertices;
+}
+
+// White `text` at `size` pixels on a transparent bitmap just big enough for
+// it, with the bitmap's width and height. None if the browser can't draw it.
+pub(crate) fn rasterize_text(canvas: &OffscreenCanvas, text: &str, size: f32) -> Option<(ImageBitmap, u32, u32)> {
+    let ctx: OffscreenCanvasRenderingContext2d = canvas.get_context("2d").ok().flatten()?
+        .dyn_into::<OffscreenCanvasRenderingContext2d>().ok()?;
+
+    // Size the 2D canvas to fit the text, which also clears it
+    let font: String = format!("{}px \"Noto Sans\", sans-serif", size);
+    ctx.set_font(&font);
+    let text_w: f64 = ctx.measure_text(text).ok()?.width();
+    let tex_w: u32 = (text_w.ceil() as u32).max(1);
+    let tex_h: u32 = ((size * 1.4).ceil() as u32).max(1);
+    canvas.set_width(tex_w);
+    canvas.set_height(tex_h);
+
+    // Resizing resets the context state, so the font has to be set again
+    ctx.set_font(&font);
+    ctx.set_text_baseline("top");
+    ctx.set_fill_style_str("#ffffff");
+    ctx.fill_text(text, 0.0, (size * 0.1) as f64).ok()?;
+
+    let bitmap: ImageBitmap = canvas.transfer_to_image_bitmap().ok()?;
+    return Some((bitmap, tex_w, tex_h));
+}
+
+// Two triangles covering the segment a-b drawn `width` wide, the same
+// rectangle rect_shader.vert builds
+fn push_segment_quad(vertices: &mut Vec<f32>, a: Point, b: Point, width: f32) {
+    let (nx, ny): (f32, f32) = unit_normal(a, b);
+    let (ox, oy): (f32, f32) = (nx * width / 2.0, ny * width / 2.0);
+    vertices.extend_from_slice(&[
+        a.x + ox, a.y + oy, a.x - ox, a.y - oy, b.x + ox, b.y + oy,
+        b.x + ox, b.y + oy, a.x - ox, a.y - oy, b.x - ox, b.y - oy
+    ]);
+}
+
+// Unit vector perpendicular to the direction from a to b
+fn unit_normal(a: Point, b: Point) -> (f32, f32) {
+    let (dx, dy): (f32, f32) = (b.x - a.x, b.y - a.y);
+    let len: f32 = dx.hypot(dy);
+    if (len == 0.0) {
+        return (0.0, 1.0);
+    }
+    return (-dy / len, dx / len);
+}
+
+// `name` is the file to expand, see glsl.rs. Errors point at the lines of
+// the files the failing code came from.
+fn compile_shader(gl: &WebGl2RenderingContext, name: &'static str, shader_type: u32) -> Result<WebGlShader, ModmulError> {
+    let source: Rc<ShaderSource> = shader_source(name, &[])?;
+    let shader: WebGlShader = gl.create_shader(shader_type).ok_or(ModmulError::CreateFailed("shader"))?;
+    gl.shader_source(&shader, &source.text);
+    gl.compile_shader(&shader);
+
+    if (gl.get_shader_parameter(&shader, WebGl2RenderingContext::COMPILE_STATUS).as_bool().unwrap_or(false)) {
+        return Ok(shader);
+    } else {
+        return Err(ModmulError::ShaderCompile {shader: name, log: source.map_log(&gl.get_shader_info_log(&shader).unwrap_or_default())});
+    }
+}
+
+fn link_program(gl: &WebGl2RenderingContext, name: &'static str, vertex_shader: &WebGlShader, fragment_shader: &WebGlShader) -> Result<WebGlProgram, ModmulError> {
+    let program: WebGlProgram = gl.create_program().ok_or(ModmulError::CreateFailed("program"))?;
+    gl.attach_shader(&program, vertex_shader);
+    gl.attach_shader(&program, fragment_shader);
+    gl.link_program(&program);
+
+    if (gl.get_program_parameter(&program, WebGl2RenderingContext::LINK_STATUS).as_bool().unwrap_or(false)) {
+        Ok(program)
+    } else {
+        Err(ModmulError::Link {program: name, log: gl.get_program_info_log(&program).unwrap_or_default()})
+    }
+}
@@ -0,0 +1,105 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Worker side of a viewer running off the main thread, started by
+// WorkerCanvas in worker_canvas.js. The page transfers its canvas element's
+// OffscreenCanvas here, and the two talk with these messages:
+//
+// page -> worker
+//   {type: "init", canvas, layout}       canvas is the OffscreenCanvas, always first
+//   {type: "call", id, method, args}     any Canvas method, answered with "result"
+//   {type: "layout", layout}             the element was laid out again
+//   {type: "input", event}               copy of a pointer, wheel or key event
+//
+// worker -> page
+//   {type: "ready"} or {type: "error", error}      answer to "init"
+//   {type: "result", id, value} or {type: "result", id, error}
+//   {type: "change", field, value}       one per change event, see events.rs
+//
+// layout is {width, height, pixel_ratio, reduced_motion}, the element's size
+// in CSS pixels, devicePixelRatio and the reduced motion preference. Input
+// events carry the DOM property names, with client coordinates relative to
+// the element. Errors are plain objects with the name, message, code and
+// details of the ModmulError thrown, see error.rs.
+
+import init, {Canvas} from "./pkg/modmul_webgl.js"
+
+const ready = init();
+let canvas = null;
+
+self.addEventListener("message", async (e) => {
+    await ready;
+    const message = e.data;
+    switch (message.type) {
+        case "init":
+            try {
+                const layout = message.layout;
+                canvas = Canvas.from_offscreen(message.canvas, layout.width, layout.height, layout.pixel_ratio);
+                canvas.set_reduced_motion(layout.reduced_motion);
+                canvas.add_change_listener((change) => {
+                    self.postMessage({type: "change", field: change.field, value: change.value});
+                });
+                self.postMessage({type: "ready"});
+            } catch (error) {
+                self.postMessage({type: "error", error: to_plain_error(error)});
+            }
+            break;
+        case "call":
+            try {
+                const value = canvas[message.method](...message.args);
+                self.postMessage({type: "result", id: message.id, value: to_plain(value)});
+            } catch (error) {
+                self.postMessage({type: "result", id: message.id, error: to_plain_error(error)});
+            }
+            break;
+        case "layout":
+            canvas.set_layout(message.layout.width, message.layout.height, message.layout.pixel_ratio);
+            canvas.set_reduced_motion(message.layout.reduced_motion);
+            break;
+        case "input":
+            canvas.forward_input(message.event);
+            break;
+    }
+});
+
+// Results that are Rust objects can't be posted, so they go over as plain
+// objects with the same getters
+function to_plain(value) {
+    if (Array.isArray(value)) {
+        return value.map(to_plain);
+    }
+    if (value === null || typeof value !== "object" || !("__wbg_ptr" in value)) {
+        return value;
+    }
+    const out = {};
+    const getters = Object.getOwnPropertyDescriptors(Object.getPrototypeOf(value));
+    for (const [name, descriptor] of Object.entries(getters)) {
+        if (descriptor.get) {
+            out[name] = to_plain(descriptor.get.call(value));
+        }
+    }
+    value.free();
+    return out;
+}
+
+// Errors lose their own properties when posted, so they go over as plain
+// objects too
+function to_plain_error(error) {
+    if (!(error instanceof Error)) {
+        return {name: "Error", message: String(error)};
+    }
+    return {...error, name: error.name, message: error.message};
+}
@@ -0,0 +1,252 @@
+/*
+This file is part of Modular Multiplication WebGL.
+
+Modular Multiplication WebGL is free software: you can redistribute it
+and/or modify it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Modular Multiplication WebGL is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with Modular
+Multiplication WebGL. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Page side of a viewer running in a worker, so that drawing and the work
+// behind it never hold up the page. Every Canvas method can be called on a
+// WorkerCanvas under the same name, and returns a promise of its result:
+//
+//     const canvas = await WorkerCanvas.create(document.querySelector("canvas"));
+//     await canvas.set_points(2000000);
+//     const hit = await canvas.pick(e.clientX, e.clientY);
+//
+// Change listeners run on the page and `state` always holds the latest value
+// of every field they were told about. The messages are listed in worker.js.
+//
+// Input is listened to here and posted on. Box zoom is not available, and
+// only keys with a binding have their default action prevented.
+
+// Methods taking client coordinates, and where in their arguments the x of
+// each coordinate pair is. The worker sees coordinates relative to the element.
+const CLIENT_POINTS = {
+    pick: [0],
+    gallery_cell_at: [0],
+    is_over_minimap: [0],
+    zoom_to_box: [0, 2]
+};
+
+const INPUT_EVENTS = ["pointerdown", "pointermove", "pointerup", "pointercancel", "wheel", "contextmenu"];
+
+// Methods that change which keys are bound
+const KEY_METHODS = ["bind_key", "reset_key_bindings"];
+
+// Viewer taking the keys, like the one in keymap.rs
+let key_target = null;
+
+export class WorkerCanvas {
+    // Viewer for `element`, resolves once the worker has set it up
+    static async create(element, worker_url = new URL("./worker.js", import.meta.url)) {
+        const canvas = new WorkerCanvas(element, new Worker(worker_url, {type: "module"}));
+        await canvas.#start();
+        canvas.#listen();
+
+        // Anything not defined here is a Canvas method run in the worker.
+        // `then` is left alone, or awaiting the viewer would call it.
+        return new Proxy(canvas, {
+            get(target, name) {
+                if (name in target || typeof name !== "string" || name === "then") {
+                    const value = Reflect.get(target, name);
+                    return typeof value === "function" ? value.bind(target) : value;
+                }
+                return (...args) => target.call(name, args);
+            }
+        });
+    }
+
+    element;
+    worker;
+    state = {}; // latest value of each field, by name
+    #calls = new Map(); // id -> {resolve, reject}
+    #next_call = 0;
+    #listeners = new Map(); // id -> callback
+    #next_listener = 0;
+    #bound_keys = new Set();
+    #cleanup = [];
+    #started = null; // {resolve, reject} until the worker is ready
+
+    constructor(element, worker) {
+        this.element = element;
+        this.worker = worker;
+    }
+
+    // Run `method` with `args` in the worker
+    call(method, args = []) {
+        for (const index of CLIENT_POINTS[method] ?? []) {
+            const rect = this.element.getBoundingClientRect();
+            args[index] -= rect.left;
+            args[index + 1] -= rect.top;
+        }
+        const id = this.#next_call++;
+        const result = new Promise((resolve, reject) => this.#calls.set(id, {resolve, reject}));
+        this.worker.postMessage({type: "call", id, method, args});
+        if (KEY_METHODS.includes(method)) {
+            result.then(() => this.#update_bound_keys());
+        }
+        return result;
+    }
+
+    add_change_listener(callback) {
+        const id = this.#next_listener++;
+        this.#listeners.set(id, callback);
+        return id;
+    }
+
+    remove_change_listener(id) {
+        return this.#listeners.delete(id);
+    }
+
+    // Stop the worker and the listeners
+    free() {
+        for (const cleanup of this.#cleanup) {
+            cleanup();
+        }
+        this.worker.terminate();
+        if (key_target === this) {
+            key_target = null;
+        }
+    }
+
+    #start() {
+        this.worker.addEventListener("message", (e) => this.#on_message(e.data));
+        const offscreen = this.element.transferControlToOffscreen();
+        this.worker.postMessage({type: "init", canvas: offscreen, layout: this.#layout()}, [offscreen]);
+        return new Promise((resolve, reject) => {
+            this.#started = {resolve, reject};
+        });
+    }
+
+    #on_message(message) {
+        switch (message.type) {
+            case "ready":
+                this.#started.resolve();
+                break;
+            case "error":
+                this.worker.terminate();
+                this.#started.reject(from_plain_error(message.error));
+                break;
+            case "result": {
+                const call = this.#calls.get(message.id);
+                this.#calls.delete(message.id);
+                if ("error" in message) {
+                    call.reject(from_plain_error(message.error));
+                } else {
+                    call.resolve(message.value);
+                }
+                break;
+            }
+            case "change":
+                this.state[message.field] = message.value;
+                for (const callback of this.#listeners.values()) {
+                    callback({field: message.field, value: message.value});
+                }
+                break;
+        }
+    }
+
+    // Forward input and layout, once the worker is ready for them
+    #listen() {
+        this.element.style.touchAction = "none";
+        for (const name of INPUT_EVENTS) {
+            this.#listen_to(this.element, name, (e) => {
+                if (name === "pointerdown") {
+                    key_target = this;
+                    this.element.setPointerCapture(e.pointerId);
+                }
+                if (name !== "pointermove") {
+                    e.preventDefault();
+                }
+                this.#post_input(e);
+            });
+        }
+        this.#listen_to(document, "keydown", (e) => {
+            if (key_target !== this || e.defaultPrevented || e.ctrlKey || e.altKey || e.metaKey || takes_text(document.activeElement)) {
+                return;
+            }
+            if (this.#bound_keys.has([...e.key].length === 1 ? e.key.toLowerCase() : e.key)) {
+                e.preventDefault();
+            }
+            this.#post_input(e);
+        });
+
+        const observer = new ResizeObserver(() => this.#post_layout());
+        observer.observe(this.element);
+        this.#cleanup.push(() => observer.disconnect());
+        const motion = matchMedia("(prefers-reduced-motion: reduce)");
+        this.#listen_to(motion, "change", () => this.#post_layout());
+
+        if (key_target === null) {
+            key_target = this;
+        }
+        this.#update_bound_keys();
+    }
+
+    #listen_to(target, name, listener) {
+        target.addEventListener(name, listener, {passive: false});
+        this.#cleanup.push(() => target.removeEventListener(name, listener));
+    }
+
+    #post_input(e) {
+        const rect = this.element.getBoundingClientRect();
+        this.worker.postMessage({type: "input", event: {
+            type: e.type,
+            pointerId: e.pointerId,
+            button: e.button,
+            pointerType: e.pointerType,
+            clientX: e.clientX - rect.left,
+            clientY: e.clientY - rect.top,
+            deltaX: e.deltaX,
+            deltaY: e.deltaY,
+            deltaMode: e.deltaMode,
+            key: e.key,
+            shiftKey: e.shiftKey,
+            ctrlKey: e.ctrlKey,
+            altKey: e.altKey,
+            metaKey: e.metaKey
+        }});
+    }
+
+    #post_layout() {
+        this.worker.postMessage({type: "layout", layout: this.#layout()});
+    }
+
+    #layout() {
+        const rect = this.element.getBoundingClientRect();
+        return {
+            width: rect.width,
+            height: rect.height,
+            pixel_ratio: devicePixelRatio,
+            reduced_motion: matchMedia("(prefers-reduced-motion: reduce)").matches
+        };
+    }
+
+    async #update_bound_keys() {
+        const bindings = await this.call("get_key_bindings");
+        this.#bound_keys = new Set(bindings.map((binding) => binding.key));
+    }
+}
+
+// Error thrown in the worker, with its code and details back as properties
+function from_plain_error(plain) {
+    return Object.assign(new Error(plain.message), plain);
+}
+
+// Same test as `takes_text` in keymap.rs
+function takes_text(element) {
+    if (element === null) {
+        return false;
+    }
+    const tag = element.tagName;
+    return tag === "INPUT" || tag === "TEXTAREA" || tag === "SELECT" || element.isContentEditable;
+}